@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX SeenItemsKey;
+DROP TABLE SeenItems;
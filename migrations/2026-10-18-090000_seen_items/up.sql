@@ -0,0 +1,12 @@
+-- Your SQL goes here
+CREATE TABLE SeenItems(
+    id INTEGER PRIMARY KEY NOT NULL,
+    user_id BIGINT NOT NULL,
+    delivery_method INTEGER NOT NULL,
+    site_id INTEGER NOT NULL,
+    ad_id BIGINT NOT NULL,
+    seen_at BIGINT NOT NULL
+);
+
+CREATE UNIQUE INDEX SeenItemsKey
+ON SeenItems(user_id, delivery_method, site_id, ad_id);
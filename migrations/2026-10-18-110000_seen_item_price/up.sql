@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE SeenItems
+ADD price BIGINT NOT NULL DEFAULT 0;
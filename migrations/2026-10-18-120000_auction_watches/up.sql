@@ -0,0 +1,15 @@
+-- Your SQL goes here
+CREATE TABLE AuctionWatches(
+    id INTEGER PRIMARY KEY NOT NULL,
+    user_id BIGINT NOT NULL,
+    delivery_method INTEGER NOT NULL,
+    item_id BIGINT NOT NULL,
+    title TEXT NOT NULL,
+    url TEXT NOT NULL,
+    closing_time BIGINT NOT NULL,
+    remind_before BIGINT NOT NULL,
+    reminded BOOLEAN NOT NULL DEFAULT 0
+);
+
+CREATE UNIQUE INDEX AuctionWatchesKey
+ON AuctionWatches(user_id, delivery_method, item_id);
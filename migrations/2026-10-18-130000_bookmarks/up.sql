@@ -0,0 +1,13 @@
+-- Your SQL goes here
+CREATE TABLE Bookmarks(
+    id INTEGER PRIMARY KEY NOT NULL,
+    user_id BIGINT NOT NULL,
+    delivery_method INTEGER NOT NULL,
+    site_id INTEGER NOT NULL,
+    ad_id BIGINT NOT NULL,
+    title TEXT NOT NULL,
+    url TEXT NOT NULL
+);
+
+CREATE UNIQUE INDEX BookmarksKey
+ON Bookmarks(user_id, delivery_method, site_id, ad_id);
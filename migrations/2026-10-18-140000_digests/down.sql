@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX DigestItemsVahti;
+DROP TABLE DigestItems;
+
+ALTER TABLE Vahdit
+DROP COLUMN last_digest;
+
+ALTER TABLE Vahdit
+DROP COLUMN digest;
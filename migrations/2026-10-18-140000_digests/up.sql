@@ -0,0 +1,16 @@
+-- Your SQL goes here
+ALTER TABLE Vahdit
+ADD digest TEXT;
+
+ALTER TABLE Vahdit
+ADD last_digest BIGINT NOT NULL DEFAULT 0;
+
+CREATE TABLE DigestItems(
+    id INTEGER PRIMARY KEY NOT NULL,
+    vahti_id INTEGER NOT NULL,
+    item TEXT NOT NULL,
+    added_at BIGINT NOT NULL
+);
+
+CREATE INDEX DigestItemsVahti
+ON DigestItems(vahti_id);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE HeldItems;
+DROP INDEX UserSettingsKey;
+DROP TABLE UserSettings;
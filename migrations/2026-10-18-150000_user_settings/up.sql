@@ -0,0 +1,20 @@
+-- Your SQL goes here
+CREATE TABLE UserSettings(
+    id INTEGER PRIMARY KEY NOT NULL,
+    user_id BIGINT NOT NULL,
+    delivery_method INTEGER NOT NULL,
+    timezone TEXT,
+    quiet_start TEXT,
+    quiet_end TEXT
+);
+
+CREATE UNIQUE INDEX UserSettingsKey
+ON UserSettings(user_id, delivery_method);
+
+CREATE TABLE HeldItems(
+    id INTEGER PRIMARY KEY NOT NULL,
+    user_id BIGINT NOT NULL,
+    delivery_method INTEGER NOT NULL,
+    item TEXT NOT NULL,
+    added_at BIGINT NOT NULL
+);
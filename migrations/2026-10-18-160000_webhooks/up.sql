@@ -0,0 +1,8 @@
+-- Your SQL goes here
+CREATE TABLE Webhooks(
+    id INTEGER PRIMARY KEY NOT NULL,
+    user_id BIGINT NOT NULL,
+    delivery_method INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    secret TEXT
+);
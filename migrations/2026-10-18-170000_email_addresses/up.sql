@@ -0,0 +1,10 @@
+-- Your SQL goes here
+CREATE TABLE EmailAddresses(
+    id INTEGER PRIMARY KEY NOT NULL,
+    user_id BIGINT NOT NULL,
+    delivery_method INTEGER NOT NULL,
+    address TEXT NOT NULL,
+    verification_code TEXT NOT NULL,
+    verified BOOLEAN NOT NULL DEFAULT 0,
+    unsubscribe_token TEXT NOT NULL UNIQUE
+);
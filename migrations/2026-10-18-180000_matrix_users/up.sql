@@ -0,0 +1,6 @@
+-- Your SQL goes here
+CREATE TABLE MatrixUsers(
+    id INTEGER PRIMARY KEY NOT NULL,
+    matrix_id TEXT NOT NULL UNIQUE,
+    room_id TEXT NOT NULL
+);
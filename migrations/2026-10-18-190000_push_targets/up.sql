@@ -0,0 +1,10 @@
+-- Your SQL goes here
+CREATE TABLE PushTargets(
+    id INTEGER PRIMARY KEY NOT NULL,
+    user_id BIGINT NOT NULL,
+    delivery_method INTEGER NOT NULL,
+    service TEXT NOT NULL,
+    server TEXT NOT NULL,
+    topic TEXT,
+    token TEXT
+);
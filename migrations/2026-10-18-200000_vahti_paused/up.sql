@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE Vahdit
+ADD paused BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE SeenItems
+DROP COLUMN vahti_id;
+
+ALTER TABLE Vahdit
+DROP COLUMN last_error;
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+ALTER TABLE Vahdit
+ADD last_error TEXT;
+
+ALTER TABLE SeenItems
+ADD vahti_id INTEGER;
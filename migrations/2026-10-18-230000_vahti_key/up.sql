@@ -0,0 +1,8 @@
+-- Your SQL goes here
+DELETE FROM DigestItems
+WHERE vahti_id NOT IN (SELECT MIN(id) FROM Vahdit GROUP BY url, user_id, delivery_method);
+
+DELETE FROM Vahdit
+WHERE id NOT IN (SELECT MIN(id) FROM Vahdit GROUP BY url, user_id, delivery_method);
+
+CREATE UNIQUE INDEX VahditKey ON Vahdit(url, user_id, delivery_method);
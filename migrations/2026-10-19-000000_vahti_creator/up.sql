@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE Vahdit
+ADD creator_id BIGINT;
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE VahtiDestinations;
+DROP TABLE PairingCodes;
+DROP TABLE Endpoints;
@@ -0,0 +1,23 @@
+-- Your SQL goes here
+CREATE TABLE Endpoints(
+    id INTEGER PRIMARY KEY NOT NULL,
+    identity_id INTEGER NOT NULL,
+    user_id BIGINT NOT NULL,
+    delivery_method INTEGER NOT NULL
+);
+
+CREATE UNIQUE INDEX EndpointsKey ON Endpoints(user_id, delivery_method);
+
+CREATE TABLE PairingCodes(
+    code TEXT PRIMARY KEY NOT NULL,
+    user_id BIGINT NOT NULL,
+    delivery_method INTEGER NOT NULL,
+    expires_at BIGINT NOT NULL
+);
+
+CREATE TABLE VahtiDestinations(
+    id INTEGER PRIMARY KEY NOT NULL,
+    vahti_id INTEGER NOT NULL,
+    user_id BIGINT NOT NULL,
+    delivery_method INTEGER NOT NULL
+);
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+CREATE TABLE Outbox (
+    id INTEGER PRIMARY KEY NOT NULL,
+    user_id BIGINT NOT NULL,
+    delivery_method INTEGER NOT NULL,
+    items TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt BIGINT NOT NULL,
+    last_error TEXT,
+    failed BOOLEAN NOT NULL DEFAULT 0
+);
+CREATE INDEX OutboxDue ON Outbox(failed, next_attempt);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE DeliveryFailures;
+ALTER TABLE Vahdit
+DROP COLUMN disabled;
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+ALTER TABLE Vahdit
+ADD disabled BOOLEAN NOT NULL DEFAULT 0;
+CREATE TABLE DeliveryFailures (
+    user_id BIGINT NOT NULL,
+    delivery_method INTEGER NOT NULL,
+    failures INTEGER NOT NULL,
+    PRIMARY KEY (user_id, delivery_method)
+);
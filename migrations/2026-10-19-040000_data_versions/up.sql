@@ -0,0 +1,5 @@
+-- Your SQL goes here
+CREATE TABLE DataVersions (
+    name TEXT PRIMARY KEY NOT NULL,
+    version INTEGER NOT NULL
+);
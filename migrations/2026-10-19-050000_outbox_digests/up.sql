@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE Outbox
+ADD digest BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE Blacklists
+DROP COLUMN delivery_method;
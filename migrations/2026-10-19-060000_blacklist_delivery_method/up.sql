@@ -0,0 +1,11 @@
+-- Your SQL goes here
+ALTER TABLE Blacklists
+ADD delivery_method Integer NOT NULL DEFAULT 1;
+
+-- NOTE: The blacklists were only keyed on the user, so they're given the delivery method
+-- of the Vahtis of the same user
+UPDATE Blacklists
+SET delivery_method = COALESCE(
+    (SELECT Vahdit.delivery_method FROM Vahdit WHERE Vahdit.user_id = Blacklists.user_id LIMIT 1),
+    1
+);
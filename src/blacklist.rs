@@ -0,0 +1,98 @@
+use crate::database::Database;
+use crate::error::Error;
+
+/// Parses the site and the id of the seller from a seller url
+pub fn seller_from_url(url: &str) -> Option<(i32, i32)> {
+    #[cfg(feature = "tori")]
+    if url.contains("tori.fi/") {
+        return crate::tori::api::seller_id_from_url(url).map(|id| (crate::tori::ID, id));
+    }
+    #[cfg(feature = "huutonet")]
+    if url.contains("huuto.net/") {
+        return crate::huutonet::api::seller_id_from_url(url).map(|id| (crate::huutonet::ID, id));
+    }
+    None
+}
+
+pub fn seller_url(sellerid: i32, siteid: i32) -> String {
+    match siteid {
+        #[cfg(feature = "tori")]
+        crate::tori::ID => crate::tori::seller::seller_url(sellerid),
+        #[cfg(feature = "huutonet")]
+        crate::huutonet::ID => crate::huutonet::seller::seller_url(sellerid),
+        _ => sellerid.to_string(),
+    }
+}
+
+pub async fn block_seller(
+    db: Database,
+    userid: u64,
+    delivery_method: i32,
+    sellerid: i32,
+    siteid: i32,
+) -> Result<String, Error> {
+    if db
+        .fetch_user_blacklist(userid as i64, delivery_method)
+        .await?
+        .contains(&(sellerid, siteid))
+    {
+        return Ok(String::from("The seller is already blocked"));
+    }
+
+    db.add_seller_to_blacklist(userid as i64, delivery_method, sellerid, siteid)
+        .await?;
+    Ok(String::from("Seller blocked"))
+}
+
+pub async fn unblock_seller(
+    db: Database,
+    userid: u64,
+    delivery_method: i32,
+    sellerid: i32,
+    siteid: i32,
+) -> Result<String, Error> {
+    match db
+        .remove_seller_from_blacklist(userid as i64, delivery_method, sellerid, siteid)
+        .await?
+    {
+        0 => Ok(String::from("The seller is not blocked")),
+        _ => Ok(String::from("Seller unblocked")),
+    }
+}
+
+/// Lists the seller urls of the blocked sellers
+pub async fn list_blocked_sellers(
+    db: Database,
+    userid: u64,
+    delivery_method: i32,
+) -> Result<String, Error> {
+    let blacklist = db
+        .fetch_user_blacklist(userid as i64, delivery_method)
+        .await?;
+
+    if blacklist.is_empty() {
+        return Ok(String::from("You have no blocked sellers"));
+    }
+
+    Ok("Blocked sellers\n".to_owned()
+        + &blacklist
+            .into_iter()
+            .map(|(sellerid, siteid)| seller_url(sellerid, siteid))
+            .collect::<Vec<_>>()
+            .join("\n"))
+}
+
+/// Fetches the name of the seller, falling back to "Unknown Seller"
+pub async fn seller_name(sellerid: i32, siteid: i32) -> String {
+    match siteid {
+        #[cfg(feature = "tori")]
+        crate::tori::ID => crate::tori::seller::get_seller_name_from_id(sellerid)
+            .await
+            .unwrap_or(String::from("Unknown Seller")),
+        #[cfg(feature = "huutonet")]
+        crate::huutonet::ID => crate::huutonet::seller::get_seller_name_from_id(sellerid)
+            .await
+            .unwrap_or(String::from("Unknown Seller")),
+        _ => String::from("Unknown Seller"),
+    }
+}
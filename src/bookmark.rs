@@ -0,0 +1,148 @@
+use serde::Serialize;
+
+use crate::database::Database;
+use crate::delivery::{perform_notification, Notification};
+use crate::error::Error;
+use crate::models::NewBookmark;
+use crate::Torimies;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListingStatus {
+    Active,
+    /// The auction has ended, the item has most likely been sold
+    Closed,
+    /// The seller has marked the item sold
+    Sold,
+    /// The listing can no longer be found on the site
+    Removed,
+}
+
+impl ListingStatus {
+    /// A short description of the status for the users
+    pub fn describe(&self) -> &'static str {
+        match self {
+            ListingStatus::Active => "Ilmoitus on yhä voimassa",
+            ListingStatus::Closed => "Huutokauppa on päättynyt",
+            ListingStatus::Sold => "Ilmoitus on myyty",
+            ListingStatus::Removed => "Ilmoitus on poistunut",
+        }
+    }
+}
+
+/// The current state of a single listing
+#[derive(Debug, Clone, PartialEq)]
+pub struct Listing {
+    pub title: String,
+    pub url: String,
+    pub status: ListingStatus,
+}
+
+impl Listing {
+    pub fn removed() -> Self {
+        Self {
+            title: String::new(),
+            url: String::new(),
+            status: ListingStatus::Removed,
+        }
+    }
+}
+
+/// Parses the site and the id of the listing from an item url
+pub fn item_from_url(url: &str) -> Option<(i32, i64)> {
+    #[cfg(feature = "tori")]
+    if url.contains("tori.fi/") {
+        return crate::tori::api::item_id_from_url(url).map(|id| (crate::tori::ID, id));
+    }
+    #[cfg(feature = "huutonet")]
+    if url.contains("huuto.net/") {
+        return crate::huutonet::api::item_id_from_url(url).map(|id| (crate::huutonet::ID, id));
+    }
+    None
+}
+
+async fn fetch_listing(site_id: i32, ad_id: i64) -> Result<Listing, Error> {
+    match site_id {
+        #[cfg(feature = "tori")]
+        crate::tori::ID => crate::tori::api::fetch_listing(ad_id).await,
+        #[cfg(feature = "huutonet")]
+        crate::huutonet::ID => crate::huutonet::api::fetch_listing(ad_id).await,
+        i => panic!("Unsupported site_id {}", i),
+    }
+}
+
+/// Bookmarks a listing, the user is notified when the listing is removed or closed
+pub async fn bookmark_item(
+    db: Database,
+    site_id: i32,
+    ad_id: i64,
+    userid: u64,
+    delivery_method: i32,
+) -> Result<String, Error> {
+    let listing = fetch_listing(site_id, ad_id).await?;
+
+    if listing.status != ListingStatus::Active {
+        return Ok(String::from("The listing is no longer available"));
+    }
+
+    db.add_bookmark(NewBookmark {
+        user_id: userid as i64,
+        delivery_method,
+        site_id,
+        ad_id,
+        title: listing.title.clone(),
+        url: listing.url,
+    })
+    .await?;
+
+    Ok(format!(
+        "You will be notified when `{}` is no longer available",
+        listing.title
+    ))
+}
+
+impl Torimies {
+    pub async fn check_bookmarks(&self) -> Result<(), Error> {
+        let bookmarks = self.database.fetch_all_bookmarks().await?;
+        debug!("Checking {} bookmarks", bookmarks.len());
+
+        for b in bookmarks {
+            let listing = match fetch_listing(b.site_id, b.ad_id).await {
+                Ok(l) => l,
+                Err(e) => {
+                    error!("Failed to fetch listing {}: {}", b.ad_id, e);
+                    continue;
+                }
+            };
+
+            if listing.status == ListingStatus::Active {
+                continue;
+            }
+
+            let notification = Notification::ListingGone {
+                title: b.title,
+                url: b.url,
+                status: listing.status,
+            };
+
+            if let Err(e) = perform_notification(
+                self.delivery.clone(),
+                b.user_id as u64,
+                b.delivery_method,
+                notification,
+            )
+            .await
+            {
+                error!(
+                    "Failed to send a bookmark notification to {}: {}",
+                    b.user_id, e
+                );
+                continue;
+            }
+
+            self.database.remove_bookmark(b.id).await?;
+        }
+
+        Ok(())
+    }
+}
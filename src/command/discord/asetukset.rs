@@ -0,0 +1,61 @@
+use serenity::builder::{CreateCommand, CreateCommandOption};
+use serenity::client::Context;
+use serenity::model::application::{CommandInteraction, CommandOptionType};
+
+use super::extensions::ClientContextExt;
+use crate::settings::{parse_quiet_hours, parse_timezone, update_user_settings, UserSettings};
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("asetukset")
+        .description("Näytä tai muuta asetuksiasi")
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "aikavyohyke",
+            "Aikavyöhyke, esim. Europe/Helsinki (off palauttaa oletuksen)",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "hiljaiset_tunnit",
+            "Aika, jolloin ilmoituksia ei lähetetä, esim. 22:00-07:00 (off poistaa)",
+        ))
+}
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> String {
+    let mut timezone = None;
+    let mut quiet_hours = None;
+    for a in &command.data.options {
+        let value = a.value.as_str().unwrap().trim();
+        match a.name.as_str() {
+            "aikavyohyke" if value == "off" => timezone = Some(None),
+            "aikavyohyke" => match parse_timezone(value) {
+                Ok(tz) => timezone = Some(Some(tz)),
+                Err(e) => return e.to_string(),
+            },
+            "hiljaiset_tunnit" if value == "off" => quiet_hours = Some(None),
+            "hiljaiset_tunnit" => match parse_quiet_hours(value) {
+                Ok(q) => quiet_hours = Some(Some(q)),
+                Err(e) => return e.to_string(),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    let db = ctx.get_db().await.unwrap();
+    let userid = u64::from(command.user.id);
+
+    if timezone.is_none() && quiet_hours.is_none() {
+        return UserSettings::fetch(&db, userid as i64, crate::delivery::discord::ID)
+            .await
+            .to_string();
+    }
+
+    update_user_settings(
+        db,
+        userid,
+        crate::delivery::discord::ID,
+        timezone,
+        quiet_hours,
+    )
+    .await
+    .unwrap_or_else(|e| e.to_string())
+}
@@ -1,12 +1,15 @@
 use itertools::Itertools;
 use serenity::all::ComponentInteractionDataKind;
 use serenity::builder::{
-    CreateActionRow, CreateSelectMenu, CreateSelectMenuOption, EditInteractionResponse,
+    CreateActionRow, CreateInteractionResponseFollowup, CreateSelectMenu, CreateSelectMenuOption,
+    EditInteractionResponse,
 };
-use serenity::model::application::Interaction;
+use serenity::model::application::{ComponentInteraction, Interaction};
+use serenity::model::channel::Message;
 use serenity::prelude::*;
 
 use super::extensions::ClientContextExt;
+use crate::unreachable::welcome_back;
 
 pub fn menu_from_options(
     custom_id: &str,
@@ -25,6 +28,63 @@ pub fn menu_from_options(
     vec![CreateActionRow::SelectMenu(menu)]
 }
 
+/// The owner of the Vahtis behind the message the component belongs to,
+/// a guild channel or the user themself in DMs
+fn component_owner(button: &ComponentInteraction) -> Option<(u64, i32)> {
+    crate::delivery::discord::vahti_owner(
+        u64::from(button.user.id),
+        u64::from(button.channel_id),
+        button.guild_id.is_some(),
+        button.member.as_ref().and_then(|m| m.permissions),
+    )
+}
+
+/// Collects the linked sellers of the embeds as (name, "sellerid,siteid") pairs
+fn sellers_from_message(message: &Message) -> Vec<(String, String)> {
+    message
+        .embeds
+        .iter()
+        .map(|e| e.fields.iter().find(|f| f.name == "Myyjä"))
+        .filter_map(|f| f.map(|ff| ff.value.clone()))
+        .filter_map(|s| match s {
+            #[cfg(feature = "tori")]
+            _ if s.contains("https://www.tori.fi/li?&aid=") => Some((
+                s[1..s.find(']').unwrap()].to_string(),
+                format!(
+                    "{},{}",
+                    &s[s.rfind('=').unwrap() + 1..s.find(')').unwrap()],
+                    crate::tori::ID
+                ),
+            )),
+            #[cfg(feature = "huutonet")]
+            _ if s.contains("https://www.huuto.net/kayttaja/") => Some((
+                s[1..s.find(']').unwrap()].to_string(),
+                format!(
+                    "{},{}",
+                    &s[s.rfind('/').unwrap() + 1..s.find(')').unwrap()],
+                    crate::huutonet::ID
+                ),
+            )),
+            _ => None,
+        })
+        .unique()
+        .collect()
+}
+
+/// Collects the linked listings of the embeds as (title, url) pairs
+fn items_from_message(message: &Message) -> Vec<(String, String)> {
+    message
+        .embeds
+        .iter()
+        .filter_map(|e| e.description.clone())
+        .filter_map(|d| {
+            let title = d.get(1..d.rfind("](")?)?.to_string();
+            let url = d.get(d.rfind("](")? + 2..d.rfind(')')?)?.to_string();
+            Some((title, url))
+        })
+        .collect()
+}
+
 pub async fn handle_interaction(ctx: Context, interaction: Interaction) {
     match interaction {
         Interaction::Command(command) => {
@@ -33,7 +93,19 @@ pub async fn handle_interaction(ctx: Context, interaction: Interaction) {
             let content = match command.data.name.as_str() {
                 "vahti" => super::vahti::run(&ctx, &command).await,
                 "poistavahti" => super::poistavahti::run(&ctx, &command).await,
+                "vahdit" => super::vahdit::run(&ctx, &command).await,
+                "tauota" => super::tauota::run(&ctx, &command).await,
+                "jatka" => super::jatka::run(&ctx, &command).await,
+                "linkita" => super::linkita::run(&ctx, &command).await,
+                "torkku" => super::torkku::run(&ctx, &command).await,
                 "poistaesto" => super::poistaesto::run(&ctx, &command).await,
+                "asetukset" => super::asetukset::run(&ctx, &command).await,
+                #[cfg(feature = "webhook-delivery")]
+                "webhook" => super::webhook::run(&ctx, &command).await,
+                #[cfg(feature = "email-delivery")]
+                "sahkoposti" => super::sahkoposti::run(&ctx, &command).await,
+                #[cfg(feature = "push-delivery")]
+                "push" => super::push::run(&ctx, &command).await,
                 _ => unreachable!(),
             };
 
@@ -43,11 +115,48 @@ pub async fn handle_interaction(ctx: Context, interaction: Interaction) {
                     .await
                     .unwrap();
             }
+
+            let db = ctx.get_db().await.unwrap();
+            if let Some(welcome) = welcome_back(
+                &db,
+                u64::from(command.user.id),
+                crate::delivery::discord::ID,
+            )
+            .await
+            {
+                let _ = command
+                    .create_followup(
+                        &ctx.http,
+                        CreateInteractionResponseFollowup::new()
+                            .content(welcome)
+                            .ephemeral(true),
+                    )
+                    .await;
+            }
         }
         Interaction::Component(button) => {
-            if button.data.custom_id == "remove_vahti" {
+            if [
+                "remove_vahti",
+                "remove_vahti_menu",
+                "block_seller",
+                "block_seller_menu",
+            ]
+            .contains(&button.data.custom_id.as_str())
+                && component_owner(&button).is_none()
+            {
+                button.defer_ephemeral(&ctx.http).await.unwrap();
+                button
+                    .edit_response(
+                        &ctx.http,
+                        EditInteractionResponse::new()
+                            .content("Kanavan vahtien hallinta vaatii Hallitse kanavia -oikeuden"),
+                    )
+                    .await
+                    .unwrap();
+            } else if button.data.custom_id == "remove_vahti" {
                 button.defer_ephemeral(&ctx.http).await.unwrap();
                 let message = button.message.clone();
+                #[allow(clippy::useless_asref)]
                 let urls: Vec<_> = message
                     .embeds
                     .iter()
@@ -77,6 +186,7 @@ pub async fn handle_interaction(ctx: Context, interaction: Interaction) {
                 button.defer_ephemeral(&ctx.http).await.unwrap();
                 let message = button.message.clone();
 
+                #[allow(clippy::useless_asref)]
                 let urls: Vec<_> = message
                     .embeds
                     .iter()
@@ -85,34 +195,7 @@ pub async fn handle_interaction(ctx: Context, interaction: Interaction) {
 
                 assert!(!urls.is_empty(), "Cannot determine search url");
 
-                let sellers = message
-                    .embeds
-                    .iter()
-                    .map(|e| e.fields.iter().find(|f| f.name == "Myyjä"))
-                    .filter_map(|f| f.map(|ff| ff.value.clone()))
-                    .filter_map(|s| match s {
-                        #[cfg(feature = "tori")]
-                        _ if s.contains("https://www.tori.fi/li?&aid=") => Some((
-                            s[1..s.find(']').unwrap()].to_string(),
-                            format!(
-                                "{},{}",
-                                &s[s.rfind('=').unwrap() + 1..s.find(')').unwrap()],
-                                crate::tori::ID
-                            ),
-                        )),
-                        #[cfg(feature = "huutonet")]
-                        _ if s.contains("https://www.huuto.net/kayttaja/") => Some((
-                            s[1..s.find(']').unwrap()].to_string(),
-                            format!(
-                                "{},{}",
-                                &s[s.rfind('/').unwrap() + 1..s.find(')').unwrap()],
-                                crate::huutonet::ID
-                            ),
-                        )),
-                        _ => None,
-                    })
-                    .unique()
-                    .collect::<Vec<_>>();
+                let sellers = sellers_from_message(&message);
 
                 button
                     .edit_response(
@@ -123,7 +206,20 @@ pub async fn handle_interaction(ctx: Context, interaction: Interaction) {
                     )
                     .await
                     .unwrap();
-            } else if button.data.custom_id == "unblock_seller" {
+            } else if button.data.custom_id == "follow_seller" {
+                button.defer_ephemeral(&ctx.http).await.unwrap();
+                let sellers = sellers_from_message(&button.message);
+
+                button
+                    .edit_response(
+                        &ctx.http,
+                        EditInteractionResponse::new()
+                            .content("Valitse myyjä, jonka ilmoituksia haluat seurata")
+                            .components(menu_from_options("follow_seller_menu", sellers)),
+                    )
+                    .await
+                    .unwrap();
+            } else if button.data.custom_id == "follow_seller_menu" {
                 button.defer_ephemeral(&ctx.http).await.unwrap();
                 let db = ctx.get_db().await.unwrap();
                 let userid = u64::from(button.user.id);
@@ -136,9 +232,88 @@ pub async fn handle_interaction(ctx: Context, interaction: Interaction) {
                 let sellerid = ids[0].parse::<i32>().unwrap();
                 let siteid = ids[1].parse::<i32>().unwrap();
 
-                db.remove_seller_from_blacklist(userid.try_into().unwrap(), sellerid, siteid)
+                let content = crate::vahti::follow_seller(
+                    db,
+                    siteid,
+                    sellerid,
+                    userid,
+                    crate::delivery::discord::ID,
+                )
+                .await
+                .unwrap_or_else(|e| e.to_string());
+
+                button
+                    .edit_response(&ctx.http, EditInteractionResponse::new().content(content))
                     .await
                     .unwrap();
+            } else if button.data.custom_id == "bookmark_item" {
+                button.defer_ephemeral(&ctx.http).await.unwrap();
+                let items = items_from_message(&button.message)
+                    .into_iter()
+                    .filter_map(|(title, url)| {
+                        crate::bookmark::item_from_url(&url)
+                            .map(|(siteid, adid)| (title, format!("{},{}", adid, siteid)))
+                    })
+                    .unique()
+                    .collect::<Vec<_>>();
+
+                button
+                    .edit_response(
+                        &ctx.http,
+                        EditInteractionResponse::new()
+                            .content("Valitse ilmoitus, jonka poistumisesta haluat ilmoituksen")
+                            .components(menu_from_options("bookmark_item_menu", items)),
+                    )
+                    .await
+                    .unwrap();
+            } else if button.data.custom_id == "bookmark_item_menu" {
+                button.defer_ephemeral(&ctx.http).await.unwrap();
+                let db = ctx.get_db().await.unwrap();
+                let userid = u64::from(button.user.id);
+                let ids: Vec<String> = match button.data.kind.clone() {
+                    ComponentInteractionDataKind::StringSelect { values } => {
+                        values[0].split(',').map(|s| s.to_string()).collect()
+                    }
+                    _ => unreachable!(),
+                };
+                let adid = ids[0].parse::<i64>().unwrap();
+                let siteid = ids[1].parse::<i32>().unwrap();
+
+                let content = crate::bookmark::bookmark_item(
+                    db,
+                    siteid,
+                    adid,
+                    userid,
+                    crate::delivery::discord::ID,
+                )
+                .await
+                .unwrap_or_else(|e| e.to_string());
+
+                button
+                    .edit_response(&ctx.http, EditInteractionResponse::new().content(content))
+                    .await
+                    .unwrap();
+            } else if button.data.custom_id == "unblock_seller" {
+                button.defer_ephemeral(&ctx.http).await.unwrap();
+                let db = ctx.get_db().await.unwrap();
+                let userid = u64::from(button.user.id);
+                let ids: Vec<String> = match button.data.kind.clone() {
+                    ComponentInteractionDataKind::StringSelect { values } => {
+                        values[0].split(',').map(|s| s.to_string()).collect()
+                    }
+                    _ => unreachable!(),
+                };
+                let sellerid = ids[0].parse::<i32>().unwrap();
+                let siteid = ids[1].parse::<i32>().unwrap();
+
+                db.remove_seller_from_blacklist(
+                    userid.try_into().unwrap(),
+                    crate::delivery::discord::ID,
+                    sellerid,
+                    siteid,
+                )
+                .await
+                .unwrap();
                 button
                     .edit_response(
                         &ctx.http,
@@ -148,14 +323,17 @@ pub async fn handle_interaction(ctx: Context, interaction: Interaction) {
                     .unwrap();
             } else if button.data.custom_id == "remove_vahti_menu" {
                 button.defer_ephemeral(&ctx.http).await.unwrap();
-                let userid = u64::from(button.user.id);
+                let (userid, delivery_method) = component_owner(&button).unwrap();
                 let url = match button.data.kind.clone() {
                     ComponentInteractionDataKind::StringSelect { values } => values[0].to_string(),
                     _ => unreachable!(),
                 };
                 let db = ctx.get_db().await.unwrap();
+                // NOTE: The listings of a Vahti may come from a linked account
+                let (userid, delivery_method) =
+                    crate::identity::linked_vahti_owner(&db, &url, userid, delivery_method).await;
 
-                crate::vahti::remove_vahti(db, &url, userid, crate::delivery::discord::ID)
+                crate::vahti::remove_vahti(db, &url, userid, delivery_method)
                     .await
                     .unwrap();
                 button
@@ -190,10 +368,80 @@ pub async fn handle_interaction(ctx: Context, interaction: Interaction) {
                     .await
                     .unwrap();
                 return;
-            } else if button.data.custom_id == "block_seller_menu" {
+            } else if button.data.custom_id.starts_with("vahdit_") {
+                button
+                    .create_response(
+                        &ctx.http,
+                        serenity::builder::CreateInteractionResponse::UpdateMessage(
+                            super::vahdit::update_message(
+                                &ctx,
+                                &button.data.custom_id,
+                                u64::from(button.user.id),
+                            )
+                            .await,
+                        ),
+                    )
+                    .await
+                    .unwrap();
+                return;
+            } else if button.data.custom_id == "watch_auction" {
+                button.defer_ephemeral(&ctx.http).await.unwrap();
+                #[cfg(feature = "huutonet")]
+                let auctions = items_from_message(&button.message)
+                    .into_iter()
+                    .filter(|(_, url)| url.contains("https://www.huuto.net/kohteet/"))
+                    .filter_map(|(title, url)| {
+                        crate::huutonet::api::item_id_from_url(&url).map(|id| (title, id))
+                    })
+                    .unique()
+                    .collect::<Vec<_>>();
+                #[cfg(not(feature = "huutonet"))]
+                let auctions: Vec<(String, i64)> = vec![];
+
+                button
+                    .edit_response(
+                        &ctx.http,
+                        EditInteractionResponse::new()
+                            .content("Valitse huutokauppa, jonka päättymisestä haluat muistutuksen")
+                            .components(menu_from_options("watch_auction_menu", auctions)),
+                    )
+                    .await
+                    .unwrap();
+            } else if button.data.custom_id == "watch_auction_menu" {
                 button.defer_ephemeral(&ctx.http).await.unwrap();
                 let db = ctx.get_db().await.unwrap();
                 let userid = u64::from(button.user.id);
+                let itemid = match button.data.kind.clone() {
+                    ComponentInteractionDataKind::StringSelect { values } => {
+                        values[0].parse::<i64>().unwrap()
+                    }
+                    _ => unreachable!(),
+                };
+
+                #[cfg(feature = "huutonet")]
+                let content = crate::reminder::watch_auction(
+                    db,
+                    itemid,
+                    userid,
+                    crate::delivery::discord::ID,
+                    *crate::AUCTION_REMINDER_BEFORE,
+                )
+                .await
+                .unwrap_or_else(|e| e.to_string());
+                #[cfg(not(feature = "huutonet"))]
+                let content = {
+                    let _ = (db, userid, itemid);
+                    String::from("Huuto.net-tuki ei ole käytössä")
+                };
+
+                button
+                    .edit_response(&ctx.http, EditInteractionResponse::new().content(content))
+                    .await
+                    .unwrap();
+            } else if button.data.custom_id == "block_seller_menu" {
+                button.defer_ephemeral(&ctx.http).await.unwrap();
+                let db = ctx.get_db().await.unwrap();
+                let (userid, delivery_method) = component_owner(&button).unwrap();
                 let ids: Vec<String> = match button.data.kind.clone() {
                     ComponentInteractionDataKind::StringSelect { values } => {
                         values[0].split(',').map(|s| s.to_string()).collect()
@@ -203,7 +451,7 @@ pub async fn handle_interaction(ctx: Context, interaction: Interaction) {
                 let sellerid = ids[0].parse::<i32>().unwrap();
                 let siteid = ids[1].parse::<i32>().unwrap();
 
-                db.add_seller_to_blacklist(userid as i64, sellerid, siteid)
+                db.add_seller_to_blacklist(userid as i64, delivery_method, sellerid, siteid)
                     .await
                     .unwrap();
                 button
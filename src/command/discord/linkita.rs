@@ -0,0 +1,51 @@
+use serenity::builder::{CreateCommand, CreateCommandOption};
+use serenity::client::Context;
+use serenity::model::application::{CommandInteraction, CommandOptionType};
+
+use super::extensions::ClientContextExt;
+use crate::identity::{create_pairing_code, list_linked, pair, unlink};
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("linkita")
+        .description("Linkitä tilisi muiden alustojen tileihin, ilman valintoja luo linkityskoodin")
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "koodi",
+            "Toisella alustalla luotu linkityskoodi",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "poista",
+            "Poista tilin linkitys",
+        ))
+}
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> String {
+    let mut code = None;
+    let mut remove = false;
+    for a in &command.data.options {
+        match a.name.as_str() {
+            "koodi" => code = a.value.as_str().map(String::from),
+            "poista" => remove = a.value.as_bool().unwrap_or(false),
+            _ => unreachable!(),
+        }
+    }
+
+    let db = ctx.get_db().await.unwrap();
+    let userid = u64::from(command.user.id);
+    let delivery_method = crate::delivery::discord::ID;
+
+    if remove {
+        unlink(&db, userid, delivery_method).await
+    } else if let Some(code) = code {
+        pair(&db, &code, userid, delivery_method).await
+    } else {
+        match list_linked(&db, userid, delivery_method).await {
+            Ok(linked) => create_pairing_code(&db, userid, delivery_method)
+                .await
+                .map(|c| format!("{}\n{}", linked, c)),
+            Err(e) => Err(e),
+        }
+    }
+    .unwrap_or_else(|e| e.to_string())
+}
@@ -1,8 +1,20 @@
+mod asetukset;
 mod extensions;
 mod interaction;
+mod jatka;
+mod linkita;
 mod poistaesto;
 mod poistavahti;
+#[cfg(feature = "push-delivery")]
+mod push;
+#[cfg(feature = "email-delivery")]
+mod sahkoposti;
+mod tauota;
+mod torkku;
+mod vahdit;
 mod vahti;
+#[cfg(feature = "webhook-delivery")]
+mod webhook;
 
 use std::sync::Arc;
 
@@ -29,15 +41,27 @@ impl EventHandler for Handler {
 
     async fn ready(&self, ctx: Context, ready: Ready) {
         info!("Connected as {}", ready.user.name);
-        let _ = serenity::model::application::Command::set_global_commands(
-            &ctx.http,
-            vec![
-                vahti::register(),
-                poistavahti::register(),
-                poistaesto::register(),
-            ],
-        )
-        .await;
+        #[allow(unused_mut)]
+        let mut commands = vec![
+            vahti::register(),
+            poistavahti::register(),
+            vahdit::register(),
+            tauota::register(),
+            jatka::register(),
+            linkita::register(),
+            torkku::register(),
+            poistaesto::register(),
+            asetukset::register(),
+        ];
+        #[cfg(feature = "webhook-delivery")]
+        commands.push(webhook::register());
+        #[cfg(feature = "email-delivery")]
+        commands.push(sahkoposti::register());
+        #[cfg(feature = "push-delivery")]
+        commands.push(push::register());
+
+        let _ =
+            serenity::model::application::Command::set_global_commands(&ctx.http, commands).await;
     }
     async fn resume(&self, _: Context, _: ResumedEvent) {
         info!("Resumed");
@@ -12,23 +12,16 @@ pub fn register() -> CreateCommand {
 pub async fn run(ctx: &Context, command: &CommandInteraction) -> String {
     let db = ctx.get_db().await.unwrap();
     let blacklist = db
-        .fetch_user_blacklist(u64::from(command.user.id) as i64)
+        .fetch_user_blacklist(
+            u64::from(command.user.id) as i64,
+            crate::delivery::discord::ID,
+        )
         .await
         .unwrap();
 
     let mut blacklist_names = vec![];
     for entry in &blacklist {
-        blacklist_names.push(match entry.1 {
-            #[cfg(feature = "tori")]
-            crate::tori::ID => crate::tori::seller::get_seller_name_from_id(entry.0)
-                .await
-                .unwrap_or(String::from("Unknown Seller")),
-            #[cfg(feature = "huutonet")]
-            crate::huutonet::ID => crate::huutonet::seller::get_seller_name_from_id(entry.0)
-                .await
-                .unwrap_or(String::from("Unknown Seller")),
-            _ => String::from("Unknown Seller"),
-        });
+        blacklist_names.push(crate::blacklist::seller_name(entry.0, entry.1).await);
     }
 
     let options = blacklist_names
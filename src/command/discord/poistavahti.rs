@@ -57,7 +57,7 @@ pub async fn update_message(
     let db = ctx.get_db().await.unwrap();
 
     let vahtilist = db
-        .fetch_vahti_entries_by_user_id(user_id as i64)
+        .fetch_vahti_entries_by_user_id(user_id as i64, crate::delivery::discord::ID)
         .await
         .unwrap();
 
@@ -95,7 +95,10 @@ pub async fn run(ctx: &Context, command: &CommandInteraction) -> String {
     } else {
         let db = ctx.get_db().await.unwrap();
         let vahtilist = db
-            .fetch_vahti_entries_by_user_id(u64::from(command.user.id) as i64)
+            .fetch_vahti_entries_by_user_id(
+                u64::from(command.user.id) as i64,
+                crate::delivery::discord::ID,
+            )
             .await
             .unwrap();
 
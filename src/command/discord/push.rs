@@ -0,0 +1,81 @@
+use serenity::builder::{CreateCommand, CreateCommandOption};
+use serenity::client::Context;
+use serenity::model::application::{CommandInteraction, CommandOptionType};
+
+use super::extensions::ClientContextExt;
+use crate::delivery::push::{add_push_target, list_push_targets, remove_push_target, PushService};
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("push")
+        .description("Lisää, poista tai listaa ntfy- ja Gotify-ilmoituskohteita")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "palvelu", "Ilmoituspalvelu")
+                .add_string_choice("ntfy", "ntfy")
+                .add_string_choice("Gotify", "gotify"),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "palvelin",
+            "Palvelimen osoite, esim. https://ntfy.sh",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "aihe",
+            "ntfy-aihe (topic)",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "token",
+            "ntfy-käyttöoikeustunnus tai Gotify-sovelluksen token",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "poista",
+            "Poistettavan kohteen numero",
+        ))
+}
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> String {
+    let mut service = None;
+    let mut server = None;
+    let mut topic = None;
+    let mut token = None;
+    let mut remove = None;
+    for a in &command.data.options {
+        match a.name.as_str() {
+            "palvelu" => service = a.value.as_str().map(String::from),
+            "palvelin" => server = a.value.as_str().map(String::from),
+            "aihe" => topic = a.value.as_str().map(String::from),
+            "token" => token = a.value.as_str().map(String::from),
+            "poista" => remove = a.value.as_i64(),
+            _ => unreachable!(),
+        }
+    }
+
+    let db = ctx.get_db().await.unwrap();
+    let userid = u64::from(command.user.id);
+
+    match (service, server, remove) {
+        (_, _, Some(id)) => {
+            remove_push_target(db, id as i32, userid, crate::delivery::discord::ID).await
+        }
+        (Some(service), Some(server), None) => match service.parse::<PushService>() {
+            Ok(service) => {
+                add_push_target(
+                    db,
+                    service,
+                    server.trim(),
+                    topic,
+                    token,
+                    userid,
+                    crate::delivery::discord::ID,
+                )
+                .await
+            }
+            Err(e) => Err(e),
+        },
+        (None, None, None) => list_push_targets(db, userid, crate::delivery::discord::ID).await,
+        _ => Ok(String::from("Anna sekä palvelu että palvelin")),
+    }
+    .unwrap_or_else(|e| e.to_string())
+}
@@ -0,0 +1,62 @@
+use serenity::builder::{CreateCommand, CreateCommandOption};
+use serenity::client::Context;
+use serenity::model::application::{CommandInteraction, CommandOptionType};
+
+use super::extensions::ClientContextExt;
+use crate::delivery::email::{
+    add_email_address, list_email_addresses, remove_email_address, verify_email_address, Mailer,
+};
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("sahkoposti")
+        .description("Lisää, vahvista, poista tai listaa sähköpostiosoitteita")
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "osoite",
+            "Osoite, johon ilmoitukset lähetetään",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "koodi",
+            "Osoitteeseen lähetetty vahvistuskoodi",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "poista",
+            "Poistettavan osoitteen numero",
+        ))
+}
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> String {
+    let mut address = None;
+    let mut code = None;
+    let mut remove = None;
+    for a in &command.data.options {
+        match a.name.as_str() {
+            "osoite" => address = a.value.as_str().map(String::from),
+            "koodi" => code = a.value.as_str().map(String::from),
+            "poista" => remove = a.value.as_i64(),
+            _ => unreachable!(),
+        }
+    }
+
+    let db = ctx.get_db().await.unwrap();
+    let userid = u64::from(command.user.id);
+
+    match (address, code, remove) {
+        (_, _, Some(id)) => {
+            remove_email_address(db, id as i32, userid, crate::delivery::discord::ID).await
+        }
+        (_, Some(code), None) => {
+            verify_email_address(db, &code, userid, crate::delivery::discord::ID).await
+        }
+        (Some(address), None, None) => match Mailer::from_env() {
+            Ok(mailer) => {
+                add_email_address(db, &mailer, &address, userid, crate::delivery::discord::ID).await
+            }
+            Err(e) => Err(e),
+        },
+        (None, None, None) => list_email_addresses(db, userid, crate::delivery::discord::ID).await,
+    }
+    .unwrap_or_else(|e| e.to_string())
+}
@@ -0,0 +1,51 @@
+use serenity::builder::{CreateCommand, CreateCommandOption};
+use serenity::client::Context;
+use serenity::model::application::{CommandInteraction, CommandOptionType};
+
+use super::extensions::ClientContextExt;
+use crate::vahti::{parse_duration, snooze_vahti};
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("torkku")
+        .description("Keskeytä vahti määräajaksi")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "url", "Hakusivun linkki")
+                .required(true),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "kesto",
+                "Kuinka pitkäksi aikaa, esim. 30m, 12h, 3d tai 2w",
+            )
+            .required(true),
+        )
+}
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> String {
+    let mut url = String::new();
+    let mut duration = String::new();
+    for a in &command.data.options {
+        match a.name.as_str() {
+            "url" => url = String::from(a.value.as_str().unwrap()),
+            "kesto" => duration = String::from(a.value.as_str().unwrap()),
+            _ => unreachable!(),
+        }
+    }
+
+    let duration = match parse_duration(&duration) {
+        Ok(d) => d,
+        Err(e) => return e.to_string(),
+    };
+
+    let db = ctx.get_db().await.unwrap();
+    snooze_vahti(
+        db,
+        &url,
+        u64::from(command.user.id),
+        crate::delivery::discord::ID,
+        duration,
+    )
+    .await
+    .unwrap_or_else(|e| e.to_string())
+}
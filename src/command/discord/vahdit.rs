@@ -0,0 +1,200 @@
+use serenity::all::{ButtonStyle, ReactionType};
+use serenity::builder::{
+    CreateActionRow, CreateButton, CreateCommand, CreateEmbed, CreateEmbedFooter,
+    CreateInteractionResponseMessage, EditInteractionResponse,
+};
+use serenity::client::Context;
+use serenity::model::application::CommandInteraction;
+
+use super::extensions::ClientContextExt;
+use crate::database::Database;
+use crate::models::DbVahti;
+use crate::settings::UserSettings;
+use crate::vahti::{format_elapsed, remove_vahti, summarize_url};
+
+/// The Vahtis shown on a single page, each of them takes one row of buttons
+const PAGE_SIZE: usize = 4;
+const WEEK: i64 = 7 * 24 * 60 * 60;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("vahdit").description("Näytä vahtisi ja niiden tila")
+}
+
+async fn user_vahtis(db: &Database, userid: u64) -> Vec<DbVahti> {
+    let mut vahtis = db
+        .fetch_vahti_entries_by_user_id(userid as i64, crate::delivery::discord::ID)
+        .await
+        .unwrap_or_default();
+    vahtis.sort_by_key(|v| v.id);
+    vahtis
+}
+
+async fn show_page(
+    db: &Database,
+    userid: u64,
+    page: usize,
+) -> (Option<CreateEmbed>, Vec<CreateActionRow>) {
+    let vahtis = user_vahtis(db, userid).await;
+    if vahtis.is_empty() {
+        return (None, vec![]);
+    }
+
+    let pages = vahtis.len().div_ceil(PAGE_SIZE);
+    let page = page.min(pages - 1);
+    let now = chrono::Local::now().timestamp();
+    let settings = UserSettings::fetch(db, userid as i64, crate::delivery::discord::ID).await;
+
+    let mut embed = CreateEmbed::new()
+        .title("Vahdit")
+        .footer(CreateEmbedFooter::new(format!(
+            "Sivu {}/{}",
+            page + 1,
+            pages
+        )));
+    let mut rows = vec![];
+
+    for (i, v) in vahtis
+        .iter()
+        .enumerate()
+        .skip(page * PAGE_SIZE)
+        .take(PAGE_SIZE)
+    {
+        let n = i + 1;
+        let recent = db
+            .count_seen_items_by_vahti(v.id, now - WEEK)
+            .await
+            .unwrap_or_default();
+
+        let mut value = format!(
+            "{}\nPäivitetty: {}\nIlmoituksia 7 pv: {}",
+            v.url,
+            format_elapsed(now, v.last_updated),
+            recent
+        );
+        if v.paused {
+            value.push_str("\n**Tauotettu**");
+        } else if let Some(until) = v.snoozed_until {
+            value.push_str(&format!(
+                "\n**Torkuttu {} asti**",
+                settings.format_time(until)
+            ));
+        }
+        if let Some(error) = &v.last_error {
+            value.push_str(&format!("\nVirhe: {}", error));
+        }
+
+        let mut name = format!("#{} {}", n, summarize_url(&v.url));
+        if name.chars().count() > 256 {
+            name = name.chars().take(255).collect::<String>() + "…";
+        }
+        embed = embed.field(name, value.chars().take(1024).collect::<String>(), false);
+
+        rows.push(CreateActionRow::Buttons(vec![
+            CreateButton::new(format!("vahdit_pause_{}_{}", v.id, page))
+                .label(if v.paused || v.snoozed_until.is_some() {
+                    format!("Jatka #{}", n)
+                } else {
+                    format!("Tauota #{}", n)
+                })
+                .style(ButtonStyle::Secondary),
+            CreateButton::new(format!("vahdit_delete_{}_{}", v.id, page))
+                .label(format!("Poista #{}", n))
+                .style(ButtonStyle::Danger),
+        ]));
+    }
+
+    rows.push(CreateActionRow::Buttons(vec![
+        CreateButton::new(format!("vahdit_page_{}", page.saturating_sub(1)))
+            .emoji(ReactionType::Unicode("◀️".to_string()))
+            .disabled(page == 0),
+        CreateButton::new(format!("vahdit_page_{}", page + 1))
+            .emoji(ReactionType::Unicode("▶️".to_string()))
+            .disabled(page + 1 >= pages),
+    ]));
+
+    (Some(embed), rows)
+}
+
+/// Pauses or deletes a Vahti of the user as instructed by the `custom_id` of the button,
+/// returning the page to show afterwards
+async fn run_action(db: &Database, userid: u64, custom_id: &str) -> usize {
+    let (action, rest) = if let Some(rest) = custom_id.strip_prefix("vahdit_pause_") {
+        ("pause", rest)
+    } else if let Some(rest) = custom_id.strip_prefix("vahdit_delete_") {
+        ("delete", rest)
+    } else {
+        return custom_id
+            .strip_prefix("vahdit_page_")
+            .and_then(|p| p.parse().ok())
+            .unwrap_or_default();
+    };
+
+    let Some((vahtiid, page)) = rest
+        .split_once('_')
+        .and_then(|(v, p)| Some((v.parse::<i32>().ok()?, p.parse::<usize>().ok()?)))
+    else {
+        return 0;
+    };
+
+    let vahti = match db.fetch_vahti_by_id(vahtiid).await {
+        Ok(v)
+            if v.user_id == userid as i64 && v.delivery_method == crate::delivery::discord::ID =>
+        {
+            v
+        }
+        _ => return page,
+    };
+
+    let result = if action == "pause" {
+        if vahti.paused || vahti.snoozed_until.is_some() {
+            db.resume_vahti(vahti.id).await
+        } else {
+            db.pause_vahti(vahti.id).await
+        }
+        .map(|_| String::new())
+    } else {
+        remove_vahti(db.clone(), &vahti.url, userid, crate::delivery::discord::ID).await
+    };
+    if let Err(e) = result {
+        error!("Failed to update Vahti {}: {}", vahti.id, e);
+    }
+    page
+}
+
+pub async fn update_message(
+    ctx: &Context,
+    custom_id: &str,
+    user_id: u64,
+) -> CreateInteractionResponseMessage {
+    let db = ctx.get_db().await.unwrap();
+    let page = run_action(&db, user_id, custom_id).await;
+
+    match show_page(&db, user_id, page).await {
+        (Some(embed), rows) => CreateInteractionResponseMessage::new()
+            .content("")
+            .embed(embed)
+            .components(rows),
+        (None, _) => CreateInteractionResponseMessage::new()
+            .content("Ei vahteja! Aseta vahti komennolla `/vahti`")
+            .embeds(vec![])
+            .components(vec![]),
+    }
+}
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> String {
+    let db = ctx.get_db().await.unwrap();
+
+    match show_page(&db, u64::from(command.user.id), 0).await {
+        (Some(embed), rows) => {
+            command
+                .edit_response(
+                    &ctx.http,
+                    EditInteractionResponse::new().embed(embed).components(rows),
+                )
+                .await
+                .unwrap();
+            String::new()
+        }
+        (None, _) => String::from("Ei vahteja! Aseta vahti komennolla `/vahti`"),
+    }
+}
@@ -1,38 +1,216 @@
 use serenity::builder::{CreateCommand, CreateCommandOption};
 use serenity::client::Context;
 use serenity::model::application::{CommandInteraction, CommandOptionType};
+use serenity::model::channel::ChannelType;
 
 use super::extensions::ClientContextExt;
-use crate::vahti::new_vahti;
+use crate::delivery::discord::vahti_owner;
+use crate::digest::{set_vahti_digest, DigestSchedule};
+use crate::filters::VahtiFilters;
+use crate::identity::set_vahti_endpoints;
+use crate::vahti::{
+    canonicalize_url, new_vahti, resolve_destination, set_vahti_filters, show_vahti_filters,
+};
 
 pub fn register() -> CreateCommand {
     CreateCommand::new("vahti")
-        .description("Luo uusi vahti")
+        .description("Luo uusi vahti tai muokkaa olemassaolevan vahdin suodattimia")
         .add_option(
             CreateCommandOption::new(CommandOptionType::String, "url", "Hakusivun linkki")
                 .required(true),
         )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "min_hinta",
+            "Alin hinta",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "max_hinta",
+            "Ylin hinta",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "sisaltaa",
+            "Otsikosta löydettävät sanat pilkuilla eroteltuina",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "ei_sisalla",
+            "Otsikosta kielletyt sanat pilkuilla eroteltuina",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "regex",
+            "Säännöllinen lauseke, johon otsikon täytyy täsmätä",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "tyyppi",
+            "Sallitut ilmoitustyypit pilkuilla eroteltuina (esim. Myydään)",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "tyhjenna",
+            "Poista vahdin suodattimet",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "kooste",
+            "Lähetä ilmoitukset koosteena (hourly, daily@08:00, mon@08:00 tai off)",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "kohde",
+            "Minne ilmoitukset lähetetään, esim. webhook:3, email:2 tai push:1 (oletuksena yksityisviestinä)",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "kohteet",
+            "Linkitetyt tilit, joille ilmoitukset lähetetään pilkuilla eroteltuina (esim. discord,telegram)",
+        ))
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Channel,
+                "kanava",
+                "Palvelimen kanava tai ketju, jolle ilmoitukset lähetetään (vaatii Hallitse kanavia -oikeuden)",
+            )
+            .channel_types(vec![
+                ChannelType::Text,
+                ChannelType::News,
+                ChannelType::PublicThread,
+                ChannelType::PrivateThread,
+            ]),
+        )
+}
+
+fn split_list(s: &str) -> Vec<String> {
+    s.split(',')
+        .map(|w| w.trim().to_string())
+        .filter(|w| !w.is_empty())
+        .collect()
 }
 
 pub async fn run(ctx: &Context, command: &CommandInteraction) -> String {
     let mut url = String::new();
+    let mut filters = VahtiFilters::default();
+    let mut clear = false;
+    let mut digest = None;
+    let mut destination = None;
+    let mut channel = None;
+    let mut endpoints = None;
     for a in &command.data.options {
         match a.name.as_str() {
             "url" => url = String::from(a.value.as_str().unwrap()),
+            "min_hinta" => filters.min_price = a.value.as_i64(),
+            "max_hinta" => filters.max_price = a.value.as_i64(),
+            "sisaltaa" => filters.include = split_list(a.value.as_str().unwrap()),
+            "ei_sisalla" => filters.exclude = split_list(a.value.as_str().unwrap()),
+            "regex" => filters.title_regex = a.value.as_str().map(String::from),
+            "tyyppi" => filters.ad_types = split_list(a.value.as_str().unwrap()),
+            "tyhjenna" => clear = a.value.as_bool().unwrap_or(false),
+            "kooste" => digest = a.value.as_str().map(String::from),
+            "kohde" => destination = a.value.as_str().map(String::from),
+            "kanava" => channel = a.value.as_channel_id(),
+            "kohteet" => endpoints = a.value.as_str().map(String::from),
             _ => unreachable!(),
         }
     }
 
+    let url = canonicalize_url(&url);
     info!("New vahti {}", &url);
 
+    if let Err(e) = filters.validate() {
+        return e.to_string();
+    }
+
+    // NOTE: `off` turns the digest off, i.e. the items are delivered immediately
+    let digest = match digest.as_deref().map(str::trim) {
+        None => None,
+        Some("off") => Some(None),
+        Some(d) => match d.parse::<DigestSchedule>() {
+            Ok(schedule) => Some(Some(schedule)),
+            Err(e) => return e.to_string(),
+        },
+    };
+
     let db = ctx.get_db().await.unwrap();
+    let (userid, delivery_method) = if let Some(channel) = channel {
+        if destination.is_some() {
+            return String::from("Anna joko kohde tai kanava, ei molempia");
+        }
+        if command.guild_id.is_none() {
+            return String::from("Kanavan voi valita vain palvelimella");
+        }
 
-    new_vahti(
-        db,
-        &url,
-        u64::from(command.user.id),
-        crate::delivery::discord::ID,
-    )
-    .await
+        let permissions = command
+            .data
+            .resolved
+            .channels
+            .get(&channel)
+            .and_then(|c| c.permissions);
+        match vahti_owner(u64::from(command.user.id), channel.get(), true, permissions) {
+            Some(owner) => owner,
+            None => {
+                return String::from(
+                    "Vahdin asettaminen kanavalle vaatii Hallitse kanavia -oikeuden",
+                )
+            }
+        }
+    } else {
+        match resolve_destination(
+            &db,
+            destination.as_deref(),
+            u64::from(command.user.id),
+            crate::delivery::discord::ID,
+        )
+        .await
+        {
+            Ok(d) => d,
+            Err(e) => return e.to_string(),
+        }
+    };
+
+    let res = if db
+        .fetch_vahti(&url, userid as i64, delivery_method)
+        .await
+        .is_ok()
+    {
+        if clear || !filters.is_empty() {
+            set_vahti_filters(db.clone(), &url, userid, delivery_method, filters).await
+        } else if digest.is_some() || endpoints.is_some() {
+            Ok(String::new())
+        } else {
+            show_vahti_filters(db.clone(), &url, userid, delivery_method)
+                .await
+                .map(|f| format!("Vahti on jo olemassa\n{}", f))
+        }
+    } else {
+        new_vahti(
+            db.clone(),
+            &url,
+            userid,
+            delivery_method,
+            Some(u64::from(command.user.id)),
+            filters,
+        )
+        .await
+    };
+
+    let res = match (res, digest) {
+        (Ok(msg), Some(schedule)) => {
+            set_vahti_digest(db.clone(), &url, userid, delivery_method, schedule)
+                .await
+                .map(|d| format!("{}\n{}", msg, d).trim().to_string())
+        }
+        (res, _) => res,
+    };
+
+    match (res, endpoints) {
+        (Ok(msg), Some(spec)) => set_vahti_endpoints(db, &url, userid, delivery_method, &spec)
+            .await
+            .map(|d| format!("{}\n{}", msg, d).trim().to_string()),
+        (res, _) => res,
+    }
     .unwrap_or_else(|e| e.to_string())
 }
@@ -0,0 +1,52 @@
+use serenity::builder::{CreateCommand, CreateCommandOption};
+use serenity::client::Context;
+use serenity::model::application::{CommandInteraction, CommandOptionType};
+
+use super::extensions::ClientContextExt;
+use crate::delivery::webhook::{add_webhook, list_webhooks, remove_webhook};
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("webhook")
+        .description("Lisää, poista tai listaa webhookkeja")
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "url",
+            "Osoite, johon ilmoitukset lähetetään",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "salaisuus",
+            "Salaisuus, jolla pyynnöt allekirjoitetaan",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Integer,
+            "poista",
+            "Poistettavan webhookin numero",
+        ))
+}
+
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> String {
+    let mut url = None;
+    let mut secret = None;
+    let mut remove = None;
+    for a in &command.data.options {
+        match a.name.as_str() {
+            "url" => url = a.value.as_str().map(String::from),
+            "salaisuus" => secret = a.value.as_str().map(String::from),
+            "poista" => remove = a.value.as_i64(),
+            _ => unreachable!(),
+        }
+    }
+
+    let db = ctx.get_db().await.unwrap();
+    let userid = u64::from(command.user.id);
+
+    match (url, remove) {
+        (_, Some(id)) => remove_webhook(db, id as i32, userid, crate::delivery::discord::ID).await,
+        (Some(url), None) => {
+            add_webhook(db, url.trim(), secret, userid, crate::delivery::discord::ID).await
+        }
+        (None, None) => list_webhooks(db, userid, crate::delivery::discord::ID).await,
+    }
+    .unwrap_or_else(|e| e.to_string())
+}
@@ -0,0 +1,28 @@
+use crate::blacklist::{block_seller, list_blocked_sellers, seller_from_url, unblock_seller};
+use crate::database::Database;
+
+pub async fn block(db: Database, userid: u64, url: String) -> String {
+    let Some((siteid, sellerid)) = seller_from_url(url.trim()) else {
+        return String::from("Please provide a seller url");
+    };
+
+    block_seller(db, userid, crate::delivery::matrix::ID, sellerid, siteid)
+        .await
+        .unwrap_or_else(|e| e.to_string())
+}
+
+pub async fn unblock(db: Database, userid: u64, url: String) -> String {
+    let Some((siteid, sellerid)) = seller_from_url(url.trim()) else {
+        return String::from("Please provide a seller url");
+    };
+
+    unblock_seller(db, userid, crate::delivery::matrix::ID, sellerid, siteid)
+        .await
+        .unwrap_or_else(|e| e.to_string())
+}
+
+pub async fn list(db: Database, userid: u64) -> String {
+    list_blocked_sellers(db, userid, crate::delivery::matrix::ID)
+        .await
+        .unwrap_or_else(|e| e.to_string())
+}
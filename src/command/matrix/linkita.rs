@@ -0,0 +1,18 @@
+use crate::database::Database;
+use crate::identity::{create_pairing_code, list_linked, pair, unlink};
+
+pub async fn run(db: Database, userid: u64, args: String) -> String {
+    let delivery_method = crate::delivery::matrix::ID;
+
+    match args.trim() {
+        "" => match list_linked(&db, userid, delivery_method).await {
+            Ok(linked) => create_pairing_code(&db, userid, delivery_method)
+                .await
+                .map(|c| format!("{}\n{}", linked, c)),
+            Err(e) => Err(e),
+        },
+        "remove" => unlink(&db, userid, delivery_method).await,
+        code => pair(&db, code, userid, delivery_method).await,
+    }
+    .unwrap_or_else(|e| e.to_string())
+}
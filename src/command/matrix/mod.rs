@@ -0,0 +1,158 @@
+mod esto;
+mod linkita;
+mod poistavahti;
+mod vahti;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::Notify;
+
+use crate::command::Command;
+use crate::database::Database;
+use crate::error::Error;
+use crate::matrix::{invites_from_sync, messages_from_sync, MatrixClient, RoomMessage};
+use crate::unreachable::welcome_back;
+
+pub const NAME: &str = "matrix";
+
+/// How long a single sync request waits for new events
+const SYNC_TIMEOUT_MS: u64 = 30000;
+
+const HELP: &str = "Supported commands
+!vahti [url] [filters] - Add a new vahti or change the filters of an existing one. \
+Filters are e.g. `min=100 max=500 +word -word regex=x2[0-9]0 type=Myydään`, `clear` removes them. \
+`to=webhook:N` delivers the items elsewhere and `endpoints=discord,matrix` to the chosen linked accounts
+!poistavahti [url] - Remove a vahti, without an url lists your vahtis
+!esto [seller url] - Block a seller
+!poistaesto [seller url] - Unblock a seller
+!estot - List the blocked sellers
+!linkita [code|remove] - Link your accounts on other platforms, without arguments creates a pairing code
+!apua - Display this message";
+
+pub struct Matrix {
+    pub client: MatrixClient,
+    pub db: Database,
+    pub shutdown: Arc<Notify>,
+}
+
+pub struct Manager {
+    shutdown: Arc<Notify>,
+}
+
+impl Matrix {
+    pub async fn init(db: &Database) -> Result<Self, Error> {
+        Ok(Self {
+            client: MatrixClient::from_env()?,
+            db: db.clone(),
+            shutdown: Arc::new(Notify::new()),
+        })
+    }
+
+    async fn handle(&self, msg: RoomMessage) -> Result<(), Error> {
+        let (cmd, args) = msg
+            .body
+            .trim()
+            .split_once(char::is_whitespace)
+            .unwrap_or((msg.body.trim(), ""));
+        let args = args.trim().to_string();
+
+        if !matches!(
+            cmd,
+            "!vahti" | "!poistavahti" | "!esto" | "!poistaesto" | "!estot" | "!linkita" | "!apua"
+        ) {
+            return Ok(());
+        }
+
+        let userid = self.db.matrix_user_id(&msg.sender, &msg.room_id).await? as u64;
+        let db = self.db.clone();
+        let welcome = welcome_back(&db, userid, crate::delivery::matrix::ID).await;
+        let response = match cmd {
+            "!vahti" => vahti::run(db, userid, args).await,
+            "!poistavahti" => poistavahti::run(db, userid, args).await,
+            "!esto" => esto::block(db, userid, args).await,
+            "!poistaesto" => esto::unblock(db, userid, args).await,
+            "!estot" => esto::list(db, userid).await,
+            "!linkita" => linkita::run(db, userid, args).await,
+            _ => String::from(HELP),
+        };
+        let response = match welcome {
+            Some(w) => format!("{}\n\n{}", w, response),
+            None => response,
+        };
+
+        self.client
+            .send_message(&msg.room_id, &response, &escape_html(&response))
+            .await
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\n', "<br>")
+}
+
+#[async_trait]
+impl super::Manager for Manager {
+    async fn shutdown(&self) {
+        info!("Matrix destroy");
+        self.shutdown.notify_one();
+        info!("Matrix destroy done");
+    }
+}
+
+#[async_trait]
+impl Command for Matrix {
+    async fn start(&mut self) -> Result<(), Error> {
+        let own_id = self.client.whoami().await?;
+        info!("Connected to Matrix as {}", own_id);
+
+        // NOTE: The messages of the initial sync are old and therefore skipped
+        let mut since: Option<String> = None;
+        loop {
+            let sync = tokio::select! {
+                _ = self.shutdown.notified() => break,
+                sync = self.client.sync(since.as_deref(), SYNC_TIMEOUT_MS) => sync,
+            };
+
+            let sync = match sync {
+                Ok(s) => s,
+                Err(e) => {
+                    error!("Matrix sync failed: {}", e);
+                    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                    continue;
+                }
+            };
+
+            for room in invites_from_sync(&sync) {
+                info!("Joining Matrix room {}", room);
+                if let Err(e) = self.client.join(&room).await {
+                    error!("Failed to join {}: {}", room, e);
+                }
+            }
+
+            if since.is_some() {
+                for msg in messages_from_sync(&sync)
+                    .into_iter()
+                    .filter(|m| m.sender != own_id)
+                {
+                    if let Err(e) = self.handle(msg).await {
+                        error!("Failed to handle a Matrix command: {}", e);
+                    }
+                }
+            }
+
+            since = sync["next_batch"].as_str().map(String::from);
+        }
+
+        Ok(())
+    }
+
+    fn manager(&self) -> Box<dyn super::Manager + Send + Sync> {
+        Box::new(Manager {
+            shutdown: self.shutdown.clone(),
+        })
+    }
+}
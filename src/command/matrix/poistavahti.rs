@@ -0,0 +1,25 @@
+use crate::database::Database;
+use crate::vahti::remove_vahti;
+
+pub async fn run(db: Database, userid: u64, vahti: String) -> String {
+    if vahti.is_empty() {
+        let vahdit = db
+            .fetch_vahti_entries_by_user_id(userid as i64, crate::delivery::matrix::ID)
+            .await
+            .unwrap_or(Vec::new())
+            .iter()
+            .map(|v| v.url.clone())
+            .collect::<Vec<_>>();
+
+        if vahdit.is_empty() {
+            return String::from("You have no registered Vahtis");
+        }
+
+        return "Please provide a Vahti url, here are your registered Vahtis\n".to_owned()
+            + &vahdit.join("\n");
+    }
+
+    remove_vahti(db, &vahti, userid, crate::delivery::matrix::ID)
+        .await
+        .unwrap_or_else(|e| e.to_string())
+}
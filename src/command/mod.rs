@@ -4,6 +4,9 @@ pub mod discord;
 #[cfg(feature = "telegram-command")]
 pub mod telegram;
 
+#[cfg(feature = "matrix-command")]
+pub mod matrix;
+
 use async_trait::async_trait;
 
 use crate::error::Error;
@@ -0,0 +1,44 @@
+use teloxide::prelude::*;
+
+use crate::database::Database;
+use crate::settings::{parse_quiet_hours, parse_timezone, update_user_settings, UserSettings};
+
+pub async fn run(msg: Message, args: String, db: Database) -> ResponseResult<String> {
+    let userid = msg.chat.id.0 as u64;
+
+    let mut timezone = None;
+    let mut quiet_hours = None;
+    for arg in args.split_whitespace() {
+        match arg.split_once('=') {
+            Some(("timezone", "off")) => timezone = Some(None),
+            Some(("timezone", tz)) => match parse_timezone(tz) {
+                Ok(tz) => timezone = Some(Some(tz)),
+                Err(e) => return Ok(e.to_string()),
+            },
+            Some(("quiet", "off")) => quiet_hours = Some(None),
+            Some(("quiet", q)) => match parse_quiet_hours(q) {
+                Ok(q) => quiet_hours = Some(Some(q)),
+                Err(e) => return Ok(e.to_string()),
+            },
+            _ => return Ok(format!("Unknown setting {}", arg)),
+        }
+    }
+
+    if timezone.is_none() && quiet_hours.is_none() {
+        return Ok(
+            UserSettings::fetch(&db, userid as i64, crate::delivery::telegram::ID)
+                .await
+                .to_string(),
+        );
+    }
+
+    Ok(update_user_settings(
+        db,
+        userid,
+        crate::delivery::telegram::ID,
+        timezone,
+        quiet_hours,
+    )
+    .await
+    .unwrap_or_else(|e| e.to_string()))
+}
@@ -0,0 +1,22 @@
+use teloxide::prelude::*;
+
+use crate::blacklist::{block_seller, seller_from_url};
+use crate::database::Database;
+
+pub async fn run(msg: Message, args: String, db: Database) -> ResponseResult<String> {
+    let Some((siteid, sellerid)) = seller_from_url(args.trim()) else {
+        return Ok(String::from(
+            "Please provide a seller url, sellers can also be blocked with the buttons under the listings",
+        ));
+    };
+
+    Ok(block_seller(
+        db,
+        msg.chat.id.0 as u64,
+        crate::delivery::telegram::ID,
+        sellerid,
+        siteid,
+    )
+    .await
+    .unwrap_or_else(|e| e.to_string()))
+}
@@ -0,0 +1,12 @@
+use teloxide::prelude::*;
+
+use crate::blacklist::list_blocked_sellers;
+use crate::database::Database;
+
+pub async fn run(msg: Message, db: Database) -> ResponseResult<String> {
+    Ok(
+        list_blocked_sellers(db, msg.chat.id.0 as u64, crate::delivery::telegram::ID)
+            .await
+            .unwrap_or_else(|e| e.to_string()),
+    )
+}
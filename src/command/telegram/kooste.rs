@@ -0,0 +1,29 @@
+use teloxide::prelude::*;
+
+use crate::database::Database;
+use crate::digest::{set_vahti_digest, DigestSchedule};
+
+pub async fn run(msg: Message, args: String, db: Database) -> ResponseResult<String> {
+    let mut args = args.split_whitespace();
+    let (Some(url), Some(schedule)) = (args.next(), args.next()) else {
+        return Ok(String::from(
+            "Usage: /kooste [url] [hourly|daily@08:00|mon@08:00|off]",
+        ));
+    };
+
+    // NOTE: `off` turns the digest off, i.e. the items are delivered immediately
+    let schedule = match schedule {
+        "off" => None,
+        s => match s.parse::<DigestSchedule>() {
+            Ok(s) => Some(s),
+            Err(e) => return Ok(e.to_string()),
+        },
+    };
+
+    let userid = msg.chat.id.0 as u64;
+    Ok(
+        set_vahti_digest(db, url, userid, crate::delivery::telegram::ID, schedule)
+            .await
+            .unwrap_or_else(|e| e.to_string()),
+    )
+}
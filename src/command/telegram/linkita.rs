@@ -0,0 +1,27 @@
+use teloxide::prelude::*;
+
+use crate::database::Database;
+use crate::identity::{create_pairing_code, list_linked, pair, unlink};
+
+pub async fn run(msg: Message, args: String, db: Database) -> ResponseResult<String> {
+    if !msg.chat.is_private() {
+        return Ok(String::from(
+            "Accounts can only be linked in a private chat with the bot",
+        ));
+    }
+
+    let userid = msg.chat.id.0 as u64;
+    let delivery_method = crate::delivery::telegram::ID;
+
+    Ok(match args.trim() {
+        "" => match list_linked(&db, userid, delivery_method).await {
+            Ok(linked) => create_pairing_code(&db, userid, delivery_method)
+                .await
+                .map(|c| format!("{}\n{}", linked, c)),
+            Err(e) => Err(e),
+        },
+        "remove" => unlink(&db, userid, delivery_method).await,
+        code => pair(&db, code, userid, delivery_method).await,
+    }
+    .unwrap_or_else(|e| e.to_string()))
+}
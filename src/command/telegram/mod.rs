@@ -1,18 +1,42 @@
+mod asetukset;
+mod esto;
+mod estot;
 mod help;
+mod jatka;
+mod kooste;
+mod linkita;
+#[cfg(feature = "huutonet")]
+mod muistuta;
+mod poistaesto;
 mod poistavahti;
+#[cfg(feature = "push-delivery")]
+mod push;
+#[cfg(feature = "email-delivery")]
+mod sahkoposti;
+mod seuraa;
 mod start;
+mod tauota;
+mod torkku;
 mod vahti;
+#[cfg(feature = "webhook-delivery")]
+mod webhook;
 
 use async_trait::async_trait;
 use teloxide::adaptors::throttle::Limits;
 use teloxide::dispatching::{DefaultKey, ShutdownToken};
 use teloxide::prelude::*;
+use teloxide::types::{Chat, ChatId, UserId};
 use teloxide::utils::command::BotCommands;
 use teloxide::RequestError;
 
+use crate::blacklist::{block_seller, unblock_seller};
 use crate::command::Command;
 use crate::database::Database;
+use crate::delivery::telegram::TelegramAction;
 use crate::error::Error;
+use crate::identity::owns_vahti;
+use crate::unreachable::welcome_back;
+use crate::vahti::remove_vahti;
 
 pub const NAME: &str = "telegram";
 
@@ -34,11 +58,18 @@ impl Telegram {
 
         let _ = bot.set_my_commands(TelegramCommand::bot_commands()).await;
 
-        let handler = Update::filter_message().branch(
-            dptree::entry()
-                .filter_command::<TelegramCommand>()
-                .endpoint(handle),
-        );
+        let handler = dptree::entry()
+            .branch(
+                Update::filter_message()
+                    .filter_command::<TelegramCommand>()
+                    .endpoint(handle),
+            )
+            .branch(
+                Update::filter_channel_post()
+                    .filter_command::<TelegramCommand>()
+                    .endpoint(handle),
+            )
+            .branch(Update::filter_callback_query().endpoint(handle_callback));
 
         let dispatcher = Dispatcher::builder(bot.clone(), handler)
             .dependencies(dptree::deps![db.clone()])
@@ -58,26 +89,260 @@ enum TelegramCommand {
     Start,
     #[command(description = "Display help message")]
     Help,
-    #[command(description = "Add new vahti with `/vahti [url]`")]
+    #[command(
+        description = "Add new vahti with `/vahti [url] [filters]` or change the filters of an existing one. \
+        Filters are e.g. `min=100 max=500 +word -word regex=x2[0-9]0 type=Myydään`, `clear` removes them. \
+        The url can also be a seller link to follow every new listing of the seller. \
+        `to=webhook:N`, `to=email:N` or `to=push:N` delivers the items to one of your webhooks, email addresses or push targets"
+    )]
     Vahti(String),
     #[command(description = "Remove a vahti with `/poistavahti [url]`")]
     PoistaVahti(String),
+    #[command(description = "Pause a vahti until it is resumed with `/tauota [url]`")]
+    Tauota(String),
+    #[command(
+        description = "Resume a paused or snoozed vahti with `/jatka [url]`, the listings posted in the meantime are skipped"
+    )]
+    Jatka(String),
+    #[command(description = "Pause a vahti for a while with `/torkku [url] [30m|12h|3d|2w]`")]
+    Torkku(String),
+    #[command(
+        description = "Block a seller with `/esto [seller url]`, sellers can also be blocked with the buttons under the listings"
+    )]
+    Esto(String),
+    #[command(
+        description = "Unblock a seller with `/poistaesto [seller url]`, without arguments lists the blocked sellers to choose from"
+    )]
+    PoistaEsto(String),
+    #[command(description = "List the blocked sellers")]
+    Estot,
+    #[cfg(feature = "huutonet")]
+    #[command(
+        description = "Get reminded before a huuto.net auction closes with `/muistuta [url] [minutes]`"
+    )]
+    Muistuta(String),
+    #[command(
+        description = "Get notified when a listing is removed or sold with `/seuraa [url]`, without arguments lists the followed listings"
+    )]
+    Seuraa(String),
+    #[command(
+        description = "Deliver the items of a vahti as a digest with `/kooste [url] [hourly|daily@08:00|mon@08:00|off]`"
+    )]
+    Kooste(String),
+    #[command(
+        description = "Link your accounts on other platforms: without arguments creates a pairing code, \
+        `/linkita [code]` uses a code created elsewhere and `/linkita remove` unlinks this account. \
+        `/vahti [url] endpoints=discord,telegram` then chooses the linked accounts a vahti delivers to"
+    )]
+    Linkita(String),
+    #[command(
+        description = "Show or change your settings with `/asetukset timezone=Europe/Helsinki quiet=22:00-07:00`, `off` resets a setting"
+    )]
+    Asetukset(String),
+    #[cfg(feature = "webhook-delivery")]
+    #[command(
+        description = "Add a webhook with `/webhook [url] [secret]` and use it with `/vahti [url] to=webhook:N`, \
+        `/webhook remove [id]` removes one and without arguments lists your webhooks"
+    )]
+    Webhook(String),
+    #[cfg(feature = "email-delivery")]
+    #[command(
+        description = "Add an email address with `/sahkoposti [address]`, confirm it with `/sahkoposti verify [code]` \
+        and use it with `/vahti [url] to=email:N`. `/sahkoposti remove [id]` removes one and without arguments lists your addresses"
+    )]
+    Sahkoposti(String),
+    #[cfg(feature = "push-delivery")]
+    #[command(
+        description = "Add a push target with `/push ntfy [server] [topic] [token]` or `/push gotify [server] [token]` \
+        and use it with `/vahti [url] to=push:N`. `/push remove [id]` removes one and without arguments lists your push targets"
+    )]
+    Push(String),
+}
+
+/// Whether the user may manage the Vahtis and the blacklist of the chat,
+/// in groups and channels this is limited to the admins
+async fn is_admin(bot: &Bot, chat: &Chat, user: UserId) -> bool {
+    if chat.is_private() {
+        return true;
+    }
+
+    bot.get_chat_member(chat.id, user)
+        .await
+        .map(|m| m.is_privileged())
+        .unwrap_or(false)
+}
+
+/// Whether the sender of the message may manage the Vahtis and the blacklist of the chat.
+/// Only the admins can post into a channel or as the group itself
+async fn sender_is_admin(bot: &Bot, msg: &Message) -> bool {
+    if msg.chat.is_channel() || msg.sender_chat().is_some_and(|c| c.id == msg.chat.id) {
+        return true;
+    }
+
+    match msg.from() {
+        Some(user) => is_admin(bot, &msg.chat, user.id).await,
+        None => false,
+    }
 }
 
 async fn handle(bot: Bot, msg: Message, cmd: TelegramCommand, db: Database) -> ResponseResult<()> {
+    // NOTE: These act on the chat, so in groups and channels they are limited to the admins
+    let manages_chat = match cmd {
+        TelegramCommand::Vahti(_)
+        | TelegramCommand::PoistaVahti(_)
+        | TelegramCommand::Tauota(_)
+        | TelegramCommand::Jatka(_)
+        | TelegramCommand::Torkku(_)
+        | TelegramCommand::Kooste(_)
+        | TelegramCommand::Esto(_)
+        | TelegramCommand::PoistaEsto(_)
+        | TelegramCommand::Seuraa(_)
+        | TelegramCommand::Asetukset(_) => true,
+        #[cfg(feature = "huutonet")]
+        TelegramCommand::Muistuta(_) => true,
+        #[cfg(feature = "webhook-delivery")]
+        TelegramCommand::Webhook(_) => true,
+        #[cfg(feature = "email-delivery")]
+        TelegramCommand::Sahkoposti(_) => true,
+        #[cfg(feature = "push-delivery")]
+        TelegramCommand::Push(_) => true,
+        _ => false,
+    };
+    if manages_chat && !sender_is_admin(&bot, &msg).await {
+        bot.throttle(Limits::default())
+            .send_message(
+                msg.chat.id,
+                "Only the admins of the chat can manage its vahtis, settings and blocked sellers",
+            )
+            .await?;
+        return Ok(());
+    }
+
+    let keyboard = match &cmd {
+        TelegramCommand::PoistaEsto(v) if v.trim().is_empty() => {
+            poistaesto::keyboard(&msg, &db).await
+        }
+        _ => None,
+    };
+
+    let welcome = welcome_back(&db, msg.chat.id.0 as u64, crate::delivery::telegram::ID).await;
+
     let response = match cmd {
         TelegramCommand::Vahti(v) => vahti::run(msg.clone(), v, db).await,
-        TelegramCommand::PoistaVahti(v) => poistavahti::run(msg.clone(), v, db).await,
+        TelegramCommand::PoistaVahti(v) => poistavahti::run(&bot, msg.clone(), v, db).await,
+        TelegramCommand::Tauota(v) => tauota::run(msg.clone(), v, db).await,
+        TelegramCommand::Jatka(v) => jatka::run(msg.clone(), v, db).await,
+        TelegramCommand::Torkku(v) => torkku::run(msg.clone(), v, db).await,
+        TelegramCommand::Esto(v) => esto::run(msg.clone(), v, db).await,
+        TelegramCommand::PoistaEsto(v) => poistaesto::run(msg.clone(), v, db).await,
+        TelegramCommand::Estot => estot::run(msg.clone(), db).await,
+        #[cfg(feature = "huutonet")]
+        TelegramCommand::Muistuta(v) => muistuta::run(msg.clone(), v, db).await,
+        TelegramCommand::Seuraa(v) => seuraa::run(msg.clone(), v, db).await,
+        TelegramCommand::Kooste(v) => kooste::run(msg.clone(), v, db).await,
+        TelegramCommand::Linkita(v) => linkita::run(msg.clone(), v, db).await,
+        TelegramCommand::Asetukset(v) => asetukset::run(msg.clone(), v, db).await,
+        #[cfg(feature = "webhook-delivery")]
+        TelegramCommand::Webhook(v) => webhook::run(msg.clone(), v, db).await,
+        #[cfg(feature = "email-delivery")]
+        TelegramCommand::Sahkoposti(v) => sahkoposti::run(msg.clone(), v, db).await,
+        #[cfg(feature = "push-delivery")]
+        TelegramCommand::Push(v) => push::run(msg.clone(), v, db).await,
         TelegramCommand::Help => help::run().await,
         TelegramCommand::Start => start::run().await,
     }
     .unwrap_or(String::from(
         "Ran into an unhandled error while processing the command",
     ));
+    let response = match welcome {
+        Some(w) => format!("{}\n\n{}", w, response),
+        None => response,
+    };
 
-    bot.throttle(Limits::default())
+    let request = bot
+        .throttle(Limits::default())
         .send_message(msg.chat.id, response)
-        .disable_web_page_preview(true)
+        .disable_web_page_preview(true);
+    match keyboard {
+        Some(keyboard) => request.reply_markup(keyboard).await?,
+        None => request.await?,
+    };
+    Ok(())
+}
+
+/// Performs the action of an inline keyboard button on behalf of the chat
+async fn run_action(db: Database, chat: ChatId, action: TelegramAction) -> Result<String, Error> {
+    let userid = chat.0 as u64;
+    match action {
+        TelegramAction::BlockSeller { site_id, seller_id } => {
+            block_seller(
+                db,
+                userid,
+                crate::delivery::telegram::ID,
+                seller_id,
+                site_id,
+            )
+            .await
+        }
+        TelegramAction::UnblockSeller { site_id, seller_id } => {
+            unblock_seller(
+                db,
+                userid,
+                crate::delivery::telegram::ID,
+                seller_id,
+                site_id,
+            )
+            .await
+        }
+        TelegramAction::RemoveVahti { vahti_id } | TelegramAction::MuteVahti { vahti_id } => {
+            // NOTE: The Vahti may belong to a linked account
+            let vahti = match db.fetch_vahti_by_id(vahti_id).await {
+                Ok(v) if owns_vahti(&db, &v, userid, crate::delivery::telegram::ID).await => v,
+                _ => return Ok(String::from("The Vahti no longer exists")),
+            };
+
+            if let TelegramAction::RemoveVahti { .. } = action {
+                return remove_vahti(db, &vahti.url, vahti.user_id as u64, vahti.delivery_method)
+                    .await;
+            }
+
+            if vahti.paused {
+                db.resume_vahti(vahti_id).await?;
+                Ok(String::from("Vahti unmuted"))
+            } else {
+                db.pause_vahti(vahti_id).await?;
+                Ok(String::from(
+                    "Vahti muted, press the button again to unmute it",
+                ))
+            }
+        }
+    }
+}
+
+async fn handle_callback(bot: Bot, q: CallbackQuery, db: Database) -> ResponseResult<()> {
+    let action = q
+        .data
+        .as_deref()
+        .and_then(|d| d.parse::<TelegramAction>().ok());
+    let chat = q.message.as_ref().map(|m| &m.chat);
+
+    let response = match (action, chat) {
+        (Some(_), Some(chat)) if !is_admin(&bot, chat, q.from.id).await => {
+            String::from("Only the admins of the chat can manage its vahtis and blocked sellers")
+        }
+        (Some(action), Some(chat)) => {
+            run_action(db, chat.id, action)
+                .await
+                .unwrap_or(String::from(
+                    "Ran into an unhandled error while processing the command",
+                ))
+        }
+        _ => String::from("Unknown action"),
+    };
+
+    bot.throttle(Limits::default())
+        .answer_callback_query(q.id)
+        .text(response)
         .await?;
     Ok(())
 }
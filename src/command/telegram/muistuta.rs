@@ -0,0 +1,57 @@
+use chrono::{Local, TimeZone};
+use teloxide::prelude::*;
+
+use crate::database::Database;
+use crate::huutonet::api::item_id_from_url;
+use crate::reminder::watch_auction;
+
+pub async fn run(msg: Message, args: String, db: Database) -> ResponseResult<String> {
+    let userid = msg.chat.id.0 as u64;
+
+    if args.trim().is_empty() {
+        let watches = db
+            .fetch_auction_watches_by_user_id(userid as i64, crate::delivery::telegram::ID)
+            .await
+            .unwrap_or_default()
+            .iter()
+            .map(|w| {
+                format!(
+                    "{} ({}) closes at {}",
+                    w.title,
+                    w.url,
+                    Local
+                        .timestamp_opt(w.closing_time, 0)
+                        .unwrap()
+                        .format("%d/%m/%Y %R")
+                )
+            })
+            .collect::<Vec<_>>();
+
+        if watches.is_empty() {
+            return Ok(String::from("You are not watching any auctions"));
+        }
+
+        return Ok("You are watching the following auctions\n".to_owned() + &watches.join("\n"));
+    }
+
+    let mut args = args.split_whitespace();
+    let Some(itemid) = args.next().and_then(item_id_from_url) else {
+        return Ok(String::from("Invalid huuto.net item url"));
+    };
+
+    let remind_before = match args.next().map(|m| m.parse::<u32>()) {
+        Some(Ok(minutes)) if minutes > 0 => i64::from(minutes) * 60,
+        Some(_) => return Ok(String::from("Invalid amount of minutes")),
+        None => *crate::AUCTION_REMINDER_BEFORE,
+    };
+
+    Ok(watch_auction(
+        db,
+        itemid,
+        userid,
+        crate::delivery::telegram::ID,
+        remind_before,
+    )
+    .await
+    .unwrap_or_else(|e| e.to_string()))
+}
@@ -0,0 +1,59 @@
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+
+use crate::blacklist::{seller_from_url, seller_name, unblock_seller};
+use crate::database::Database;
+use crate::delivery::telegram::TelegramAction;
+
+pub async fn run(msg: Message, args: String, db: Database) -> ResponseResult<String> {
+    if args.trim().is_empty() {
+        let blocked = db
+            .fetch_user_blacklist(msg.chat.id.0, crate::delivery::telegram::ID)
+            .await
+            .unwrap_or_default();
+
+        if blocked.is_empty() {
+            return Ok(String::from("You have no blocked sellers"));
+        }
+
+        return Ok(String::from("Choose the seller to unblock"));
+    }
+
+    let Some((siteid, sellerid)) = seller_from_url(args.trim()) else {
+        return Ok(String::from("Please provide a seller url"));
+    };
+
+    Ok(unblock_seller(
+        db,
+        msg.chat.id.0 as u64,
+        crate::delivery::telegram::ID,
+        sellerid,
+        siteid,
+    )
+    .await
+    .unwrap_or_else(|e| e.to_string()))
+}
+
+/// A keyboard with a button for unblocking each of the blocked sellers
+pub async fn keyboard(msg: &Message, db: &Database) -> Option<InlineKeyboardMarkup> {
+    let blocked = db
+        .fetch_user_blacklist(msg.chat.id.0, crate::delivery::telegram::ID)
+        .await
+        .ok()?;
+    if blocked.is_empty() {
+        return None;
+    }
+
+    let mut rows = vec![];
+    for (sellerid, siteid) in blocked {
+        rows.push(vec![InlineKeyboardButton::callback(
+            seller_name(sellerid, siteid).await,
+            TelegramAction::UnblockSeller {
+                site_id: siteid,
+                seller_id: sellerid,
+            }
+            .to_string(),
+        )]);
+    }
+    Some(InlineKeyboardMarkup::new(rows))
+}
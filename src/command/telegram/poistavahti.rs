@@ -1,22 +1,43 @@
 use teloxide::prelude::*;
+use teloxide::types::UserId;
 
 use crate::database::Database;
 use crate::vahti::remove_vahti;
 
-pub async fn run(msg: Message, vahti: String, db: Database) -> ResponseResult<String> {
+/// The name of the user who added the Vahti into a group or a channel
+async fn creator_name(bot: &Bot, chat: ChatId, creator: Option<i64>) -> Option<String> {
+    let creator = creator?;
+    Some(
+        match bot.get_chat_member(chat, UserId(creator as u64)).await {
+            Ok(m) => m.user.full_name(),
+            Err(_) => creator.to_string(),
+        },
+    )
+}
+
+pub async fn run(bot: &Bot, msg: Message, vahti: String, db: Database) -> ResponseResult<String> {
     if vahti.is_empty() {
-        let vahdit = db
-            .fetch_vahti_entries_by_user_id(msg.chat.id.0)
+        let vahtis = db
+            .fetch_vahti_entries_by_user_id(msg.chat.id.0, crate::delivery::telegram::ID)
             .await
-            .unwrap_or(Vec::new())
-            .iter()
-            .map(|v| v.url.clone())
-            .collect::<Vec<_>>();
+            .unwrap_or(Vec::new());
 
-        if vahdit.is_empty() {
+        if vahtis.is_empty() {
             return Ok(String::from("You have no registered Vahtis"));
         }
 
+        let mut vahdit = vec![];
+        for v in vahtis {
+            vahdit.push(if msg.chat.is_private() {
+                v.url
+            } else {
+                match creator_name(bot, msg.chat.id, v.creator_id).await {
+                    Some(name) => format!("{} (added by {})", v.url, name),
+                    None => v.url,
+                }
+            });
+        }
+
         return Ok(
             "Please provide a Vahti url, here are your registered Vahtis\n".to_owned()
                 + &vahdit.join("\n"),
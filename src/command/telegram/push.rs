@@ -0,0 +1,45 @@
+use teloxide::prelude::*;
+
+use crate::database::Database;
+use crate::delivery::push::{add_push_target, list_push_targets, remove_push_target, PushService};
+
+const USAGE: &str = "Usage: /push ntfy [server] [topic] [token] or /push gotify [server] [token]";
+
+pub async fn run(msg: Message, args: String, db: Database) -> ResponseResult<String> {
+    let userid = msg.chat.id.0 as u64;
+    let args = args.split_whitespace().collect::<Vec<_>>();
+
+    let (service, server, topic, token) = match args.as_slice() {
+        [] => {
+            return Ok(list_push_targets(db, userid, crate::delivery::telegram::ID)
+                .await
+                .unwrap_or_else(|e| e.to_string()))
+        }
+        ["remove", id] => {
+            let Ok(id) = id.trim_start_matches("push:").parse::<i32>() else {
+                return Ok(String::from("Usage: /push remove [id]"));
+            };
+            return Ok(
+                remove_push_target(db, id, userid, crate::delivery::telegram::ID)
+                    .await
+                    .unwrap_or_else(|e| e.to_string()),
+            );
+        }
+        ["ntfy", server, topic] => (PushService::Ntfy, server, Some(topic), None),
+        ["ntfy", server, topic, token] => (PushService::Ntfy, server, Some(topic), Some(token)),
+        ["gotify", server, token] => (PushService::Gotify, server, None, Some(token)),
+        _ => return Ok(String::from(USAGE)),
+    };
+
+    Ok(add_push_target(
+        db,
+        service,
+        server,
+        topic.map(|t| t.to_string()),
+        token.map(|t| t.to_string()),
+        userid,
+        crate::delivery::telegram::ID,
+    )
+    .await
+    .unwrap_or_else(|e| e.to_string()))
+}
@@ -0,0 +1,30 @@
+use teloxide::prelude::*;
+
+use crate::database::Database;
+use crate::delivery::email::{
+    add_email_address, list_email_addresses, remove_email_address, verify_email_address, Mailer,
+};
+
+pub async fn run(msg: Message, args: String, db: Database) -> ResponseResult<String> {
+    let userid = msg.chat.id.0 as u64;
+    let args = args.split_whitespace().collect::<Vec<_>>();
+
+    Ok(match args.as_slice() {
+        [] => list_email_addresses(db, userid, crate::delivery::telegram::ID).await,
+        ["verify", code] => {
+            verify_email_address(db, code, userid, crate::delivery::telegram::ID).await
+        }
+        ["remove", id] => match id.trim_start_matches("email:").parse::<i32>() {
+            Ok(id) => remove_email_address(db, id, userid, crate::delivery::telegram::ID).await,
+            Err(_) => return Ok(String::from("Usage: /sahkoposti remove [id]")),
+        },
+        [address] => match Mailer::from_env() {
+            Ok(mailer) => {
+                add_email_address(db, &mailer, address, userid, crate::delivery::telegram::ID).await
+            }
+            Err(e) => Err(e),
+        },
+        _ => return Ok(String::from("Usage: /sahkoposti [address]")),
+    }
+    .unwrap_or_else(|e| e.to_string()))
+}
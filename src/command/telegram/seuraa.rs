@@ -0,0 +1,34 @@
+use teloxide::prelude::*;
+
+use crate::bookmark::{bookmark_item, item_from_url};
+use crate::database::Database;
+
+pub async fn run(msg: Message, args: String, db: Database) -> ResponseResult<String> {
+    let userid = msg.chat.id.0 as u64;
+
+    if args.trim().is_empty() {
+        let bookmarks = db
+            .fetch_bookmarks_by_user_id(userid as i64, crate::delivery::telegram::ID)
+            .await
+            .unwrap_or_default()
+            .iter()
+            .map(|b| format!("{} ({})", b.title, b.url))
+            .collect::<Vec<_>>();
+
+        if bookmarks.is_empty() {
+            return Ok(String::from("You are not following any listings"));
+        }
+
+        return Ok("You are following the following listings\n".to_owned() + &bookmarks.join("\n"));
+    }
+
+    let Some((siteid, adid)) = item_from_url(args.trim()) else {
+        return Ok(String::from("Invalid listing url"));
+    };
+
+    Ok(
+        bookmark_item(db, siteid, adid, userid, crate::delivery::telegram::ID)
+            .await
+            .unwrap_or_else(|e| e.to_string()),
+    )
+}
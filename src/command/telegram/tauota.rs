@@ -0,0 +1,19 @@
+use teloxide::prelude::*;
+
+use crate::database::Database;
+use crate::vahti::pause_vahti;
+
+pub async fn run(msg: Message, url: String, db: Database) -> ResponseResult<String> {
+    if url.trim().is_empty() {
+        return Ok(String::from("Usage: /tauota [url]"));
+    }
+
+    Ok(pause_vahti(
+        db,
+        url.trim(),
+        msg.chat.id.0 as u64,
+        crate::delivery::telegram::ID,
+    )
+    .await
+    .unwrap_or_else(|e| e.to_string()))
+}
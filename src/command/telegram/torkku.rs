@@ -0,0 +1,26 @@
+use teloxide::prelude::*;
+
+use crate::database::Database;
+use crate::vahti::{parse_duration, snooze_vahti};
+
+pub async fn run(msg: Message, args: String, db: Database) -> ResponseResult<String> {
+    let mut args = args.split_whitespace();
+    let (Some(url), Some(duration)) = (args.next(), args.next()) else {
+        return Ok(String::from("Usage: /torkku [url] [30m|12h|3d|2w]"));
+    };
+
+    let duration = match parse_duration(duration) {
+        Ok(d) => d,
+        Err(e) => return Ok(e.to_string()),
+    };
+
+    Ok(snooze_vahti(
+        db,
+        url,
+        msg.chat.id.0 as u64,
+        crate::delivery::telegram::ID,
+        duration,
+    )
+    .await
+    .unwrap_or_else(|e| e.to_string()))
+}
@@ -1,19 +1,84 @@
 use teloxide::prelude::*;
 
 use crate::database::Database;
-use crate::vahti::new_vahti;
+use crate::filters::VahtiFilters;
+use crate::identity::set_vahti_endpoints;
+use crate::vahti::{
+    canonicalize_url, new_vahti, resolve_destination, set_vahti_filters, show_vahti_filters,
+};
 
 pub async fn run(msg: Message, vahti: String, db: Database) -> ResponseResult<String> {
     if vahti.is_empty() {
         return Ok(String::from("No url provided"));
     }
 
-    Ok(new_vahti(
-        db,
-        &vahti,
+    let (url, spec) = vahti
+        .trim()
+        .split_once(char::is_whitespace)
+        .unwrap_or((vahti.trim(), ""));
+    let url = &canonicalize_url(url);
+
+    // NOTE: `to=webhook:N` sets the destination and `endpoints=discord,telegram`
+    // the linked accounts, neither is a filter
+    let destination = spec.split_whitespace().find_map(|t| t.strip_prefix("to="));
+    let endpoints = spec
+        .split_whitespace()
+        .find_map(|t| t.strip_prefix("endpoints="));
+    let spec = spec
+        .split_whitespace()
+        .filter(|t| !t.starts_with("to=") && !t.starts_with("endpoints="))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let filters = match spec.trim() {
+        "clear" => VahtiFilters::default(),
+        s => match s.parse::<VahtiFilters>() {
+            Ok(f) => f,
+            Err(e) => return Ok(e.to_string()),
+        },
+    };
+
+    // NOTE: Channel posts and anonymous group admins don't reveal the user
+    let creator = msg
+        .from()
+        .filter(|_| msg.sender_chat().is_none())
+        .map(|u| u.id.0);
+
+    let (userid, delivery_method) = match resolve_destination(
+        &db,
+        destination,
         msg.chat.id.0 as u64,
         crate::delivery::telegram::ID,
     )
     .await
+    {
+        Ok(d) => d,
+        Err(e) => return Ok(e.to_string()),
+    };
+
+    let res = if db
+        .fetch_vahti(url, userid as i64, delivery_method)
+        .await
+        .is_ok()
+    {
+        if spec.trim().is_empty() && endpoints.is_some() {
+            Ok(String::new())
+        } else if spec.trim().is_empty() {
+            show_vahti_filters(db.clone(), url, userid, delivery_method)
+                .await
+                .map(|f| format!("The Vahti already exists\n{}", f))
+        } else {
+            set_vahti_filters(db.clone(), url, userid, delivery_method, filters).await
+        }
+    } else {
+        new_vahti(db.clone(), url, userid, delivery_method, creator, filters).await
+    };
+
+    Ok(match (res, endpoints) {
+        (Ok(msg), Some(spec)) => set_vahti_endpoints(db, url, userid, delivery_method, spec)
+            .await
+            .map(|e| format!("{}\n{}", msg, e).trim().to_string()),
+        (res, _) => res,
+    }
     .unwrap_or_else(|e| e.to_string()))
 }
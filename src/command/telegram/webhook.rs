@@ -0,0 +1,30 @@
+use teloxide::prelude::*;
+
+use crate::database::Database;
+use crate::delivery::webhook::{add_webhook, list_webhooks, remove_webhook};
+
+pub async fn run(msg: Message, args: String, db: Database) -> ResponseResult<String> {
+    let userid = msg.chat.id.0 as u64;
+    let args = args.split_whitespace().collect::<Vec<_>>();
+
+    Ok(match args.as_slice() {
+        [] => list_webhooks(db, userid, crate::delivery::telegram::ID).await,
+        ["remove", id] => match id.trim_start_matches("webhook:").parse::<i32>() {
+            Ok(id) => remove_webhook(db, id, userid, crate::delivery::telegram::ID).await,
+            Err(_) => return Ok(String::from("Usage: /webhook remove [id]")),
+        },
+        [url] => add_webhook(db, url, None, userid, crate::delivery::telegram::ID).await,
+        [url, secret] => {
+            add_webhook(
+                db,
+                url,
+                Some(secret.to_string()),
+                userid,
+                crate::delivery::telegram::ID,
+            )
+            .await
+        }
+        _ => return Ok(String::from("Usage: /webhook [url] [secret]")),
+    }
+    .unwrap_or_else(|e| e.to_string()))
+}
@@ -6,6 +6,7 @@ use diesel::connection::SimpleConnection;
 use diesel::prelude::*;
 use diesel::r2d2::{ConnectionManager, CustomizeConnection, Pool};
 use diesel::sqlite::SqliteConnection;
+use itertools::Itertools;
 use serenity::prelude::TypeMapKey;
 
 use crate::error::Error;
@@ -50,7 +51,10 @@ impl Database {
         dotenv::dotenv().ok();
 
         let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        Self::from_url(&database_url)
+    }
 
+    pub fn from_url(database_url: &str) -> Database {
         let manager = ConnectionManager::<SqliteConnection>::new(database_url);
         let database = Pool::builder()
             .max_size(16)
@@ -71,6 +75,8 @@ impl Database {
         userid: i64,
         site_id: i32,
         delivery_method: i32,
+        filters: Option<String>,
+        creator_id: Option<i64>,
     ) -> Result<usize, Error> {
         let time = chrono::Local::now().timestamp();
         info!("Adding Vahti `{}` for the user {}", arg_url, userid);
@@ -81,10 +87,19 @@ impl Database {
             user_id: userid,
             site_id,
             delivery_method,
+            filters,
+            creator_id,
         };
-        Ok(diesel::insert_into(Vahdit::table)
+        diesel::insert_into(Vahdit::table)
             .values(&new_vahti)
-            .execute(&self.database.get()?)?)
+            .execute(&self.database.get()?)
+            .map_err(|e| match e {
+                diesel::result::Error::DatabaseError(
+                    diesel::result::DatabaseErrorKind::UniqueViolation,
+                    _,
+                ) => Error::VahtiExists,
+                e => e.into(),
+            })
     }
 
     pub async fn remove_vahti_entry(
@@ -95,13 +110,120 @@ impl Database {
     ) -> Result<usize, Error> {
         info!("Removing Vahti `{}` from the user {}", arg_url, userid);
         use crate::schema::Vahdit::dsl::*;
-        Ok(diesel::delete(
+        use crate::schema::{DigestItems, VahtiDestinations};
+        let conn = self.database.get()?;
+        conn.transaction::<_, Error, _>(|| {
+            let vahti = Vahdit.filter(
+                url.eq(arg_url)
+                    .and(user_id.eq(userid))
+                    .and(delivery_method.eq(delivery)),
+            );
+            let ids = vahti.select(id).load::<i32>(&conn)?;
+            diesel::delete(DigestItems::table.filter(DigestItems::vahti_id.eq_any(&ids)))
+                .execute(&conn)?;
+            diesel::delete(
+                VahtiDestinations::table.filter(VahtiDestinations::vahti_id.eq_any(&ids)),
+            )
+            .execute(&conn)?;
+            Ok(diesel::delete(vahti).execute(&conn)?)
+        })
+    }
+
+    /// Rewrites the urls of the Vahtis and removes the duplicates in a single transaction
+    pub async fn rewrite_vahti_urls(
+        &self,
+        rewrites: &[(i32, String)],
+        removed: &[i32],
+    ) -> Result<(), Error> {
+        info!(
+            "Rewriting the urls of {} Vahtis and removing {} duplicates",
+            rewrites.len(),
+            removed.len()
+        );
+        use crate::schema::Vahdit::dsl::*;
+        use crate::schema::{DigestItems, VahtiDestinations};
+        let conn = self.database.get()?;
+        conn.transaction::<_, Error, _>(|| {
+            diesel::delete(DigestItems::table.filter(DigestItems::vahti_id.eq_any(removed)))
+                .execute(&conn)?;
+            diesel::delete(
+                VahtiDestinations::table.filter(VahtiDestinations::vahti_id.eq_any(removed)),
+            )
+            .execute(&conn)?;
+            diesel::delete(Vahdit.filter(id.eq_any(removed))).execute(&conn)?;
+            for (vahtiid, new_url) in rewrites {
+                diesel::update(Vahdit.filter(id.eq(vahtiid)))
+                    .set(url.eq(new_url))
+                    .execute(&conn)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// The version of a one-off conversion of the stored data, 0 if it hasn't been run
+    pub async fn fetch_data_version(&self, data: &str) -> Result<i32, Error> {
+        use crate::schema::DataVersions::dsl::*;
+        Ok(DataVersions
+            .filter(name.eq(data))
+            .select(version)
+            .first::<i32>(&self.database.get()?)
+            .optional()?
+            .unwrap_or(0))
+    }
+
+    pub async fn set_data_version(&self, data: &str, new_version: i32) -> Result<usize, Error> {
+        use crate::schema::DataVersions::dsl::*;
+        Ok(diesel::replace_into(DataVersions)
+            .values((name.eq(data), version.eq(new_version)))
+            .execute(&self.database.get()?)?)
+    }
+
+    pub async fn update_vahti_filters(
+        &self,
+        arg_url: &str,
+        userid: i64,
+        delivery: i32,
+        arg_filters: Option<String>,
+    ) -> Result<usize, Error> {
+        info!(
+            "Updating the filters of Vahti `{}` of user {}",
+            arg_url, userid
+        );
+        use crate::schema::Vahdit::dsl::*;
+        Ok(diesel::update(
             Vahdit.filter(
                 url.eq(arg_url)
                     .and(user_id.eq(userid))
                     .and(delivery_method.eq(delivery)),
             ),
         )
+        .set(filters.eq(arg_filters))
+        .execute(&self.database.get()?)?)
+    }
+
+    pub async fn update_vahti_digest(
+        &self,
+        arg_url: &str,
+        userid: i64,
+        delivery: i32,
+        arg_digest: Option<String>,
+    ) -> Result<usize, Error> {
+        info!(
+            "Updating the digest of Vahti `{}` of user {}",
+            arg_url, userid
+        );
+        use crate::schema::Vahdit::dsl::*;
+        Ok(diesel::update(
+            Vahdit.filter(
+                url.eq(arg_url)
+                    .and(user_id.eq(userid))
+                    .and(delivery_method.eq(delivery)),
+            ),
+        )
+        .set((
+            digest.eq(arg_digest),
+            last_digest.eq(chrono::Local::now().timestamp()),
+        ))
         .execute(&self.database.get()?)?)
     }
 
@@ -113,22 +235,82 @@ impl Database {
             .load::<DbVahti>(&self.database.get()?)?)
     }
 
-    pub async fn fetch_vahti_entries_by_user_id(&self, userid: i64) -> Result<Vec<DbVahti>, Error> {
-        info!("Fetching the Vahtis of user {}...", userid);
+    pub async fn fetch_vahti_entries_by_user_id(
+        &self,
+        userid: i64,
+        delivery: i32,
+    ) -> Result<Vec<DbVahti>, Error> {
+        info!("Fetching the Vahtis of user {} ({})...", userid, delivery);
         use crate::schema::Vahdit::dsl::*;
         Ok(Vahdit
-            .filter(user_id.eq(userid))
+            .filter(user_id.eq(userid).and(delivery_method.eq(delivery)))
             .load::<DbVahti>(&self.database.get()?)?)
     }
 
-    pub async fn fetch_vahti(&self, arg_url: &str, userid: i64) -> Result<DbVahti, Error> {
+    pub async fn fetch_vahti(
+        &self,
+        arg_url: &str,
+        userid: i64,
+        delivery: i32,
+    ) -> Result<DbVahti, Error> {
         info!("Fetching the user {}'s Vahti {}...", userid, arg_url);
         use crate::schema::Vahdit::dsl::*;
         Ok(Vahdit
-            .filter(user_id.eq(userid).and(url.eq(arg_url)))
+            .filter(
+                url.eq(arg_url)
+                    .and(user_id.eq(userid))
+                    .and(delivery_method.eq(delivery)),
+            )
             .first::<DbVahti>(&self.database.get()?)?)
     }
 
+    pub async fn fetch_vahti_by_id(&self, vahtiid: i32) -> Result<DbVahti, Error> {
+        info!("Fetching Vahti {}...", vahtiid);
+        use crate::schema::Vahdit::dsl::*;
+        Ok(Vahdit
+            .filter(id.eq(vahtiid))
+            .first::<DbVahti>(&self.database.get()?)?)
+    }
+
+    pub async fn pause_vahti(&self, vahtiid: i32) -> Result<usize, Error> {
+        info!("Pausing Vahti {}", vahtiid);
+        use crate::schema::Vahdit::dsl::*;
+        Ok(diesel::update(Vahdit.filter(id.eq(vahtiid)))
+            .set(paused.eq(true))
+            .execute(&self.database.get()?)?)
+    }
+
+    pub async fn snooze_vahti(&self, vahtiid: i32, until: i64) -> Result<usize, Error> {
+        info!("Snoozing Vahti {} until {}", vahtiid, until);
+        use crate::schema::Vahdit::dsl::*;
+        Ok(diesel::update(Vahdit.filter(id.eq(vahtiid)))
+            .set(snoozed_until.eq(Some(until)))
+            .execute(&self.database.get()?)?)
+    }
+
+    /// Unpauses and unsnoozes the Vahti. The items published in the meantime
+    /// are skipped by moving `last_updated` to the current time
+    pub async fn resume_vahti(&self, vahtiid: i32) -> Result<usize, Error> {
+        info!("Resuming Vahti {}", vahtiid);
+        use crate::schema::Vahdit::dsl::*;
+        let time = chrono::Local::now().timestamp();
+        Ok(diesel::update(Vahdit.filter(id.eq(vahtiid)))
+            .set((
+                paused.eq(false),
+                snoozed_until.eq(None::<i64>),
+                last_updated.eq(time),
+            ))
+            .execute(&self.database.get()?)?)
+    }
+
+    /// Resumes the Vahtis whose snooze has ended by `now`, see `resume_vahti`
+    pub async fn resume_snoozed_vahtis(&self, now: i64) -> Result<usize, Error> {
+        use crate::schema::Vahdit::dsl::*;
+        Ok(diesel::update(Vahdit.filter(snoozed_until.le(now)))
+            .set((snoozed_until.eq(None::<i64>), last_updated.eq(now)))
+            .execute(&self.database.get()?)?)
+    }
+
     pub async fn fetch_all_vahtis(&self) -> Result<Vec<DbVahti>, Error> {
         info!("Fetching all Vahtis...");
         use crate::schema::Vahdit::dsl::*;
@@ -139,8 +321,11 @@ impl Database {
         // FIXME: This could be done in sql
         info!("Fetching all vahtis grouping them by url");
         let vahdit = self.fetch_all_vahtis().await?;
-        let ret: BTreeMap<String, Vec<DbVahti>> =
-            vahdit.into_iter().fold(BTreeMap::new(), |mut acc, v| {
+        // NOTE: Paused, snoozed and disabled Vahtis are left out as they are not to be updated
+        let ret: BTreeMap<String, Vec<DbVahti>> = vahdit
+            .into_iter()
+            .filter(|v| !v.paused && v.snoozed_until.is_none() && !v.disabled)
+            .fold(BTreeMap::new(), |mut acc, v| {
                 acc.entry(v.url.clone()).or_default().push(v);
                 acc
             });
@@ -165,18 +350,39 @@ impl Database {
         Ok(diesel::update(
             Vahdit.filter(
                 url.eq(vahti.url)
-                    .and(user_id.eq(vahti.user_id).and(last_updated.lt(time))),
+                    .and(user_id.eq(vahti.user_id))
+                    .and(delivery_method.eq(vahti.delivery_method))
+                    .and(last_updated.lt(time)),
             ),
         )
         .set(last_updated.eq(time))
         .execute(&self.database.get()?)?)
     }
 
-    pub async fn fetch_user_blacklist(&self, userid: i64) -> Result<Vec<(i32, i32)>, Error> {
-        debug!("Fetching the blacklist for user {}...", userid);
+    /// Sets or clears the fetch error of every Vahti with the url
+    pub async fn set_vahti_error(
+        &self,
+        arg_url: &str,
+        error: Option<String>,
+    ) -> Result<usize, Error> {
+        use crate::schema::Vahdit::dsl::*;
+        Ok(diesel::update(Vahdit.filter(url.eq(arg_url)))
+            .set(last_error.eq(error))
+            .execute(&self.database.get()?)?)
+    }
+
+    pub async fn fetch_user_blacklist(
+        &self,
+        userid: i64,
+        delivery: i32,
+    ) -> Result<Vec<(i32, i32)>, Error> {
+        debug!(
+            "Fetching the blacklist for user {} ({})...",
+            userid, delivery
+        );
         use crate::schema::Blacklists::dsl::*;
         Ok(Blacklists
-            .filter(user_id.eq(userid))
+            .filter(user_id.eq(userid).and(delivery_method.eq(delivery)))
             .select((seller_id, site_id))
             .load::<(i32, i32)>(&self.database.get()?)?)
     }
@@ -184,6 +390,7 @@ impl Database {
     pub async fn add_seller_to_blacklist(
         &self,
         userid: i64,
+        delivery: i32,
         sellerid: i32,
         siteid: i32,
     ) -> Result<usize, Error> {
@@ -196,6 +403,7 @@ impl Database {
             user_id: userid,
             seller_id: sellerid,
             site_id: siteid,
+            delivery_method: delivery,
         };
         Ok(diesel::insert_into(Blacklists::table)
             .values(new_entry)
@@ -205,6 +413,7 @@ impl Database {
     pub async fn remove_seller_from_blacklist(
         &self,
         userid: i64,
+        delivery: i32,
         sellerid: i32,
         siteid: i32,
     ) -> Result<usize, Error> {
@@ -217,10 +426,974 @@ impl Database {
             Blacklists.filter(
                 user_id
                     .eq(userid)
+                    .and(delivery_method.eq(delivery))
                     .and(seller_id.eq(sellerid))
                     .and(site_id.eq(siteid)),
             ),
         )
         .execute(&self.database.get()?)?)
     }
+
+    pub async fn fetch_seen_items(
+        &self,
+        userid: i64,
+        delivery: i32,
+    ) -> Result<Vec<(i64, i32, i64, i64)>, Error> {
+        debug!("Fetching the seen items of user {}...", userid);
+        use crate::schema::SeenItems::dsl::*;
+        Ok(SeenItems
+            .filter(user_id.eq(userid).and(delivery_method.eq(delivery)))
+            .select((ad_id, site_id, seen_at, price))
+            .load::<(i64, i32, i64, i64)>(&self.database.get()?)?)
+    }
+
+    pub async fn add_seen_items(
+        &self,
+        userid: i64,
+        delivery: i32,
+        vahtiid: i32,
+        items: &[(i64, i32, i64)],
+    ) -> Result<usize, Error> {
+        debug!("Adding {} seen items for user {}", items.len(), userid);
+        use crate::schema::SeenItems;
+        let time = chrono::Local::now().timestamp();
+        let new_items = items
+            .iter()
+            .map(|(aid, sid, p)| NewSeenItem {
+                user_id: userid,
+                delivery_method: delivery,
+                site_id: *sid,
+                ad_id: *aid,
+                seen_at: time,
+                price: *p,
+                vahti_id: Some(vahtiid),
+            })
+            .collect::<Vec<_>>();
+
+        // NOTE: Sqlite doesn't support batch inserts with `INSERT OR IGNORE`
+        let conn = self.database.get()?;
+        conn.transaction::<_, Error, _>(|| {
+            let mut count = 0;
+            for item in &new_items {
+                count += diesel::insert_or_ignore_into(SeenItems::table)
+                    .values(item)
+                    .execute(&conn)?;
+            }
+            Ok(count)
+        })
+    }
+
+    /// Counts the items first seen by the Vahti after `since`
+    pub async fn count_seen_items_by_vahti(&self, vahtiid: i32, since: i64) -> Result<i64, Error> {
+        use crate::schema::SeenItems::dsl::*;
+        Ok(SeenItems
+            .filter(vahti_id.eq(vahtiid).and(seen_at.ge(since)))
+            .count()
+            .get_result::<i64>(&self.database.get()?)?)
+    }
+
+    pub async fn update_seen_item_prices(
+        &self,
+        userid: i64,
+        delivery: i32,
+        items: &[(i64, i32, i64)],
+    ) -> Result<usize, Error> {
+        debug!(
+            "Updating the prices of {} seen items for user {}",
+            items.len(),
+            userid
+        );
+        use crate::schema::SeenItems::dsl::*;
+        let conn = self.database.get()?;
+        conn.transaction::<_, Error, _>(|| {
+            let mut count = 0;
+            for (aid, sid, p) in items {
+                count += diesel::update(
+                    SeenItems.filter(
+                        user_id
+                            .eq(userid)
+                            .and(delivery_method.eq(delivery))
+                            .and(site_id.eq(sid))
+                            .and(ad_id.eq(aid)),
+                    ),
+                )
+                .set(price.eq(p))
+                .execute(&conn)?;
+            }
+            Ok(count)
+        })
+    }
+
+    pub async fn purge_seen_items(&self, older_than: i64) -> Result<usize, Error> {
+        info!("Purging seen items older than {}", older_than);
+        use crate::schema::SeenItems::dsl::*;
+        Ok(diesel::delete(SeenItems.filter(seen_at.lt(older_than)))
+            .execute(&self.database.get()?)?)
+    }
+
+    pub async fn add_auction_watch(&self, watch: NewAuctionWatch) -> Result<usize, Error> {
+        info!(
+            "Adding a watch for auction {} for the user {}",
+            watch.item_id, watch.user_id
+        );
+        use crate::schema::AuctionWatches;
+        // NOTE: Watching the same auction again replaces the old watch
+        Ok(diesel::replace_into(AuctionWatches::table)
+            .values(&watch)
+            .execute(&self.database.get()?)?)
+    }
+
+    pub async fn fetch_auction_watches_by_user_id(
+        &self,
+        userid: i64,
+        delivery: i32,
+    ) -> Result<Vec<AuctionWatch>, Error> {
+        debug!("Fetching the auction watches of user {}...", userid);
+        use crate::schema::AuctionWatches::dsl::*;
+        Ok(AuctionWatches
+            .filter(user_id.eq(userid).and(delivery_method.eq(delivery)))
+            .order(closing_time.asc())
+            .load::<AuctionWatch>(&self.database.get()?)?)
+    }
+
+    /// Fetches the watches that should be reminded about at `now`
+    pub async fn fetch_due_auction_watches(&self, now: i64) -> Result<Vec<AuctionWatch>, Error> {
+        debug!("Fetching due auction watches...");
+        use crate::schema::AuctionWatches::dsl::*;
+        Ok(AuctionWatches
+            .filter(
+                reminded
+                    .eq(false)
+                    .and((closing_time - remind_before).le(now)),
+            )
+            .load::<AuctionWatch>(&self.database.get()?)?)
+    }
+
+    pub async fn update_auction_closing_time(
+        &self,
+        watchid: i32,
+        time: i64,
+    ) -> Result<usize, Error> {
+        use crate::schema::AuctionWatches::dsl::*;
+        Ok(diesel::update(AuctionWatches.filter(id.eq(watchid)))
+            .set(closing_time.eq(time))
+            .execute(&self.database.get()?)?)
+    }
+
+    pub async fn mark_auction_reminded(&self, watchid: i32) -> Result<usize, Error> {
+        use crate::schema::AuctionWatches::dsl::*;
+        Ok(diesel::update(AuctionWatches.filter(id.eq(watchid)))
+            .set(reminded.eq(true))
+            .execute(&self.database.get()?)?)
+    }
+
+    pub async fn purge_auction_watches(&self, closed_before: i64) -> Result<usize, Error> {
+        debug!("Purging auction watches closed before {}", closed_before);
+        use crate::schema::AuctionWatches::dsl::*;
+        Ok(
+            diesel::delete(AuctionWatches.filter(closing_time.lt(closed_before)))
+                .execute(&self.database.get()?)?,
+        )
+    }
+
+    pub async fn add_bookmark(&self, bookmark: NewBookmark) -> Result<usize, Error> {
+        info!(
+            "Adding a bookmark for item {} for the user {}",
+            bookmark.ad_id, bookmark.user_id
+        );
+        use crate::schema::Bookmarks;
+        Ok(diesel::replace_into(Bookmarks::table)
+            .values(&bookmark)
+            .execute(&self.database.get()?)?)
+    }
+
+    pub async fn fetch_bookmarks_by_user_id(
+        &self,
+        userid: i64,
+        delivery: i32,
+    ) -> Result<Vec<Bookmark>, Error> {
+        debug!("Fetching the bookmarks of user {}...", userid);
+        use crate::schema::Bookmarks::dsl::*;
+        Ok(Bookmarks
+            .filter(user_id.eq(userid).and(delivery_method.eq(delivery)))
+            .load::<Bookmark>(&self.database.get()?)?)
+    }
+
+    pub async fn fetch_all_bookmarks(&self) -> Result<Vec<Bookmark>, Error> {
+        debug!("Fetching all bookmarks...");
+        use crate::schema::Bookmarks::dsl::*;
+        Ok(Bookmarks.load::<Bookmark>(&self.database.get()?)?)
+    }
+
+    pub async fn remove_bookmark(&self, bookmarkid: i32) -> Result<usize, Error> {
+        use crate::schema::Bookmarks::dsl::*;
+        Ok(diesel::delete(Bookmarks.filter(id.eq(bookmarkid))).execute(&self.database.get()?)?)
+    }
+
+    pub async fn fetch_digest_vahtis(&self) -> Result<Vec<DbVahti>, Error> {
+        debug!("Fetching Vahtis with a digest...");
+        use crate::schema::Vahdit::dsl::*;
+        Ok(Vahdit
+            .filter(digest.is_not_null().and(disabled.eq(false)))
+            .load::<DbVahti>(&self.database.get()?)?)
+    }
+
+    pub async fn add_digest_items(&self, vahtiid: i32, items: Vec<String>) -> Result<usize, Error> {
+        debug!("Buffering {} items for Vahti {}", items.len(), vahtiid);
+        use crate::schema::DigestItems;
+        let time = chrono::Local::now().timestamp();
+        let conn = self.database.get()?;
+        conn.transaction::<_, Error, _>(|| {
+            let mut count = 0;
+            for item in items {
+                count += diesel::insert_into(DigestItems::table)
+                    .values(&NewDigestItem {
+                        vahti_id: vahtiid,
+                        item,
+                        added_at: time,
+                    })
+                    .execute(&conn)?;
+            }
+            Ok(count)
+        })
+    }
+
+    pub async fn fetch_digest_items(&self, vahtiid: i32) -> Result<Vec<DigestItem>, Error> {
+        use crate::schema::DigestItems::dsl::*;
+        Ok(DigestItems
+            .filter(vahti_id.eq(vahtiid))
+            .order(id.asc())
+            .load::<DigestItem>(&self.database.get()?)?)
+    }
+
+    /// Removes the buffered items of a Vahti after they have been sent and
+    /// marks the digest sent at `time`
+    pub async fn digest_sent(&self, vahtiid: i32, up_to: i32, time: i64) -> Result<(), Error> {
+        use crate::schema::{DigestItems, Vahdit};
+        let conn = self.database.get()?;
+        conn.transaction::<_, Error, _>(|| {
+            diesel::delete(
+                DigestItems::table.filter(
+                    DigestItems::vahti_id
+                        .eq(vahtiid)
+                        .and(DigestItems::id.le(up_to)),
+                ),
+            )
+            .execute(&conn)?;
+            diesel::update(Vahdit::table.filter(Vahdit::id.eq(vahtiid)))
+                .set(Vahdit::last_digest.eq(time))
+                .execute(&conn)?;
+            Ok(())
+        })
+    }
+
+    pub async fn fetch_user_settings(
+        &self,
+        userid: i64,
+        delivery: i32,
+    ) -> Result<Option<DbUserSettings>, Error> {
+        use crate::schema::UserSettings::dsl::*;
+        Ok(UserSettings
+            .filter(user_id.eq(userid).and(delivery_method.eq(delivery)))
+            .first::<DbUserSettings>(&self.database.get()?)
+            .optional()?)
+    }
+
+    pub async fn set_user_settings(&self, settings: NewUserSettings) -> Result<usize, Error> {
+        info!("Updating the settings of user {}", settings.user_id);
+        use crate::schema::UserSettings;
+        Ok(diesel::replace_into(UserSettings::table)
+            .values(&settings)
+            .execute(&self.database.get()?)?)
+    }
+
+    pub async fn add_held_items(
+        &self,
+        userid: i64,
+        delivery: i32,
+        items: Vec<String>,
+    ) -> Result<usize, Error> {
+        debug!("Holding {} items for user {}", items.len(), userid);
+        use crate::schema::HeldItems;
+        let time = chrono::Local::now().timestamp();
+        let conn = self.database.get()?;
+        conn.transaction::<_, Error, _>(|| {
+            let mut count = 0;
+            for item in items {
+                count += diesel::insert_into(HeldItems::table)
+                    .values(&NewHeldItem {
+                        user_id: userid,
+                        delivery_method: delivery,
+                        item,
+                        added_at: time,
+                    })
+                    .execute(&conn)?;
+            }
+            Ok(count)
+        })
+    }
+
+    /// Fetches the (user_id, delivery_method) pairs that have items held
+    pub async fn fetch_held_item_users(&self) -> Result<Vec<(i64, i32)>, Error> {
+        use crate::schema::HeldItems::dsl::*;
+        Ok(HeldItems
+            .select((user_id, delivery_method))
+            .distinct()
+            .load::<(i64, i32)>(&self.database.get()?)?)
+    }
+
+    pub async fn fetch_held_items(
+        &self,
+        userid: i64,
+        delivery: i32,
+    ) -> Result<Vec<HeldItem>, Error> {
+        use crate::schema::HeldItems::dsl::*;
+        Ok(HeldItems
+            .filter(user_id.eq(userid).and(delivery_method.eq(delivery)))
+            .order(id.asc())
+            .load::<HeldItem>(&self.database.get()?)?)
+    }
+
+    pub async fn remove_held_items(
+        &self,
+        userid: i64,
+        delivery: i32,
+        up_to: i32,
+    ) -> Result<usize, Error> {
+        use crate::schema::HeldItems::dsl::*;
+        Ok(diesel::delete(
+            HeldItems.filter(
+                user_id
+                    .eq(userid)
+                    .and(delivery_method.eq(delivery))
+                    .and(id.le(up_to)),
+            ),
+        )
+        .execute(&self.database.get()?)?)
+    }
+
+    /// Adds a webhook owned by the user, returning the id of the webhook
+    pub async fn add_webhook(&self, webhook: NewWebhook) -> Result<i32, Error> {
+        info!("Adding a webhook for the user {}", webhook.user_id);
+        use crate::schema::Webhooks::dsl::*;
+        let conn = self.database.get()?;
+        conn.transaction::<_, Error, _>(|| {
+            diesel::insert_into(Webhooks)
+                .values(&webhook)
+                .execute(&conn)?;
+            Ok(Webhooks.select(id).order(id.desc()).first::<i32>(&conn)?)
+        })
+    }
+
+    pub async fn fetch_webhook(&self, webhookid: i32) -> Result<Webhook, Error> {
+        use crate::schema::Webhooks::dsl::*;
+        Ok(Webhooks
+            .filter(id.eq(webhookid))
+            .first::<Webhook>(&self.database.get()?)?)
+    }
+
+    pub async fn fetch_webhooks_by_user_id(
+        &self,
+        userid: i64,
+        delivery: i32,
+    ) -> Result<Vec<Webhook>, Error> {
+        use crate::schema::Webhooks::dsl::*;
+        Ok(Webhooks
+            .filter(user_id.eq(userid).and(delivery_method.eq(delivery)))
+            .load::<Webhook>(&self.database.get()?)?)
+    }
+
+    pub async fn remove_webhook(&self, webhookid: i32) -> Result<usize, Error> {
+        info!("Removing webhook {}", webhookid);
+        use crate::schema::Webhooks::dsl::*;
+        Ok(diesel::delete(Webhooks.filter(id.eq(webhookid))).execute(&self.database.get()?)?)
+    }
+
+    /// Adds an unverified email address for the user, returning the id of the address
+    pub async fn add_email_address(&self, email: NewEmailAddress) -> Result<i32, Error> {
+        info!("Adding an email address for the user {}", email.user_id);
+        use crate::schema::EmailAddresses::dsl::*;
+        let conn = self.database.get()?;
+        conn.transaction::<_, Error, _>(|| {
+            diesel::insert_into(EmailAddresses)
+                .values(&email)
+                .execute(&conn)?;
+            Ok(EmailAddresses
+                .select(id)
+                .order(id.desc())
+                .first::<i32>(&conn)?)
+        })
+    }
+
+    pub async fn fetch_email_address(&self, emailid: i32) -> Result<EmailAddress, Error> {
+        use crate::schema::EmailAddresses::dsl::*;
+        Ok(EmailAddresses
+            .filter(id.eq(emailid))
+            .first::<EmailAddress>(&self.database.get()?)?)
+    }
+
+    pub async fn fetch_email_address_by_token(
+        &self,
+        token: &str,
+    ) -> Result<Option<EmailAddress>, Error> {
+        use crate::schema::EmailAddresses::dsl::*;
+        Ok(EmailAddresses
+            .filter(unsubscribe_token.eq(token))
+            .first::<EmailAddress>(&self.database.get()?)
+            .optional()?)
+    }
+
+    pub async fn fetch_email_addresses_by_user_id(
+        &self,
+        userid: i64,
+        delivery: i32,
+    ) -> Result<Vec<EmailAddress>, Error> {
+        use crate::schema::EmailAddresses::dsl::*;
+        Ok(EmailAddresses
+            .filter(user_id.eq(userid).and(delivery_method.eq(delivery)))
+            .load::<EmailAddress>(&self.database.get()?)?)
+    }
+
+    pub async fn verify_email_address(&self, emailid: i32) -> Result<usize, Error> {
+        info!("Verifying email address {}", emailid);
+        use crate::schema::EmailAddresses::dsl::*;
+        Ok(diesel::update(EmailAddresses.filter(id.eq(emailid)))
+            .set(verified.eq(true))
+            .execute(&self.database.get()?)?)
+    }
+
+    pub async fn remove_email_address(&self, emailid: i32) -> Result<usize, Error> {
+        info!("Removing email address {}", emailid);
+        use crate::schema::EmailAddresses::dsl::*;
+        Ok(diesel::delete(EmailAddresses.filter(id.eq(emailid))).execute(&self.database.get()?)?)
+    }
+
+    /// Returns the numeric id of the Matrix user, adding the user if needed.
+    /// The room of the user is updated to `room`
+    pub async fn matrix_user_id(&self, matrixid: &str, room: &str) -> Result<i32, Error> {
+        use crate::schema::MatrixUsers::dsl::*;
+        let conn = self.database.get()?;
+        conn.transaction::<_, Error, _>(|| {
+            if let Some(user) = MatrixUsers
+                .filter(matrix_id.eq(matrixid))
+                .first::<MatrixUser>(&conn)
+                .optional()?
+            {
+                if user.room_id != room {
+                    diesel::update(MatrixUsers.filter(id.eq(user.id)))
+                        .set(room_id.eq(room))
+                        .execute(&conn)?;
+                }
+                return Ok(user.id);
+            }
+
+            info!("Adding Matrix user {}", matrixid);
+            diesel::insert_into(MatrixUsers)
+                .values(&NewMatrixUser {
+                    matrix_id: matrixid.to_string(),
+                    room_id: room.to_string(),
+                })
+                .execute(&conn)?;
+            Ok(MatrixUsers
+                .filter(matrix_id.eq(matrixid))
+                .select(id)
+                .first::<i32>(&conn)?)
+        })
+    }
+
+    pub async fn fetch_matrix_user(&self, userid: i32) -> Result<MatrixUser, Error> {
+        use crate::schema::MatrixUsers::dsl::*;
+        Ok(MatrixUsers
+            .filter(id.eq(userid))
+            .first::<MatrixUser>(&self.database.get()?)?)
+    }
+
+    /// Adds a push target owned by the user, returning the id of the target
+    pub async fn add_push_target(&self, target: NewPushTarget) -> Result<i32, Error> {
+        info!("Adding a push target for the user {}", target.user_id);
+        use crate::schema::PushTargets::dsl::*;
+        let conn = self.database.get()?;
+        conn.transaction::<_, Error, _>(|| {
+            diesel::insert_into(PushTargets)
+                .values(&target)
+                .execute(&conn)?;
+            Ok(PushTargets
+                .select(id)
+                .order(id.desc())
+                .first::<i32>(&conn)?)
+        })
+    }
+
+    pub async fn fetch_push_target(&self, targetid: i32) -> Result<PushTarget, Error> {
+        use crate::schema::PushTargets::dsl::*;
+        Ok(PushTargets
+            .filter(id.eq(targetid))
+            .first::<PushTarget>(&self.database.get()?)?)
+    }
+
+    pub async fn fetch_push_targets_by_user_id(
+        &self,
+        userid: i64,
+        delivery: i32,
+    ) -> Result<Vec<PushTarget>, Error> {
+        use crate::schema::PushTargets::dsl::*;
+        Ok(PushTargets
+            .filter(user_id.eq(userid).and(delivery_method.eq(delivery)))
+            .load::<PushTarget>(&self.database.get()?)?)
+    }
+
+    pub async fn remove_push_target(&self, targetid: i32) -> Result<usize, Error> {
+        info!("Removing push target {}", targetid);
+        use crate::schema::PushTargets::dsl::*;
+        Ok(diesel::delete(PushTargets.filter(id.eq(targetid))).execute(&self.database.get()?)?)
+    }
+
+    /// Adds a pairing code for the user, replacing the previous one
+    pub async fn add_pairing_code(&self, pairing: NewPairingCode) -> Result<usize, Error> {
+        info!("Adding a pairing code for the user {}", pairing.user_id);
+        use crate::schema::PairingCodes::dsl::*;
+        let conn = self.database.get()?;
+        conn.transaction::<_, Error, _>(|| {
+            diesel::delete(
+                PairingCodes.filter(
+                    user_id
+                        .eq(pairing.user_id)
+                        .and(delivery_method.eq(pairing.delivery_method)),
+                ),
+            )
+            .execute(&conn)?;
+            Ok(diesel::insert_into(PairingCodes)
+                .values(&pairing)
+                .execute(&conn)?)
+        })
+    }
+
+    /// Removes and returns the pairing code if it has not expired by `now`
+    pub async fn take_pairing_code(
+        &self,
+        arg_code: &str,
+        now: i64,
+    ) -> Result<Option<PairingCode>, Error> {
+        use crate::schema::PairingCodes::dsl::*;
+        let conn = self.database.get()?;
+        conn.transaction::<_, Error, _>(|| {
+            diesel::delete(PairingCodes.filter(expires_at.lt(now))).execute(&conn)?;
+            let pairing = PairingCodes
+                .filter(code.eq(arg_code))
+                .first::<PairingCode>(&conn)
+                .optional()?;
+            diesel::delete(PairingCodes.filter(code.eq(arg_code))).execute(&conn)?;
+            Ok(pairing)
+        })
+    }
+
+    /// Returns the identity of the endpoint, adding the endpoint as its own identity if needed
+    fn endpoint_identity(
+        conn: &SqliteConnection,
+        userid: i64,
+        delivery: i32,
+    ) -> Result<i32, Error> {
+        use crate::schema::Endpoints::dsl::*;
+        if let Some(identity) = Endpoints
+            .filter(user_id.eq(userid).and(delivery_method.eq(delivery)))
+            .select(identity_id)
+            .first::<i32>(conn)
+            .optional()?
+        {
+            return Ok(identity);
+        }
+
+        diesel::insert_into(Endpoints)
+            .values(&NewEndpoint {
+                identity_id: 0,
+                user_id: userid,
+                delivery_method: delivery,
+            })
+            .execute(conn)?;
+        let new_id = Endpoints.select(id).order(id.desc()).first::<i32>(conn)?;
+        diesel::update(Endpoints.filter(id.eq(new_id)))
+            .set(identity_id.eq(new_id))
+            .execute(conn)?;
+        Ok(new_id)
+    }
+
+    /// Links the endpoint `other` and everything linked to it with the endpoint `to`
+    pub async fn link_endpoints(&self, to: (i64, i32), other: (i64, i32)) -> Result<(), Error> {
+        info!("Linking the endpoint {:?} with {:?}", other, to);
+        use crate::schema::Endpoints::dsl::*;
+        let conn = self.database.get()?;
+        conn.transaction::<_, Error, _>(|| {
+            let identity = Self::endpoint_identity(&conn, to.0, to.1)?;
+            let old = Self::endpoint_identity(&conn, other.0, other.1)?;
+            diesel::update(Endpoints.filter(identity_id.eq(old)))
+                .set(identity_id.eq(identity))
+                .execute(&conn)?;
+            Ok(())
+        })
+    }
+
+    /// Unlinks the endpoint from the others, the Vahtis stop delivering across the link
+    pub async fn unlink_endpoint(&self, userid: i64, delivery: i32) -> Result<usize, Error> {
+        info!("Unlinking the endpoint ({}, {})", userid, delivery);
+        use crate::schema::{Endpoints, Vahdit, VahtiDestinations};
+        let conn = self.database.get()?;
+        conn.transaction::<_, Error, _>(|| {
+            let owned = Vahdit::table
+                .filter(
+                    Vahdit::user_id
+                        .eq(userid)
+                        .and(Vahdit::delivery_method.eq(delivery)),
+                )
+                .select(Vahdit::id)
+                .load::<i32>(&conn)?;
+            diesel::delete(
+                VahtiDestinations::table.filter(
+                    VahtiDestinations::user_id
+                        .eq(userid)
+                        .and(VahtiDestinations::delivery_method.eq(delivery))
+                        .and(VahtiDestinations::vahti_id.ne_all(&owned)),
+                ),
+            )
+            .execute(&conn)?;
+            diesel::delete(
+                VahtiDestinations::table.filter(
+                    VahtiDestinations::vahti_id.eq_any(&owned).and(
+                        VahtiDestinations::user_id
+                            .ne(userid)
+                            .or(VahtiDestinations::delivery_method.ne(delivery)),
+                    ),
+                ),
+            )
+            .execute(&conn)?;
+            Ok(diesel::delete(
+                Endpoints::table.filter(
+                    Endpoints::user_id
+                        .eq(userid)
+                        .and(Endpoints::delivery_method.eq(delivery)),
+                ),
+            )
+            .execute(&conn)?)
+        })
+    }
+
+    /// Returns the endpoints linked with the given one, including itself
+    pub async fn fetch_linked_endpoints(
+        &self,
+        userid: i64,
+        delivery: i32,
+    ) -> Result<Vec<(i64, i32)>, Error> {
+        use crate::schema::Endpoints::dsl::*;
+        let conn = self.database.get()?;
+        let Some(identity) = Endpoints
+            .filter(user_id.eq(userid).and(delivery_method.eq(delivery)))
+            .select(identity_id)
+            .first::<i32>(&conn)
+            .optional()?
+        else {
+            return Ok(vec![(userid, delivery)]);
+        };
+
+        Ok(Endpoints
+            .filter(identity_id.eq(identity))
+            .order(id)
+            .select((user_id, delivery_method))
+            .load::<(i64, i32)>(&conn)?)
+    }
+
+    /// The blacklist shared by all the endpoints linked with the given one
+    pub async fn fetch_shared_blacklist(
+        &self,
+        userid: i64,
+        delivery: i32,
+    ) -> Result<Vec<(i32, i32)>, Error> {
+        debug!("Fetching the shared blacklist for user {}...", userid);
+        let endpoints = self.fetch_linked_endpoints(userid, delivery).await?;
+        let users = endpoints.iter().map(|(u, _)| *u).collect::<Vec<_>>();
+        use crate::schema::Blacklists::dsl::*;
+        // NOTE: The ids of different delivery methods may collide, so the endpoints
+        // are matched as a whole
+        Ok(Blacklists
+            .filter(user_id.eq_any(users))
+            .select((user_id, delivery_method, seller_id, site_id))
+            .load::<(i64, i32, i32, i32)>(&self.database.get()?)?
+            .into_iter()
+            .filter(|(u, d, _, _)| endpoints.contains(&(*u, *d)))
+            .map(|(_, _, seller, site)| (seller, site))
+            .unique()
+            .collect())
+    }
+
+    /// Replaces the endpoints the items of the Vahti are delivered to
+    pub async fn set_vahti_destinations(
+        &self,
+        vahtiid: i32,
+        destinations: &[(i64, i32)],
+    ) -> Result<(), Error> {
+        info!("Setting the destinations of Vahti {}", vahtiid);
+        use crate::schema::VahtiDestinations::dsl::*;
+        let conn = self.database.get()?;
+        conn.transaction::<_, Error, _>(|| {
+            diesel::delete(VahtiDestinations.filter(vahti_id.eq(vahtiid))).execute(&conn)?;
+            for (userid, delivery) in destinations {
+                diesel::insert_into(VahtiDestinations)
+                    .values(&NewVahtiDestination {
+                        vahti_id: vahtiid,
+                        user_id: *userid,
+                        delivery_method: *delivery,
+                    })
+                    .execute(&conn)?;
+            }
+            Ok(())
+        })
+    }
+
+    pub async fn fetch_vahti_destinations(&self, vahtiid: i32) -> Result<Vec<(i64, i32)>, Error> {
+        use crate::schema::VahtiDestinations::dsl::*;
+        Ok(VahtiDestinations
+            .filter(vahti_id.eq(vahtiid))
+            .order(id)
+            .select((user_id, delivery_method))
+            .load::<(i64, i32)>(&self.database.get()?)?)
+    }
+
+    pub async fn fetch_all_vahti_destinations(&self) -> Result<Vec<VahtiDestination>, Error> {
+        use crate::schema::VahtiDestinations::dsl::*;
+        Ok(VahtiDestinations
+            .order(id)
+            .load::<VahtiDestination>(&self.database.get()?)?)
+    }
+
+    pub async fn add_outbox_entry(&self, entry: NewOutboxEntry) -> Result<usize, Error> {
+        debug!(
+            "Queueing a delivery to {} ({})",
+            entry.user_id, entry.delivery_method
+        );
+        use crate::schema::Outbox;
+        Ok(diesel::insert_into(Outbox::table)
+            .values(&entry)
+            .execute(&self.database.get()?)?)
+    }
+
+    /// Fetches the pending entries that are due to be attempted, oldest first
+    pub async fn fetch_due_outbox_entries(
+        &self,
+        now: i64,
+        limit: i64,
+    ) -> Result<Vec<OutboxEntry>, Error> {
+        use crate::schema::Outbox::dsl::*;
+        Ok(Outbox
+            .filter(failed.eq(false).and(next_attempt.le(now)))
+            .order(id.asc())
+            .limit(limit)
+            .load::<OutboxEntry>(&self.database.get()?)?)
+    }
+
+    pub async fn remove_outbox_entry(&self, entryid: i32) -> Result<usize, Error> {
+        use crate::schema::Outbox::dsl::*;
+        Ok(diesel::delete(Outbox.filter(id.eq(entryid))).execute(&self.database.get()?)?)
+    }
+
+    /// Records a failed attempt, the entry is attempted again at `retry_at`
+    pub async fn reschedule_outbox_entry(
+        &self,
+        entryid: i32,
+        retry_at: i64,
+        error: &str,
+    ) -> Result<usize, Error> {
+        use crate::schema::Outbox::dsl::*;
+        Ok(diesel::update(Outbox.filter(id.eq(entryid)))
+            .set((
+                attempts.eq(attempts + 1),
+                next_attempt.eq(retry_at),
+                last_error.eq(error),
+            ))
+            .execute(&self.database.get()?)?)
+    }
+
+    /// Gives up on the entry, it is kept for inspection until purged
+    pub async fn fail_outbox_entry(&self, entryid: i32, error: &str) -> Result<usize, Error> {
+        use crate::schema::Outbox::dsl::*;
+        Ok(diesel::update(Outbox.filter(id.eq(entryid)))
+            .set((
+                attempts.eq(attempts + 1),
+                failed.eq(true),
+                last_error.eq(error),
+            ))
+            .execute(&self.database.get()?)?)
+    }
+
+    /// The amount of pending and failed entries and the creation time of the oldest pending one
+    pub async fn outbox_stats(&self) -> Result<(i64, i64, Option<i64>), Error> {
+        use diesel::dsl::{count_star, min};
+
+        use crate::schema::Outbox::dsl::*;
+        let conn = self.database.get()?;
+        let pending = Outbox
+            .filter(failed.eq(false))
+            .select(count_star())
+            .first::<i64>(&conn)?;
+        let failures = Outbox
+            .filter(failed.eq(true))
+            .select(count_star())
+            .first::<i64>(&conn)?;
+        let oldest = Outbox
+            .filter(failed.eq(false))
+            .select(min(created_at))
+            .first::<Option<i64>>(&conn)?;
+        Ok((pending, failures, oldest))
+    }
+
+    pub async fn purge_failed_outbox_entries(&self, older_than: i64) -> Result<usize, Error> {
+        use crate::schema::Outbox::dsl::*;
+        Ok(
+            diesel::delete(Outbox.filter(failed.eq(true).and(created_at.lt(older_than))))
+                .execute(&self.database.get()?)?,
+        )
+    }
+
+    /// Counts a delivery that failed because the recipient could not be reached,
+    /// returning the amount of such failures in a row
+    pub async fn add_delivery_failure(&self, userid: i64, delivery: i32) -> Result<i32, Error> {
+        use crate::schema::DeliveryFailures::dsl::*;
+        let conn = self.database.get()?;
+        conn.transaction::<_, Error, _>(|| {
+            let count = DeliveryFailures
+                .filter(user_id.eq(userid).and(delivery_method.eq(delivery)))
+                .select(failures)
+                .first::<i32>(&conn)
+                .optional()?
+                .unwrap_or(0)
+                + 1;
+            diesel::replace_into(DeliveryFailures)
+                .values((
+                    user_id.eq(userid),
+                    delivery_method.eq(delivery),
+                    failures.eq(count),
+                ))
+                .execute(&conn)?;
+            Ok(count)
+        })
+    }
+
+    pub async fn clear_delivery_failures(
+        &self,
+        userid: i64,
+        delivery: i32,
+    ) -> Result<usize, Error> {
+        use crate::schema::DeliveryFailures::dsl::*;
+        Ok(diesel::delete(
+            DeliveryFailures.filter(user_id.eq(userid).and(delivery_method.eq(delivery))),
+        )
+        .execute(&self.database.get()?)?)
+    }
+
+    /// Disables the Vahtis of the user, returning the amount of Vahtis disabled
+    pub async fn disable_vahtis(&self, userid: i64, delivery: i32) -> Result<usize, Error> {
+        info!("Disabling the Vahtis of {} ({})", userid, delivery);
+        use crate::schema::{DeliveryFailures, Vahdit};
+        let conn = self.database.get()?;
+        conn.transaction::<_, Error, _>(|| {
+            diesel::delete(
+                DeliveryFailures::table.filter(
+                    DeliveryFailures::user_id
+                        .eq(userid)
+                        .and(DeliveryFailures::delivery_method.eq(delivery)),
+                ),
+            )
+            .execute(&conn)?;
+            Ok(diesel::update(
+                Vahdit::table.filter(
+                    Vahdit::user_id
+                        .eq(userid)
+                        .and(Vahdit::delivery_method.eq(delivery))
+                        .and(Vahdit::disabled.eq(false)),
+                ),
+            )
+            .set(Vahdit::disabled.eq(true))
+            .execute(&conn)?)
+        })
+    }
+
+    /// Enables the disabled Vahtis of the user. The items published in the meantime
+    /// are skipped like when resuming a Vahti
+    pub async fn enable_vahtis(&self, userid: i64, delivery: i32) -> Result<usize, Error> {
+        use crate::schema::Vahdit::dsl::*;
+        let time = chrono::Local::now().timestamp();
+        Ok(diesel::update(
+            Vahdit.filter(
+                user_id
+                    .eq(userid)
+                    .and(delivery_method.eq(delivery))
+                    .and(disabled.eq(true)),
+            ),
+        )
+        .set((disabled.eq(false), last_updated.eq(time)))
+        .execute(&self.database.get()?)?)
+    }
+
+    /// The amount of disabled Vahtis by delivery method
+    pub async fn count_disabled_vahtis(&self) -> Result<BTreeMap<i32, usize>, Error> {
+        use crate::schema::Vahdit::dsl::*;
+        Ok(Vahdit
+            .filter(disabled.eq(true))
+            .select(delivery_method)
+            .load::<i32>(&self.database.get()?)?
+            .into_iter()
+            .fold(BTreeMap::new(), |mut acc, d| {
+                *acc.entry(d).or_default() += 1;
+                acc
+            }))
+    }
+
+    /// Moves everything of the user to a new id, e.g. when a Telegram group is upgraded
+    /// to a supergroup and gets a new chat id
+    pub async fn migrate_user(&self, old: i64, new: i64, delivery: i32) -> Result<(), Error> {
+        info!("Migrating user {} ({}) to {}", old, delivery, new);
+        use crate::schema::*;
+        let conn = self.database.get()?;
+
+        // NOTE: The tables keyed on the user and the delivery method are all migrated the same way
+        macro_rules! migrate {
+            ($($table:ident),*) => {
+                $(
+                    diesel::update(
+                        $table::table.filter(
+                            $table::user_id
+                                .eq(old)
+                                .and($table::delivery_method.eq(delivery)),
+                        ),
+                    )
+                    .set($table::user_id.eq(new))
+                    .execute(&conn)?;
+                )*
+            };
+        }
+
+        conn.transaction::<_, Error, _>(|| {
+            migrate!(
+                Vahdit,
+                UserSettings,
+                HeldItems,
+                SeenItems,
+                Bookmarks,
+                AuctionWatches,
+                Webhooks,
+                EmailAddresses,
+                PushTargets,
+                Endpoints,
+                VahtiDestinations,
+                Outbox,
+                Blacklists
+            );
+            diesel::delete(
+                DeliveryFailures::table.filter(
+                    DeliveryFailures::user_id
+                        .eq(old)
+                        .and(DeliveryFailures::delivery_method.eq(delivery)),
+                ),
+            )
+            .execute(&conn)?;
+            Ok(())
+        })
+    }
 }
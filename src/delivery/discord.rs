@@ -1,44 +1,121 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use chrono::{Local, TimeZone};
 use futures::stream::{self, StreamExt};
+use itertools::Itertools;
 use serenity::builder::{
     CreateActionRow, CreateButton, CreateEmbed, CreateEmbedFooter, CreateMessage,
 };
 use serenity::http::Http;
 use serenity::model::application::ButtonStyle;
 use serenity::model::colour::Color;
+use serenity::model::id::ChannelId;
+use serenity::model::permissions::Permissions;
 
-use crate::delivery::Delivery;
+use crate::database::Database;
+use crate::delivery::{Delivery, Notification};
 use crate::error::Error;
+use crate::settings::UserSettings;
 use crate::vahti::VahtiItem;
 
 pub const ID: i32 = 1;
 pub const NAME: &str = "discord";
+/// Vahtis that post into a guild channel or thread instead of the DMs of a user,
+/// `deliver_to` being the id of the channel
+pub const CHANNEL_ID: i32 = 7;
 
+/// The amount of items in a single message. Let's try 5 embeds per message, discord has a
+/// limit afaik, but idk if the text/character limit will become an issue before the embed limit does
+pub const MESSAGE_SIZE: usize = 5;
+
+#[derive(Clone)]
 pub struct Discord {
     pub http: Arc<Http>,
+    pub db: Database,
+    /// Either `ID` or `CHANNEL_ID`
+    pub delivery_method: i32,
 }
 
 /// This is the discord delivery client
 /// There will be a separate client for handling commands
 impl Discord {
-    pub async fn init() -> Result<Self, Error> {
+    pub async fn init(db: &Database) -> Result<Self, Error> {
         let token =
             std::env::var("DISCORD_TOKEN").expect("Expected DISCORD_TOKEN in the environment");
 
         // NOTE: We don't need a serenity::Client because we don't have to listen to events.
         let http = Arc::new(Http::new(&token));
-        Ok(Self { http })
+        Ok(Self {
+            http,
+            db: db.clone(),
+            delivery_method: ID,
+        })
+    }
+
+    /// The same client delivering into guild channels
+    pub fn for_channels(&self) -> Self {
+        Self {
+            delivery_method: CHANNEL_ID,
+            ..self.clone()
+        }
     }
 
     pub async fn destroy(self) {}
+
+    /// The channel the messages to `deliver_to` are sent to
+    async fn channel(&self, deliver_to: u64) -> Result<ChannelId, Error> {
+        if self.delivery_method == CHANNEL_ID {
+            return Ok(ChannelId::new(deliver_to));
+        }
+
+        let recipient = self.http.get_user(deliver_to.into()).await?;
+        Ok(recipient.create_dm_channel(&self.http).await?.id)
+    }
+}
+
+/// Resolves who owns the Vahtis delivered into a channel, or `None` if the user may not
+/// manage them. In a guild the Vahtis belong to the channel and managing them requires
+/// the Manage Channels permission, in DMs they belong to the user themself
+pub fn vahti_owner(
+    userid: u64,
+    channelid: u64,
+    in_guild: bool,
+    permissions: Option<Permissions>,
+) -> Option<(u64, i32)> {
+    if !in_guild {
+        return Some((userid, ID));
+    }
+
+    permissions
+        .filter(|p| p.manage_channels() || p.administrator())
+        .map(|_| (channelid, CHANNEL_ID))
 }
 
 impl VahtiItem {
-    fn embed(self) -> CreateEmbed {
-        match self.site_id {
+    #[cfg(feature = "tori")]
+    fn tori_seller(&self) -> String {
+        // NOTE: seller_id 0 means that the seller is unknown, we don't want to
+        // provide a link (that would be used for blocking the seller) in that case
+        match (self.seller_id, self.seller_name.is_empty()) {
+            (0, true) => String::from("Tuntematon"),
+            (0, false) => self.seller_name.clone(),
+            _ => format!(
+                "[{}](https://www.tori.fi/li?&aid={})",
+                self.seller_name, self.seller_id
+            ),
+        }
+    }
+
+    fn discord_price(&self) -> String {
+        match self.previous_price {
+            Some(old) => format!("~~{} €~~ {} €", old, self.price),
+            None => format!("{} €", self.price),
+        }
+    }
+
+    fn embed(self, settings: &UserSettings) -> CreateEmbed {
+        let price_dropped = self.previous_price.is_some();
+        let e = match self.site_id {
             #[cfg(feature = "tori")]
             crate::tori::ID => {
                 let color = match self.ad_type.as_str() {
@@ -50,23 +127,12 @@ impl VahtiItem {
                 let e = CreateEmbed::new()
                     .color(color)
                     .description(format!("[{}]({})", self.title, self.url))
-                    .field("Hinta", format!("{} €", self.price), true)
-                    .field(
-                        "Myyjä",
-                        format!(
-                            "[{}](https://www.tori.fi/li?&aid={})",
-                            self.seller_name, self.seller_id
-                        ),
-                        true,
-                    )
+                    .field("Hinta", self.discord_price(), true)
+                    .field("Myyjä", self.tori_seller(), true)
                     .field("Sijainti", &self.location, true)
                     .field(
                         "Ilmoitus Jätetty",
-                        Local
-                            .timestamp_opt(self.published, 0)
-                            .unwrap()
-                            .format("%d/%m/%Y %R")
-                            .to_string(),
+                        settings.format_time(self.published),
                         true,
                     )
                     .field("Ilmoitustyyppi", self.ad_type.to_string(), true)
@@ -84,7 +150,7 @@ impl VahtiItem {
                 let e = CreateEmbed::new()
                     .color(Color::BLUE)
                     .description(format!("[{}]({})", self.title, self.url))
-                    .field("Hinta", format!("{} €", self.price), true)
+                    .field("Hinta", self.discord_price(), true)
                     .field(
                         "Myyjä",
                         format!(
@@ -96,11 +162,7 @@ impl VahtiItem {
                     .field("Sijainti", &self.location, true)
                     .field(
                         "Ilmoitus Jätetty",
-                        Local
-                            .timestamp_opt(self.published, 0)
-                            .unwrap()
-                            .format("%d/%m/%Y %R")
-                            .to_string(),
+                        settings.format_time(self.published),
                         true,
                     )
                     .field("Ilmoitustyyppi", self.ad_type.to_string(), true)
@@ -114,12 +176,144 @@ impl VahtiItem {
                 }
             }
             i => panic!("Unsupported site_id {}", i),
+        };
+
+        if price_dropped {
+            e.title("Hinta laski!").color(Color::GOLD)
+        } else {
+            e
+        }
+    }
+}
+
+/// The amount of items on a single page of a digest
+const DIGEST_PAGE_SIZE: usize = 10;
+/// The maximum amount of embeds in a single message
+const MAX_EMBEDS: usize = 10;
+/// The maximum amount of characters in all of the embeds of a message combined
+const MAX_EMBED_CHARS: usize = 6000;
+
+/// Builds the pages of a digest, one embed per page. The pages are sent together in as few
+/// messages as possible, a digest normally fits into a single message
+pub fn digest_messages(items: Vec<VahtiItem>) -> Vec<Vec<CreateEmbed>> {
+    let vahti_url = items
+        .first()
+        .and_then(|i| i.vahti_url.clone())
+        .unwrap_or_default();
+    let pages = items.chunks(DIGEST_PAGE_SIZE).count();
+
+    let mut messages: Vec<Vec<CreateEmbed>> = vec![];
+    let mut chars = 0;
+    for (n, page) in items.chunks(DIGEST_PAGE_SIZE).enumerate() {
+        let title = format!("Kooste: {} uutta ilmoitusta", items.len());
+        let description = page
+            .iter()
+            .map(|i| format!("[{}]({}) {}", i.title, i.url, i.discord_price()))
+            .join("\n");
+        let footer = format!("Sivu {}/{} • {}", n + 1, pages, vahti_url);
+
+        let len = title.chars().count() + description.chars().count() + footer.chars().count();
+        if messages.last().map_or(true, |m| {
+            m.len() >= MAX_EMBEDS || chars + len > MAX_EMBED_CHARS
+        }) {
+            messages.push(vec![]);
+            chars = 0;
+        }
+        chars += len;
+
+        messages.last_mut().expect("bug: impossible").push(
+            CreateEmbed::new()
+                .color(Color::DARK_GREEN)
+                .title(title)
+                .description(description)
+                .footer(CreateEmbedFooter::new(footer)),
+        );
+    }
+    messages
+}
+
+impl Notification {
+    fn embed(self, settings: &UserSettings) -> CreateEmbed {
+        match self {
+            Notification::AuctionEnding {
+                title,
+                url,
+                closing_time,
+                current_price,
+                buy_now_price,
+                bidder_count,
+                reserve_price_exceeded,
+            } => {
+                let mut e = CreateEmbed::new()
+                    .color(Color::ORANGE)
+                    .title("Huutokauppa päättyy pian")
+                    .description(format!("[{}]({})", title, url))
+                    .field("Nykyinen hinta", format!("{} €", current_price), true)
+                    .field("Huutajia", bidder_count.to_string(), true)
+                    .field("Päättyy", settings.format_time(closing_time), true);
+                if let Some(p) = buy_now_price {
+                    e = e.field("Osta heti", format!("{} €", p), true);
+                }
+                if let Some(exceeded) = reserve_price_exceeded {
+                    e = e.field(
+                        "Pohjahinta",
+                        if exceeded {
+                            "Ylittynyt"
+                        } else {
+                            "Ei ylittynyt"
+                        },
+                        true,
+                    );
+                }
+                e
+            }
+            Notification::ListingGone { title, url, status } => CreateEmbed::new()
+                .color(Color::DARK_GREY)
+                .title(status.describe())
+                .description(format!("[{}]({})", title, url)),
         }
     }
 }
 
 #[async_trait]
 impl Delivery for Discord {
+    async fn notify(&self, deliver_to: u64, notification: Notification) -> Result<(), Error> {
+        info!("Sending a notification to {}", deliver_to);
+        let settings = UserSettings::fetch(&self.db, deliver_to as i64, self.delivery_method).await;
+        self.channel(deliver_to)
+            .await?
+            .send_message(
+                &self.http,
+                CreateMessage::new().add_embed(notification.embed(&settings)),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn deliver_digest(&self, items: Vec<VahtiItem>) -> Result<(), Error> {
+        let Some(fst) = items.first() else {
+            return Ok(());
+        };
+
+        info!(
+            "Delivering a digest of {} items to {}",
+            items.len(),
+            fst.deliver_to.unwrap()
+        );
+
+        let channel = self
+            .channel(fst.deliver_to.expect("bug: impossible"))
+            .await?;
+
+        for embeds in digest_messages(items) {
+            channel
+                .send_message(&self.http, CreateMessage::new().embeds(embeds))
+                .await?;
+        }
+
+        Ok(())
+    }
+
     async fn deliver(&self, items: Vec<VahtiItem>) -> Result<(), Error> {
         let Some(fst) = items.first() else {
             return Ok(());
@@ -133,42 +327,69 @@ impl Delivery for Discord {
             fst.deliver_to.unwrap()
         );
 
-        // NOTE: Let's try 5 embeds per message, discord has a limit afaik, but idk
-        // if the text/character limit will become an issue before the embed limit does
-        let chunks: Vec<Vec<VahtiItem>> = items.chunks(5).map(|c| c.to_vec()).collect();
+        let chunks: Vec<Vec<VahtiItem>> = items.chunks(MESSAGE_SIZE).map(|c| c.to_vec()).collect();
 
+        let settings = UserSettings::fetch(
+            &self.db,
+            fst.deliver_to.unwrap() as i64,
+            self.delivery_method,
+        )
+        .await;
         let http = self.http.clone();
-        let recipient = http
-            .get_user(fst.deliver_to.expect("bug: impossible").into())
+        let channel = self
+            .channel(fst.deliver_to.expect("bug: impossible"))
             .await?;
 
         stream::iter(chunks.iter().cloned())
-            .map(|is| (is, http.clone(), recipient.clone()))
-            .map(async move |(items, http, rec)| {
+            .map(|is| (is, http.clone(), settings.clone()))
+            .map(async move |(items, http, settings)| {
                 let mut message = CreateMessage::new();
+                let has_auctions = items.iter().any(|i| i.is_auction());
+                // NOTE: tori doesn't tell the sellers of the listings, they can't be
+                // blocked or followed from the buttons
+                let has_sellers = items.iter().any(|i| i.seller_id != 0);
                 for item in items {
-                    message = message.add_embed(item.clone().embed());
+                    message = message.add_embed(item.clone().embed(&settings));
                 }
-                let buttons = vec![
-                    CreateButton::new("block_seller")
-                        .label("Estä myyjä")
-                        .style(ButtonStyle::Danger),
+                let mut buttons = vec![];
+                if has_sellers {
+                    buttons.push(
+                        CreateButton::new("block_seller")
+                            .label("Estä myyjä")
+                            .style(ButtonStyle::Danger),
+                    );
+                    buttons.push(
+                        CreateButton::new("follow_seller")
+                            .label("Seuraa myyjää")
+                            .style(ButtonStyle::Primary),
+                    );
+                }
+                buttons.extend([
                     CreateButton::new("remove_vahti")
                         .label("Poista vahti")
                         .style(ButtonStyle::Danger),
-                ];
+                    CreateButton::new("bookmark_item")
+                        .label("Seuraa ilmoitusta")
+                        .style(ButtonStyle::Secondary),
+                ]);
+                if has_auctions {
+                    buttons.push(
+                        CreateButton::new("watch_auction")
+                            .label("Muistuta päättymisestä")
+                            .style(ButtonStyle::Primary),
+                    );
+                }
                 let row = CreateActionRow::Buttons(buttons);
                 if cfg!(feature = "discord-command") {
                     message = message.components(vec![row]);
                 }
-                rec.dm(&http, message)
-                    .await
-                    // FIXME: Perhaps don't ignore an error here
-                    .ok()
+                channel.send_message(&http, message).await
             })
             .buffer_unordered(*crate::FUTURES_MAX_BUFFER_SIZE)
             .collect::<Vec<_>>()
-            .await;
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
 
         Ok(())
     }
@@ -0,0 +1,557 @@
+use async_trait::async_trait;
+use lettre::message::header::{ContentType, HeaderName, HeaderValue};
+use lettre::message::{Mailbox, MultiPart, SinglePart};
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+
+use crate::database::Database;
+use crate::delivery::{Delivery, Notification};
+use crate::error::Error;
+use crate::http::Response;
+use crate::models::{EmailAddress, NewEmailAddress};
+use crate::settings::UserSettings;
+use crate::vahti::VahtiItem;
+
+pub const ID: i32 = 4;
+pub const NAME: &str = "email";
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn seller_url(site_id: i32, seller_id: i32) -> Option<String> {
+    if seller_id == 0 {
+        return None;
+    }
+    match site_id {
+        #[cfg(feature = "tori")]
+        crate::tori::ID => Some(crate::tori::seller::seller_url(seller_id)),
+        #[cfg(feature = "huutonet")]
+        crate::huutonet::ID => Some(crate::huutonet::seller::seller_url(seller_id)),
+        _ => None,
+    }
+}
+
+/// The link that removes the address along with its Vahtis
+pub fn unsubscribe_url(token: &str) -> String {
+    format!(
+        "{}/unsubscribe?token={}",
+        crate::EMAIL_PUBLIC_URL.trim_end_matches('/'),
+        token
+    )
+}
+
+trait FormatEmail {
+    fn format_html(&self, settings: &UserSettings) -> String;
+    fn format_text(&self, settings: &UserSettings) -> String;
+}
+
+impl FormatEmail for VahtiItem {
+    fn format_html(&self, settings: &UserSettings) -> String {
+        let mut html = String::from(r#"<tr><td style="padding:8px;vertical-align:top">"#);
+        if !self.img_url.is_empty() {
+            html.push_str(&format!(
+                r#"<img src="{}" alt="" width="120" style="max-width:120px">"#,
+                escape(&self.img_url)
+            ));
+        }
+        html.push_str(r#"</td><td style="padding:8px;vertical-align:top">"#);
+        if self.previous_price.is_some() {
+            html.push_str("<b>Hinta laski!</b><br>");
+        }
+        html.push_str(&format!(
+            r#"<a href="{}"><b>{}</b></a><br>"#,
+            escape(&self.url),
+            escape(&self.title)
+        ));
+        match self.previous_price {
+            Some(old) => html.push_str(&format!(
+                "<b>Hinta</b>: <s>{}€</s> {}€<br>",
+                old, self.price
+            )),
+            None => html.push_str(&format!("<b>Hinta</b>: {}€<br>", self.price)),
+        }
+        let seller = if self.seller_name.is_empty() {
+            "Tuntematon"
+        } else {
+            &self.seller_name
+        };
+        match seller_url(self.site_id, self.seller_id) {
+            Some(url) => html.push_str(&format!(
+                r#"<b>Myyjä</b>: <a href="{}">{}</a><br>"#,
+                escape(&url),
+                escape(seller)
+            )),
+            None => html.push_str(&format!("<b>Myyjä</b>: {}<br>", escape(seller))),
+        }
+        html.push_str(&format!("<b>Sijainti</b>: {}<br>", escape(&self.location)));
+        html.push_str(&format!(
+            "<b>Ilmoitus jätetty</b>: {}<br>",
+            settings.format_time(self.published)
+        ));
+        if let Some(vahti_url) = &self.vahti_url {
+            html.push_str(&format!(r#"<a href="{}">Avaa haku</a>"#, escape(vahti_url)));
+        }
+        html.push_str("</td></tr>");
+        html
+    }
+
+    fn format_text(&self, settings: &UserSettings) -> String {
+        let mut text = String::new();
+        if self.previous_price.is_some() {
+            text.push_str("Hinta laski!\n");
+        }
+        text.push_str(&format!("{}\n{}\n", self.title, self.url));
+        match self.previous_price {
+            Some(old) => text.push_str(&format!("Hinta: {}€ -> {}€\n", old, self.price)),
+            None => text.push_str(&format!("Hinta: {}€\n", self.price)),
+        }
+        text.push_str(&format!("Myyjä: {}\n", self.seller_name));
+        text.push_str(&format!("Sijainti: {}\n", self.location));
+        text.push_str(&format!(
+            "Ilmoitus jätetty: {}\n",
+            settings.format_time(self.published)
+        ));
+        if let Some(vahti_url) = &self.vahti_url {
+            text.push_str(&format!("Haku: {}\n", vahti_url));
+        }
+        text
+    }
+}
+
+impl FormatEmail for Notification {
+    fn format_html(&self, settings: &UserSettings) -> String {
+        match self {
+            Notification::AuctionEnding {
+                title,
+                url,
+                closing_time,
+                current_price,
+                buy_now_price,
+                bidder_count,
+                ..
+            } => {
+                let mut html = format!(
+                    r#"<p><b>Huutokauppa päättyy pian</b><br><a href="{}">{}</a><br>"#,
+                    escape(url),
+                    escape(title)
+                );
+                html.push_str(&format!("<b>Nykyinen hinta</b>: {}€<br>", current_price));
+                html.push_str(&format!("<b>Huutajia</b>: {}<br>", bidder_count));
+                html.push_str(&format!(
+                    "<b>Päättyy</b>: {}",
+                    settings.format_time(*closing_time)
+                ));
+                if let Some(p) = buy_now_price {
+                    html.push_str(&format!("<br><b>Osta heti</b>: {}€", p));
+                }
+                html.push_str("</p>");
+                html
+            }
+            Notification::ListingGone { title, url, status } => format!(
+                r#"<p><b>{}</b><br><a href="{}">{}</a></p>"#,
+                status.describe(),
+                escape(url),
+                escape(title)
+            ),
+        }
+    }
+
+    fn format_text(&self, settings: &UserSettings) -> String {
+        match self {
+            Notification::AuctionEnding {
+                title,
+                url,
+                closing_time,
+                current_price,
+                ..
+            } => format!(
+                "Huutokauppa päättyy pian\n{}\n{}\nNykyinen hinta: {}€\nPäättyy: {}\n",
+                title,
+                url,
+                current_price,
+                settings.format_time(*closing_time)
+            ),
+            Notification::ListingGone { title, url, status } => {
+                format!("{}\n{}\n{}\n", status.describe(), title, url)
+            }
+        }
+    }
+}
+
+impl Notification {
+    fn subject(&self) -> String {
+        match self {
+            Notification::AuctionEnding { title, .. } => {
+                format!("Huutokauppa päättyy pian: {}", title)
+            }
+            Notification::ListingGone { title, status, .. } => {
+                format!("{}: {}", status.describe(), title)
+            }
+        }
+    }
+}
+
+/// Builds a message with both a HTML and a plain-text body, along with
+/// an unsubscribe link
+pub fn build_message(
+    from: &Mailbox,
+    to: &EmailAddress,
+    subject: &str,
+    html: &str,
+    text: &str,
+) -> Result<Message, Error> {
+    let unsubscribe = unsubscribe_url(&to.unsubscribe_token);
+
+    let html = format!(
+        r#"<!DOCTYPE html><html><body style="font-family:sans-serif">{}<p style="font-size:small;color:#777"><a href="{}">Lopeta ilmoitukset tähän osoitteeseen</a></p></body></html>"#,
+        html, unsubscribe
+    );
+    let text = format!(
+        "{}\n--\nLopeta ilmoitukset tähän osoitteeseen: {}\n",
+        text, unsubscribe
+    );
+
+    let mut message = Message::builder()
+        .from(from.clone())
+        .to(to
+            .address
+            .parse::<Mailbox>()
+            .map_err(|e| Error::Email(e.to_string()))?)
+        .subject(subject)
+        .multipart(
+            MultiPart::alternative()
+                .singlepart(
+                    SinglePart::builder()
+                        .header(ContentType::TEXT_PLAIN)
+                        .body(text),
+                )
+                .singlepart(
+                    SinglePart::builder()
+                        .header(ContentType::TEXT_HTML)
+                        .body(html),
+                ),
+        )
+        .map_err(|e| Error::Email(e.to_string()))?;
+
+    message.headers_mut().insert_raw(HeaderValue::new(
+        HeaderName::new_from_ascii_str("List-Unsubscribe"),
+        format!("<{}>", unsubscribe),
+    ));
+    Ok(message)
+}
+
+/// Formats the HTML and the plain-text bodies for a batch of items
+pub fn format_items(items: &[VahtiItem], settings: &UserSettings) -> (String, String) {
+    let html = format!(
+        "<table>{}</table>",
+        items
+            .iter()
+            .map(|i| i.format_html(settings))
+            .collect::<String>()
+    );
+    let text = items
+        .iter()
+        .map(|i| i.format_text(settings))
+        .collect::<Vec<_>>()
+        .join("\n");
+    (html, text)
+}
+
+/// Builds the message for a batch of items, the items are sent as a single message
+pub fn items_message(
+    from: &Mailbox,
+    to: &EmailAddress,
+    items: &[VahtiItem],
+    settings: &UserSettings,
+    digest: bool,
+) -> Result<Message, Error> {
+    let subject = if digest {
+        format!("Kooste: {} uutta ilmoitusta", items.len())
+    } else if items.len() == 1 {
+        format!("Uusi ilmoitus: {}", items[0].title)
+    } else {
+        format!("{} uutta ilmoitusta", items.len())
+    };
+
+    let (html, text) = format_items(items, settings);
+    build_message(from, to, &subject, &html, &text)
+}
+
+/// A wrapper around the SMTP transport, also used by the commands for
+/// sending the verification codes
+pub struct Mailer {
+    pub transport: AsyncSmtpTransport<Tokio1Executor>,
+    pub from: Mailbox,
+}
+
+impl Mailer {
+    pub fn from_env() -> Result<Self, Error> {
+        let url = std::env::var("SMTP_URL").expect("Expected SMTP_URL in the environment");
+        let from = std::env::var("EMAIL_FROM").expect("Expected EMAIL_FROM in the environment");
+
+        Ok(Self {
+            transport: AsyncSmtpTransport::<Tokio1Executor>::from_url(&url)
+                .map_err(|e| Error::Email(e.to_string()))?
+                .build(),
+            from: from.parse().map_err(|e| Error::Email(format!("{}", e)))?,
+        })
+    }
+
+    pub async fn send(&self, message: Message) -> Result<(), Error> {
+        self.transport
+            .send(message)
+            .await
+            .map_err(|e| Error::Email(e.to_string()))?;
+        Ok(())
+    }
+}
+
+pub struct Email {
+    pub mailer: Mailer,
+    pub db: Database,
+}
+
+/// This is the email delivery client, the `deliver_to` of the items
+/// is the id of the email address
+impl Email {
+    pub async fn init(db: &Database) -> Result<Self, Error> {
+        Ok(Self {
+            mailer: Mailer::from_env()?,
+            db: db.clone(),
+        })
+    }
+
+    pub async fn destroy(self) {}
+
+    /// Fetches the address and the settings of its owner, unverified addresses are skipped
+    async fn recipient(&self, emailid: u64) -> Result<Option<(EmailAddress, UserSettings)>, Error> {
+        let address = self.db.fetch_email_address(emailid as i32).await?;
+        if !address.verified {
+            warn!("Skipping the unverified email address {}", emailid);
+            return Ok(None);
+        }
+
+        let settings =
+            UserSettings::fetch(&self.db, address.user_id, address.delivery_method).await;
+        Ok(Some((address, settings)))
+    }
+
+    async fn send_items(&self, items: Vec<VahtiItem>, digest: bool) -> Result<(), Error> {
+        let Some(fst) = items.first() else {
+            return Ok(());
+        };
+
+        let emailid = fst.deliver_to.expect("bug: impossible");
+        let Some((address, settings)) = self.recipient(emailid).await? else {
+            return Ok(());
+        };
+
+        info!("Emailing {} items to {}", items.len(), emailid);
+        self.mailer
+            .send(items_message(
+                &self.mailer.from,
+                &address,
+                &items,
+                &settings,
+                digest,
+            )?)
+            .await
+    }
+}
+
+#[async_trait]
+impl Delivery for Email {
+    async fn deliver(&self, items: Vec<VahtiItem>) -> Result<(), Error> {
+        self.send_items(items, false).await
+    }
+
+    async fn deliver_digest(&self, items: Vec<VahtiItem>) -> Result<(), Error> {
+        self.send_items(items, true).await
+    }
+
+    async fn notify(&self, deliver_to: u64, notification: Notification) -> Result<(), Error> {
+        let Some((address, settings)) = self.recipient(deliver_to).await? else {
+            return Ok(());
+        };
+
+        info!("Emailing a notification to {}", deliver_to);
+        self.mailer
+            .send(build_message(
+                &self.mailer.from,
+                &address,
+                &notification.subject(),
+                &notification.format_html(&settings),
+                &notification.format_text(&settings),
+            )?)
+            .await
+    }
+}
+
+fn random_string(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+/// Adds an email address for the user and sends it a verification code
+pub async fn add_email_address(
+    db: Database,
+    mailer: &Mailer,
+    address: &str,
+    userid: u64,
+    delivery_method: i32,
+) -> Result<String, Error> {
+    let address = address.trim();
+    if address.parse::<Mailbox>().is_err() {
+        return Err(Error::InvalidDestination(address.to_string()));
+    }
+
+    let email = NewEmailAddress {
+        user_id: userid as i64,
+        delivery_method,
+        address: address.to_string(),
+        verification_code: format!("{:06}", rand::thread_rng().gen_range(0..1_000_000)),
+        unsubscribe_token: random_string(32),
+    };
+    let code = email.verification_code.clone();
+    let id = db.add_email_address(email).await?;
+
+    let message = build_message(
+        &mailer.from,
+        &db.fetch_email_address(id).await?,
+        "Vahvista sähköpostiosoitteesi",
+        &format!("<p>Vahvistuskoodisi on <b>{}</b></p>", code),
+        &format!("Vahvistuskoodisi on {}\n", code),
+    )?;
+    if let Err(e) = mailer.send(message).await {
+        db.remove_email_address(id).await?;
+        return Err(e);
+    }
+
+    Ok(format!(
+        "A verification code was sent to {}, confirm the address with the code",
+        address
+    ))
+}
+
+/// Verifies the address of the user that was sent the code
+pub async fn verify_email_address(
+    db: Database,
+    code: &str,
+    userid: u64,
+    delivery_method: i32,
+) -> Result<String, Error> {
+    let Some(address) = db
+        .fetch_email_addresses_by_user_id(userid as i64, delivery_method)
+        .await?
+        .into_iter()
+        .find(|a| !a.verified && a.verification_code == code.trim())
+    else {
+        return Ok(String::from("Invalid verification code"));
+    };
+
+    db.verify_email_address(address.id).await?;
+    Ok(format!(
+        "{} verified, use `{}:{}` as the destination of a Vahti",
+        address.address, NAME, address.id
+    ))
+}
+
+/// Removes the address along with the Vahtis delivered to it
+async fn remove(db: &Database, address: &EmailAddress) -> Result<(), Error> {
+    for v in db
+        .fetch_vahti_entries_by_user_id(address.id as i64, ID)
+        .await?
+    {
+        db.remove_vahti_entry(&v.url, v.user_id, ID).await?;
+    }
+    db.remove_email_address(address.id).await?;
+    Ok(())
+}
+
+pub async fn remove_email_address(
+    db: Database,
+    emailid: i32,
+    userid: u64,
+    delivery_method: i32,
+) -> Result<String, Error> {
+    match db.fetch_email_address(emailid).await {
+        Ok(a) if a.user_id == userid as i64 && a.delivery_method == delivery_method => {
+            remove(&db, &a).await?;
+            Ok(String::from("Email address removed"))
+        }
+        _ => Err(Error::InvalidDestination(format!("{}:{}", NAME, emailid))),
+    }
+}
+
+pub async fn list_email_addresses(
+    db: Database,
+    userid: u64,
+    delivery_method: i32,
+) -> Result<String, Error> {
+    let addresses = db
+        .fetch_email_addresses_by_user_id(userid as i64, delivery_method)
+        .await?;
+
+    if addresses.is_empty() {
+        return Ok(String::from("You have no email addresses"));
+    }
+
+    Ok(addresses
+        .iter()
+        .map(|a| {
+            format!(
+                "`{}:{}` {}{}",
+                NAME,
+                a.id,
+                a.address,
+                if a.verified { "" } else { " (unverified)" }
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Parses the token from a request line like `GET /unsubscribe?token=abc HTTP/1.1`
+pub fn unsubscribe_token(request_line: &str) -> Option<String> {
+    let mut parts = request_line.split_whitespace();
+    if parts.next()? != "GET" {
+        return None;
+    }
+
+    let url = url::Url::parse(&format!("http://localhost{}", parts.next()?)).ok()?;
+    if url.path() != "/unsubscribe" {
+        return None;
+    }
+    url.query_pairs()
+        .find(|(k, _)| k == "token")
+        .map(|(_, v)| v.to_string())
+}
+
+/// Handles a single request to the unsubscribe endpoint
+pub async fn handle_unsubscribe(db: Database, request: String) -> Result<Response, Error> {
+    let (status, body) = match unsubscribe_token(request.lines().next().unwrap_or_default()) {
+        Some(token) => match db.fetch_email_address_by_token(&token).await? {
+            Some(address) => {
+                info!("Unsubscribing email address {}", address.id);
+                remove(&db, &address).await?;
+                ("200 OK", "Ilmoitukset tähän osoitteeseen on lopetettu.")
+            }
+            None => ("404 Not Found", "Tilausta ei löytynyt."),
+        },
+        None => ("404 Not Found", "Tilausta ei löytynyt."),
+    };
+
+    Ok(Response {
+        status,
+        content_type: "text/plain; charset=utf-8",
+        body: body.to_string(),
+    })
+}
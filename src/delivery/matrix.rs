@@ -0,0 +1,253 @@
+use async_trait::async_trait;
+
+use crate::database::Database;
+use crate::delivery::{Delivery, Notification};
+use crate::error::Error;
+use crate::matrix::MatrixClient;
+use crate::settings::UserSettings;
+use crate::vahti::VahtiItem;
+
+pub const ID: i32 = 5;
+pub const NAME: &str = "matrix";
+
+pub struct Matrix {
+    pub client: MatrixClient,
+    pub db: Database,
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// This is the Matrix delivery client, the `deliver_to` of the items
+/// is the id of the user in `MatrixUsers`
+impl Matrix {
+    pub async fn init(db: &Database) -> Result<Self, Error> {
+        Ok(Self {
+            client: MatrixClient::from_env()?,
+            db: db.clone(),
+        })
+    }
+
+    pub async fn destroy(self) {}
+
+    async fn room_and_settings(&self, userid: u64) -> Result<(String, UserSettings), Error> {
+        let user = self.db.fetch_matrix_user(userid as i32).await?;
+        let settings = UserSettings::fetch(&self.db, userid as i64, ID).await;
+        Ok((user.room_id, settings))
+    }
+}
+
+impl VahtiItem {
+    /// Formats the item as a plain-text body and a HTML body,
+    /// `image` is the `mxc://` uri of the uploaded image
+    pub fn format_matrix(&self, settings: &UserSettings, image: Option<&str>) -> (String, String) {
+        let seller_url = match self.site_id {
+            #[cfg(feature = "tori")]
+            crate::tori::ID if self.seller_id != 0 => {
+                Some(crate::tori::seller::seller_url(self.seller_id))
+            }
+            #[cfg(feature = "huutonet")]
+            crate::huutonet::ID if self.seller_id != 0 => {
+                Some(crate::huutonet::seller::seller_url(self.seller_id))
+            }
+            _ => None,
+        };
+        let seller = if self.seller_name.is_empty() {
+            "Tuntematon"
+        } else {
+            &self.seller_name
+        };
+        let price = match self.previous_price {
+            Some(old) => format!("{}€ -> {}€", old, self.price),
+            None => format!("{}€", self.price),
+        };
+
+        let mut body = String::new();
+        let mut html = String::new();
+        if self.previous_price.is_some() {
+            body.push_str("Hinta laski!\n");
+            html.push_str("<b>Hinta laski!</b><br>");
+        }
+        if let Some(image) = image {
+            html.push_str(&format!(
+                r#"<img src="{}" alt="{}" height="300"><br>"#,
+                image,
+                escape(&self.title)
+            ));
+        }
+
+        body.push_str(&format!("{}\n{}\n", self.title, self.url));
+        html.push_str(&format!(
+            r#"<a href="{}">{}</a><br>"#,
+            escape(&self.url),
+            escape(&self.title)
+        ));
+
+        body.push_str(&format!("Hinta: {}\n", price));
+        html.push_str(&match self.previous_price {
+            Some(old) => format!("<b>Hinta</b>: <del>{}€</del> {}€<br>", old, self.price),
+            None => format!("<b>Hinta</b>: {}€<br>", self.price),
+        });
+
+        body.push_str(&format!("Myyjä: {}\n", seller));
+        html.push_str(&match seller_url {
+            Some(url) => format!(
+                r#"<b>Myyjä</b>: <a href="{}">{}</a><br>"#,
+                escape(&url),
+                escape(seller)
+            ),
+            None => format!("<b>Myyjä</b>: {}<br>", escape(seller)),
+        });
+
+        body.push_str(&format!("Sijainti: {}\n", self.location));
+        html.push_str(&format!("<b>Sijainti</b>: {}<br>", escape(&self.location)));
+
+        let published = settings.format_time(self.published);
+        body.push_str(&format!("Ilmoitus jätetty: {}\n", published));
+        html.push_str(&format!("<b>Ilmoitus jätetty</b>: {}<br>", published));
+
+        body.push_str(&format!("Ilmoitustyyppi: {}", self.ad_type));
+        html.push_str(&format!("<b>Ilmoitustyyppi</b>: {}", escape(&self.ad_type)));
+
+        if let Some(vahti_url) = &self.vahti_url {
+            body.push_str(&format!("\nHakusivu: {}", vahti_url));
+            html.push_str(&format!(
+                r#"<br><a href="{}">Avaa Hakusivu</a>"#,
+                escape(vahti_url)
+            ));
+        }
+
+        (body, html)
+    }
+}
+
+impl Notification {
+    pub fn format_matrix(&self, settings: &UserSettings) -> (String, String) {
+        match self {
+            Notification::AuctionEnding {
+                title,
+                url,
+                closing_time,
+                current_price,
+                buy_now_price,
+                bidder_count,
+                ..
+            } => {
+                let closing = settings.format_time(*closing_time);
+                let mut body = format!(
+                    "Huutokauppa päättyy pian\n{}\n{}\nNykyinen hinta: {}€\nHuutajia: {}\nPäättyy: {}",
+                    title, url, current_price, bidder_count, closing
+                );
+                let mut html = format!(
+                    r#"<b>Huutokauppa päättyy pian</b><br><a href="{}">{}</a><br><b>Nykyinen hinta</b>: {}€<br><b>Huutajia</b>: {}<br><b>Päättyy</b>: {}"#,
+                    escape(url),
+                    escape(title),
+                    current_price,
+                    bidder_count,
+                    closing
+                );
+                if let Some(p) = buy_now_price {
+                    body.push_str(&format!("\nOsta heti: {}€", p));
+                    html.push_str(&format!("<br><b>Osta heti</b>: {}€", p));
+                }
+                (body, html)
+            }
+            Notification::ListingGone { title, url, status } => (
+                format!("{}\n{}\n{}", status.describe(), title, url),
+                format!(
+                    r#"<b>{}</b><br><a href="{}">{}</a>"#,
+                    status.describe(),
+                    escape(url),
+                    escape(title)
+                ),
+            ),
+        }
+    }
+}
+
+/// Formats a digest into a single message
+fn format_digest(items: &[VahtiItem]) -> (String, String) {
+    let vahti_url = items
+        .first()
+        .and_then(|i| i.vahti_url.clone())
+        .unwrap_or_default();
+
+    let mut body = format!("Kooste: {} uutta ilmoitusta\n{}\n", items.len(), vahti_url);
+    let mut html = format!(
+        r#"<b>Kooste: {} uutta ilmoitusta</b><br><a href="{}">Avaa Hakusivu</a><ul>"#,
+        items.len(),
+        escape(&vahti_url)
+    );
+    for i in items {
+        let price = match i.previous_price {
+            Some(old) => format!("{}€ -> {}€", old, i.price),
+            None => format!("{}€", i.price),
+        };
+        body.push_str(&format!("\n{} {} {}", i.title, price, i.url));
+        html.push_str(&format!(
+            r#"<li><a href="{}">{}</a> {}</li>"#,
+            escape(&i.url),
+            escape(&i.title),
+            price
+        ));
+    }
+    html.push_str("</ul>");
+    (body, html)
+}
+
+#[async_trait]
+impl Delivery for Matrix {
+    async fn deliver(&self, items: Vec<VahtiItem>) -> Result<(), Error> {
+        let Some(fst) = items.first() else {
+            return Ok(());
+        };
+
+        assert!(items.iter().all(|i| i.deliver_to == fst.deliver_to));
+
+        let userid = fst.deliver_to.expect("bug: impossible");
+        info!("Delivering {} items to {}", items.len(), userid);
+
+        let (room, settings) = self.room_and_settings(userid).await?;
+        for i in items {
+            let image = if i.img_url.is_empty() {
+                None
+            } else {
+                // NOTE: The item is delivered without the image if the upload fails
+                self.client
+                    .upload_image(&i.img_url)
+                    .await
+                    .map_err(|e| warn!("Failed to upload {}: {}", i.img_url, e))
+                    .ok()
+            };
+
+            let (body, html) = i.format_matrix(&settings, image.as_deref());
+            self.client.send_message(&room, &body, &html).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn deliver_digest(&self, items: Vec<VahtiItem>) -> Result<(), Error> {
+        let Some(fst) = items.first() else {
+            return Ok(());
+        };
+
+        let userid = fst.deliver_to.expect("bug: impossible");
+        info!("Delivering a digest of {} items to {}", items.len(), userid);
+
+        let (room, _) = self.room_and_settings(userid).await?;
+        let (body, html) = format_digest(&items);
+        self.client.send_message(&room, &body, &html).await
+    }
+
+    async fn notify(&self, deliver_to: u64, notification: Notification) -> Result<(), Error> {
+        info!("Sending a notification to {}", deliver_to);
+        let (room, settings) = self.room_and_settings(deliver_to).await?;
+        let (body, html) = notification.format_matrix(&settings);
+        self.client.send_message(&room, &body, &html).await
+    }
+}
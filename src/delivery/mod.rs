@@ -4,12 +4,28 @@ pub mod discord;
 #[cfg(feature = "telegram-delivery")]
 pub mod telegram;
 
+#[cfg(feature = "webhook-delivery")]
+pub mod webhook;
+
+#[cfg(feature = "email-delivery")]
+pub mod email;
+
+#[cfg(feature = "matrix-delivery")]
+pub mod matrix;
+
+#[cfg(feature = "push-delivery")]
+pub mod push;
+
 use std::sync::Arc;
 
 use async_trait::async_trait;
 use dashmap::DashMap;
+use serde::Serialize;
 
+use crate::bookmark::ListingStatus;
+use crate::database::Database;
 use crate::error::Error;
+use crate::models::NewOutboxEntry;
 use crate::vahti::VahtiItem;
 
 /// This is the Delivery trait. It should be implemented for
@@ -24,21 +40,150 @@ where
     Self: Send + Sync,
 {
     async fn deliver(&self, vs: Vec<VahtiItem>) -> Result<(), Error>;
+    /// Delivers the buffered items of a single Vahti as one digest
+    async fn deliver_digest(&self, vs: Vec<VahtiItem>) -> Result<(), Error>;
+    async fn notify(&self, deliver_to: u64, notification: Notification) -> Result<(), Error>;
+}
+
+/// Notifications that are sent to the users outside of the Vahti updates
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Notification {
+    /// An auction the user is watching is about to end
+    AuctionEnding {
+        title: String,
+        url: String,
+        closing_time: i64,
+        current_price: i64,
+        buy_now_price: Option<i64>,
+        bidder_count: i64,
+        /// None if the auction doesn't have a reserve price
+        reserve_price_exceeded: Option<bool>,
+    },
+    /// A listing the user has bookmarked is no longer available
+    ListingGone {
+        title: String,
+        url: String,
+        status: ListingStatus,
+    },
+}
+
+/// How many items the delivery method sends in a single message
+fn message_size(delivery_method: i32) -> usize {
+    match delivery_method {
+        #[cfg(feature = "discord-delivery")]
+        discord::ID | discord::CHANNEL_ID => discord::MESSAGE_SIZE,
+        #[cfg(feature = "telegram-delivery")]
+        telegram::ID => 1,
+        #[cfg(feature = "matrix-delivery")]
+        matrix::ID => 1,
+        #[cfg(feature = "push-delivery")]
+        push::ID => 1,
+        _ => usize::MAX,
+    }
+}
+
+/// Queues the items into the outbox, from which they are delivered with retries.
+/// Every message is queued on its own, so that a failed message doesn't make the
+/// ones that did get through to be delivered again.
+/// All of the items must have the same delivery_method and deliver_to fields
+pub async fn perform_delivery(db: &Database, vs: Vec<VahtiItem>) -> Result<(), Error> {
+    if let Some(v) = vs.first() {
+        assert!(vs
+            .iter()
+            .all(|vc| vc.delivery_method == v.delivery_method && vc.deliver_to == v.deliver_to));
+
+        let now = chrono::Local::now().timestamp();
+        let delivery_method = v.delivery_method.expect("bug: impossible");
+        for chunk in vs.chunks(message_size(delivery_method)) {
+            db.add_outbox_entry(NewOutboxEntry {
+                user_id: v.deliver_to.expect("bug: impossible") as i64,
+                delivery_method,
+                items: serde_json::to_string(chunk)?,
+                created_at: now,
+                next_attempt: now,
+                digest: false,
+            })
+            .await?;
+        }
+    }
+
+    Ok(())
 }
 
-pub async fn perform_delivery(
+/// Delivers the items right away, this is what the outbox worker does with the queued items
+pub async fn attempt_delivery(
     delivery: Arc<DashMap<i32, Box<dyn Delivery + Sync + Send>>>,
     vs: Vec<VahtiItem>,
 ) -> Result<(), Error> {
     if let Some(v) = vs.first() {
         assert!(vs.iter().all(|vc| vc.delivery_method == v.delivery_method));
 
+        let delivery_method = v.delivery_method.expect("bug: impossible");
+        // NOTE: The entry may have been queued before the delivery method was disabled,
+        // panicking here would stop the outbox worker altogether
         return delivery
-            .get(&v.delivery_method.expect("bug: impossible"))
-            .expect("Missing delivery-method feature")
+            .get(&delivery_method)
+            .ok_or(Error::InvalidDestination(delivery_method.to_string()))?
             .deliver(vs)
             .await;
     }
 
     Ok(())
 }
+
+/// Queues the items of a digest into the outbox as a single entry.
+/// All of the items must have the same delivery_method and deliver_to fields
+pub async fn perform_digest_delivery(db: &Database, vs: Vec<VahtiItem>) -> Result<(), Error> {
+    if let Some(v) = vs.first() {
+        assert!(vs
+            .iter()
+            .all(|vc| vc.delivery_method == v.delivery_method && vc.deliver_to == v.deliver_to));
+
+        let now = chrono::Local::now().timestamp();
+        db.add_outbox_entry(NewOutboxEntry {
+            user_id: v.deliver_to.expect("bug: impossible") as i64,
+            delivery_method: v.delivery_method.expect("bug: impossible"),
+            items: serde_json::to_string(&vs)?,
+            created_at: now,
+            next_attempt: now,
+            digest: true,
+        })
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Delivers the items of a digest right away
+pub async fn attempt_digest_delivery(
+    delivery: Arc<DashMap<i32, Box<dyn Delivery + Sync + Send>>>,
+    vs: Vec<VahtiItem>,
+) -> Result<(), Error> {
+    if let Some(v) = vs.first() {
+        assert!(vs.iter().all(|vc| vc.delivery_method == v.delivery_method));
+
+        let delivery_method = v.delivery_method.expect("bug: impossible");
+        // NOTE: The digest Vahti may deliver with a method that isn't compiled in
+        return delivery
+            .get(&delivery_method)
+            .ok_or(Error::InvalidDestination(delivery_method.to_string()))?
+            .deliver_digest(vs)
+            .await;
+    }
+
+    Ok(())
+}
+
+pub async fn perform_notification(
+    delivery: Arc<DashMap<i32, Box<dyn Delivery + Sync + Send>>>,
+    deliver_to: u64,
+    delivery_method: i32,
+    notification: Notification,
+) -> Result<(), Error> {
+    delivery
+        .get(&delivery_method)
+        .ok_or(Error::InvalidDestination(delivery_method.to_string()))?
+        .notify(deliver_to, notification)
+        .await
+}
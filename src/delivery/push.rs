@@ -0,0 +1,328 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::{json, Value};
+
+use crate::database::Database;
+use crate::delivery::{Delivery, Notification};
+use crate::error::Error;
+use crate::models::{NewPushTarget, PushTarget};
+use crate::vahti::VahtiItem;
+
+pub const ID: i32 = 6;
+pub const NAME: &str = "push";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushService {
+    Ntfy,
+    Gotify,
+}
+
+impl FromStr for PushService {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "ntfy" => Ok(PushService::Ntfy),
+            "gotify" => Ok(PushService::Gotify),
+            _ => Err(Error::InvalidDestination(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for PushService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PushService::Ntfy => write!(f, "ntfy"),
+            PushService::Gotify => write!(f, "gotify"),
+        }
+    }
+}
+
+/// A single push notification
+#[derive(Debug, Clone, PartialEq)]
+pub struct PushMessage {
+    pub title: String,
+    pub message: String,
+    /// Opened when the notification is clicked
+    pub click: Option<String>,
+    /// The url of an image attached to the notification
+    pub image: Option<String>,
+}
+
+impl From<&VahtiItem> for PushMessage {
+    fn from(i: &VahtiItem) -> PushMessage {
+        let price = match i.previous_price {
+            Some(old) => format!("Hinta laski: {}€ -> {}€", old, i.price),
+            None => format!("Hinta: {}€", i.price),
+        };
+
+        PushMessage {
+            title: i.title.clone(),
+            message: format!("{}\nSijainti: {}", price, i.location),
+            click: Some(i.url.clone()),
+            image: Some(i.img_url.clone()).filter(|u| !u.is_empty()),
+        }
+    }
+}
+
+impl From<&Notification> for PushMessage {
+    fn from(n: &Notification) -> PushMessage {
+        match n {
+            Notification::AuctionEnding {
+                title,
+                url,
+                current_price,
+                bidder_count,
+                ..
+            } => PushMessage {
+                title: format!("Huutokauppa päättyy pian: {}", title),
+                message: format!(
+                    "Nykyinen hinta: {}€\nHuutajia: {}",
+                    current_price, bidder_count
+                ),
+                click: Some(url.clone()),
+                image: None,
+            },
+            Notification::ListingGone { title, url, status } => PushMessage {
+                title: status.describe().to_string(),
+                message: title.clone(),
+                click: Some(url.clone()),
+                image: None,
+            },
+        }
+    }
+}
+
+/// The JSON body published to the root of a ntfy server
+pub fn ntfy_body(topic: &str, message: &PushMessage) -> Value {
+    let mut body = json!({
+        "topic": topic,
+        "title": message.title,
+        "message": message.message,
+    });
+    if let Some(click) = &message.click {
+        body["click"] = json!(click);
+    }
+    if let Some(image) = &message.image {
+        body["attach"] = json!(image);
+    }
+    body
+}
+
+/// The JSON body of a Gotify message
+pub fn gotify_body(message: &PushMessage) -> Value {
+    let mut notification = json!({});
+    if let Some(click) = &message.click {
+        notification["click"] = json!({ "url": click });
+    }
+    if let Some(image) = &message.image {
+        notification["bigImageUrl"] = json!(image);
+    }
+
+    json!({
+        "title": message.title,
+        "message": message.message,
+        "extras": {
+            "client::display": { "contentType": "text/plain" },
+            "client::notification": notification,
+        },
+    })
+}
+
+/// Publishes the message to the target
+pub async fn publish(
+    client: &Client,
+    target: &PushTarget,
+    message: &PushMessage,
+) -> Result<(), Error> {
+    let server = target.server.trim_end_matches('/');
+    let request = match target.service.parse::<PushService>()? {
+        PushService::Ntfy => {
+            let request = client.post(server).json(&ntfy_body(
+                target.topic.as_deref().unwrap_or_default(),
+                message,
+            ));
+            match &target.token {
+                Some(token) => request.bearer_auth(token),
+                None => request,
+            }
+        }
+        PushService::Gotify => client
+            .post(format!("{}/message", server))
+            .header("X-Gotify-Key", target.token.as_deref().unwrap_or_default())
+            .json(&gotify_body(message)),
+    };
+
+    request.send().await?.error_for_status()?;
+    Ok(())
+}
+
+pub struct Push {
+    pub client: Client,
+    pub db: Database,
+}
+
+/// This is the push notification delivery client, the `deliver_to` of the items
+/// is the id of the push target
+impl Push {
+    pub async fn init(db: &Database) -> Result<Self, Error> {
+        let client = Client::builder().timeout(Duration::from_secs(10)).build()?;
+        Ok(Self {
+            client,
+            db: db.clone(),
+        })
+    }
+
+    pub async fn destroy(self) {}
+}
+
+#[async_trait]
+impl Delivery for Push {
+    async fn deliver(&self, items: Vec<VahtiItem>) -> Result<(), Error> {
+        let Some(fst) = items.first() else {
+            return Ok(());
+        };
+
+        let targetid = fst.deliver_to.expect("bug: impossible");
+        info!("Pushing {} items to {}", items.len(), targetid);
+
+        let target = self.db.fetch_push_target(targetid as i32).await?;
+        for i in &items {
+            publish(&self.client, &target, &PushMessage::from(i)).await?;
+        }
+        Ok(())
+    }
+
+    async fn deliver_digest(&self, items: Vec<VahtiItem>) -> Result<(), Error> {
+        let Some(fst) = items.first() else {
+            return Ok(());
+        };
+
+        let targetid = fst.deliver_to.expect("bug: impossible");
+        info!("Pushing a digest of {} items to {}", items.len(), targetid);
+
+        let target = self.db.fetch_push_target(targetid as i32).await?;
+        let message = PushMessage {
+            title: format!("Kooste: {} uutta ilmoitusta", items.len()),
+            message: items
+                .iter()
+                .map(|i| format!("{} {}€", i.title, i.price))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            click: fst.vahti_url.clone(),
+            image: None,
+        };
+        publish(&self.client, &target, &message).await
+    }
+
+    async fn notify(&self, deliver_to: u64, notification: Notification) -> Result<(), Error> {
+        info!("Pushing a notification to {}", deliver_to);
+        let target = self.db.fetch_push_target(deliver_to as i32).await?;
+        publish(&self.client, &target, &PushMessage::from(&notification)).await
+    }
+}
+
+/// Registers a new push target for the user, `topic` is required by ntfy
+/// and `token` by Gotify
+pub async fn add_push_target(
+    db: Database,
+    service: PushService,
+    server: &str,
+    topic: Option<String>,
+    token: Option<String>,
+    userid: u64,
+    delivery_method: i32,
+) -> Result<String, Error> {
+    if !(server.starts_with("https://") || server.starts_with("http://")) {
+        return Err(Error::InvalidDestination(server.to_string()));
+    }
+    match service {
+        PushService::Ntfy if topic.is_none() => {
+            return Err(Error::InvalidDestination(String::from(
+                "ntfy requires a topic",
+            )))
+        }
+        PushService::Gotify if token.is_none() => {
+            return Err(Error::InvalidDestination(String::from(
+                "Gotify requires an application token",
+            )))
+        }
+        _ => {}
+    }
+
+    let id = db
+        .add_push_target(NewPushTarget {
+            user_id: userid as i64,
+            delivery_method,
+            service: service.to_string(),
+            server: server.to_string(),
+            topic,
+            token,
+        })
+        .await?;
+
+    Ok(format!(
+        "Push target added, use `{}:{}` as the destination of a Vahti",
+        NAME, id
+    ))
+}
+
+/// Removes a push target of the user along with its Vahtis
+pub async fn remove_push_target(
+    db: Database,
+    targetid: i32,
+    userid: u64,
+    delivery_method: i32,
+) -> Result<String, Error> {
+    match db.fetch_push_target(targetid).await {
+        Ok(t) if t.user_id == userid as i64 && t.delivery_method == delivery_method => {}
+        _ => return Err(Error::InvalidDestination(format!("{}:{}", NAME, targetid))),
+    }
+
+    for v in db
+        .fetch_vahti_entries_by_user_id(targetid as i64, ID)
+        .await?
+    {
+        db.remove_vahti_entry(&v.url, v.user_id, ID).await?;
+    }
+    db.remove_push_target(targetid).await?;
+
+    Ok(String::from("Push target removed"))
+}
+
+/// Lists the push targets of the user
+pub async fn list_push_targets(
+    db: Database,
+    userid: u64,
+    delivery_method: i32,
+) -> Result<String, Error> {
+    let targets = db
+        .fetch_push_targets_by_user_id(userid as i64, delivery_method)
+        .await?;
+
+    if targets.is_empty() {
+        return Ok(String::from("You have no push targets"));
+    }
+
+    Ok(targets
+        .iter()
+        .map(|t| {
+            format!(
+                "`{}:{}` {} {}{}",
+                NAME,
+                t.id,
+                t.service,
+                t.server,
+                t.topic
+                    .as_ref()
+                    .map(|topic| format!(" ({})", topic))
+                    .unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
@@ -1,16 +1,22 @@
+use std::fmt;
+use std::str::FromStr;
+
 use async_trait::async_trait;
-use chrono::{Local, TimeZone};
 use futures::stream::{self, StreamExt};
 use teloxide::adaptors::throttle::Limits;
 use teloxide::prelude::*;
-use teloxide::types::{ChatId, InputFile, ParseMode};
+use teloxide::types::{ChatId, InlineKeyboardButton, InlineKeyboardMarkup, InputFile, ParseMode};
+use teloxide::utils::html::escape;
 
-use crate::delivery::Delivery;
+use crate::database::Database;
+use crate::delivery::{Delivery, Notification};
 use crate::error::Error;
+use crate::settings::UserSettings;
 use crate::vahti::VahtiItem;
 
 pub struct Telegram {
     pub bot: Bot,
+    pub db: Database,
 }
 
 pub const ID: i32 = 2;
@@ -19,18 +25,100 @@ pub const NAME: &str = "telegram";
 /// This is the telegram delivery client
 /// There will be a separate client for handling commands
 impl Telegram {
-    pub async fn init() -> Result<Self, Error> {
+    pub async fn init(db: &Database) -> Result<Self, Error> {
         let token =
             std::env::var("TELOXIDE_TOKEN").expect("Expected TELOXIDE_TOKEN in the environment");
         let bot = Bot::new(token);
-        Ok(Self { bot })
+        Ok(Self {
+            bot,
+            db: db.clone(),
+        })
     }
 
     pub async fn destroy(self) {}
 }
 
+/// The action of an inline keyboard button, Telegram limits
+/// the callback data to 64 bytes so the format is kept compact
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TelegramAction {
+    BlockSeller { site_id: i32, seller_id: i32 },
+    UnblockSeller { site_id: i32, seller_id: i32 },
+    RemoveVahti { vahti_id: i32 },
+    MuteVahti { vahti_id: i32 },
+}
+
+impl fmt::Display for TelegramAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TelegramAction::BlockSeller { site_id, seller_id } => {
+                write!(f, "b:{}:{}", site_id, seller_id)
+            }
+            TelegramAction::UnblockSeller { site_id, seller_id } => {
+                write!(f, "u:{}:{}", site_id, seller_id)
+            }
+            TelegramAction::RemoveVahti { vahti_id } => write!(f, "r:{}", vahti_id),
+            TelegramAction::MuteVahti { vahti_id } => write!(f, "m:{}", vahti_id),
+        }
+    }
+}
+
+impl FromStr for TelegramAction {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.split(':').collect::<Vec<_>>();
+        let num = |i: usize| parts.get(i).and_then(|p| p.parse::<i32>().ok()).ok_or(());
+
+        match (parts.first().copied(), parts.len()) {
+            (Some("b"), 3) => Ok(TelegramAction::BlockSeller {
+                site_id: num(1)?,
+                seller_id: num(2)?,
+            }),
+            (Some("u"), 3) => Ok(TelegramAction::UnblockSeller {
+                site_id: num(1)?,
+                seller_id: num(2)?,
+            }),
+            (Some("r"), 2) => Ok(TelegramAction::RemoveVahti { vahti_id: num(1)? }),
+            (Some("m"), 2) => Ok(TelegramAction::MuteVahti { vahti_id: num(1)? }),
+            _ => Err(()),
+        }
+    }
+}
+
 impl VahtiItem {
-    fn format_telegram(self) -> String {
+    /// The inline keyboard attached to the item, `None` if there are no actions
+    pub fn telegram_keyboard(&self) -> Option<InlineKeyboardMarkup> {
+        let mut buttons = vec![];
+        if self.seller_id != 0 {
+            buttons.push(InlineKeyboardButton::callback(
+                "Estä myyjä",
+                TelegramAction::BlockSeller {
+                    site_id: self.site_id,
+                    seller_id: self.seller_id,
+                }
+                .to_string(),
+            ));
+        }
+        if let Some(vahti_id) = self.vahti_id {
+            buttons.push(InlineKeyboardButton::callback(
+                "Poista vahti",
+                TelegramAction::RemoveVahti { vahti_id }.to_string(),
+            ));
+            buttons.push(InlineKeyboardButton::callback(
+                "Hiljennä vahti",
+                TelegramAction::MuteVahti { vahti_id }.to_string(),
+            ));
+        }
+
+        if buttons.is_empty() {
+            None
+        } else {
+            Some(InlineKeyboardMarkup::new([buttons]))
+        }
+    }
+
+    pub fn format_telegram(self, settings: &UserSettings) -> String {
         let sellerurl = match self.site_id {
             #[cfg(feature = "tori")]
             crate::tori::ID => {
@@ -43,27 +131,47 @@ impl VahtiItem {
             i => panic!("Unsupported site_id {}", i),
         };
 
-        let mut msg = format!(r#"<a href="{}">{}</a>"#, self.url, self.title) + "\n";
-        msg.push_str((format!(r#"<b>Hinta</b>: {}€"#, self.price) + "\n").as_str());
+        let mut msg = String::new();
+        if self.previous_price.is_some() {
+            msg.push_str("<b>Hinta laski!</b>\n");
+        }
         msg.push_str(
-            (format!(
-                r#"<b>Myyjä</b>: <a href="{}">{}</a>"#,
-                sellerurl, self.seller_name
-            ) + "\n")
-                .as_str(),
+            (format!(r#"<a href="{}">{}</a>"#, self.url, escape(&self.title)) + "\n").as_str(),
         );
-        msg.push_str((format!(r#"<b>Sijainti</b>: {}"#, self.location) + "\n").as_str());
+        match self.previous_price {
+            Some(old) => msg.push_str(
+                (format!(r#"<b>Hinta</b>: <s>{}€</s> {}€"#, old, self.price) + "\n").as_str(),
+            ),
+            None => msg.push_str((format!(r#"<b>Hinta</b>: {}€"#, self.price) + "\n").as_str()),
+        }
+        if self.seller_id == 0 {
+            let seller = if self.seller_name.is_empty() {
+                "Tuntematon"
+            } else {
+                &self.seller_name
+            };
+            msg.push_str((format!(r#"<b>Myyjä</b>: {}"#, escape(seller)) + "\n").as_str());
+        } else {
+            msg.push_str(
+                (format!(
+                    r#"<b>Myyjä</b>: <a href="{}">{}</a>"#,
+                    sellerurl,
+                    escape(&self.seller_name)
+                ) + "\n")
+                    .as_str(),
+            );
+        }
+        msg.push_str((format!(r#"<b>Sijainti</b>: {}"#, escape(&self.location)) + "\n").as_str());
         msg.push_str(
             (format!(
                 r#"<b>Ilmoitus jätetty</b>: {}"#,
-                Local
-                    .timestamp_opt(self.published, 0)
-                    .unwrap()
-                    .format("%d/%m/%Y %R")
+                settings.format_time(self.published)
             ) + "\n")
                 .as_str(),
         );
-        msg.push_str((format!(r#"<b>Ilmoitustyyppi</b>: {}"#, self.ad_type) + "\n").as_str());
+        msg.push_str(
+            (format!(r#"<b>Ilmoitustyyppi</b>: {}"#, escape(&self.ad_type)) + "\n").as_str(),
+        );
         msg.push_str(&format!(
             r#"<a href="{}">Avaa Hakusivu</a>"#,
             self.vahti_url.unwrap()
@@ -73,8 +181,128 @@ impl VahtiItem {
     }
 }
 
+/// Telegram doesn't accept messages longer than this
+const MAX_MESSAGE_LENGTH: usize = 4096;
+
+/// Formats a digest into as few messages as possible
+fn format_digest(items: Vec<VahtiItem>) -> Vec<String> {
+    let vahti_url = items
+        .first()
+        .and_then(|i| i.vahti_url.clone())
+        .unwrap_or_default();
+
+    let mut messages = vec![format!(
+        "<b>Kooste: {} uutta ilmoitusta</b>\n<a href=\"{}\">Avaa Hakusivu</a>\n",
+        items.len(),
+        vahti_url
+    )];
+    for i in items {
+        let price = match i.previous_price {
+            Some(old) => format!("<s>{}€</s> {}€", old, i.price),
+            None => format!("{}€", i.price),
+        };
+        let line = format!("\n<a href=\"{}\">{}</a> {}", i.url, escape(&i.title), price);
+
+        let last = messages.last_mut().expect("bug: impossible");
+        if last.len() + line.len() > MAX_MESSAGE_LENGTH {
+            messages.push(line.trim_start().to_string());
+        } else {
+            last.push_str(&line);
+        }
+    }
+    messages
+}
+
+impl Notification {
+    fn format_telegram(self, settings: &UserSettings) -> String {
+        match self {
+            Notification::AuctionEnding {
+                title,
+                url,
+                closing_time,
+                current_price,
+                buy_now_price,
+                bidder_count,
+                reserve_price_exceeded,
+            } => {
+                let mut msg = String::from("<b>Huutokauppa päättyy pian</b>\n");
+                msg.push_str(&format!(r#"<a href="{}">{}</a>"#, url, escape(&title)));
+                msg.push('\n');
+                msg.push_str(&format!("<b>Nykyinen hinta</b>: {}€\n", current_price));
+                msg.push_str(&format!("<b>Huutajia</b>: {}\n", bidder_count));
+                msg.push_str(&format!(
+                    "<b>Päättyy</b>: {}",
+                    settings.format_time(closing_time)
+                ));
+                if let Some(p) = buy_now_price {
+                    msg.push_str(&format!("\n<b>Osta heti</b>: {}€", p));
+                }
+                if let Some(exceeded) = reserve_price_exceeded {
+                    msg.push_str(&format!(
+                        "\n<b>Pohjahinta</b>: {}",
+                        if exceeded {
+                            "Ylittynyt"
+                        } else {
+                            "Ei ylittynyt"
+                        }
+                    ));
+                }
+                msg
+            }
+            Notification::ListingGone { title, url, status } => {
+                format!(
+                    "<b>{}</b>\n<a href=\"{}\">{}</a>",
+                    status.describe(),
+                    url,
+                    escape(&title)
+                )
+            }
+        }
+    }
+}
+
 #[async_trait]
 impl Delivery for Telegram {
+    async fn notify(&self, deliver_to: u64, notification: Notification) -> Result<(), Error> {
+        info!("Sending a notification to {}", deliver_to);
+        let settings = UserSettings::fetch(&self.db, deliver_to as i64, ID).await;
+        self.bot
+            .clone()
+            .throttle(Limits::default())
+            .send_message(
+                ChatId(deliver_to as i64),
+                notification.format_telegram(&settings),
+            )
+            .parse_mode(ParseMode::Html)
+            .await?;
+        Ok(())
+    }
+
+    async fn deliver_digest(&self, items: Vec<VahtiItem>) -> Result<(), Error> {
+        let Some(fst) = items.first() else {
+            return Ok(());
+        };
+
+        info!(
+            "Delivering a digest of {} items to {}",
+            items.len(),
+            fst.deliver_to.unwrap()
+        );
+
+        let recipient = ChatId(fst.deliver_to.unwrap() as i64);
+        for message in format_digest(items) {
+            self.bot
+                .clone()
+                .throttle(Limits::default())
+                .send_message(recipient, message)
+                .parse_mode(ParseMode::Html)
+                .disable_web_page_preview(true)
+                .await?;
+        }
+
+        Ok(())
+    }
+
     async fn deliver(&self, items: Vec<VahtiItem>) -> Result<(), Error> {
         let Some(fst) = items.first() else {
             return Ok(());
@@ -89,28 +317,35 @@ impl Delivery for Telegram {
         );
 
         let recipient = ChatId(fst.deliver_to.unwrap() as i64);
+        let settings = UserSettings::fetch(&self.db, fst.deliver_to.unwrap() as i64, ID).await;
 
         stream::iter(items.iter().cloned())
-            .map(async move |i| {
+            .map(|i| (i, settings.clone()))
+            .map(async move |(i, settings)| {
                 let file = if i.img_url.is_empty() {
                     InputFile::file("./media/no_image.jpg")
                 } else {
                     InputFile::url(url::Url::parse(&i.img_url).unwrap())
                 };
 
-                self.bot
+                let request = self
+                    .bot
                     .clone()
                     .throttle(Limits::default())
                     .send_photo(recipient, file)
-                    .caption(i.clone().format_telegram())
-                    .parse_mode(ParseMode::Html)
-                    .await
-                    // FIXME: Perhaps don't ignore an error here
-                    .ok()
+                    .caption(i.clone().format_telegram(&settings))
+                    .parse_mode(ParseMode::Html);
+
+                match i.telegram_keyboard() {
+                    Some(keyboard) => request.reply_markup(keyboard).await,
+                    None => request.await,
+                }
             })
             .buffer_unordered(*crate::FUTURES_MAX_BUFFER_SIZE)
             .collect::<Vec<_>>()
-            .await;
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
 
         Ok(())
     }
@@ -0,0 +1,288 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use reqwest::{Client, StatusCode};
+use serde::Serialize;
+use sha2::Sha256;
+
+use crate::database::Database;
+use crate::delivery::{Delivery, Notification};
+use crate::error::Error;
+use crate::models::NewWebhook;
+use crate::vahti::VahtiItem;
+
+pub const ID: i32 = 3;
+pub const NAME: &str = "webhook";
+
+/// The version of the JSON payload, bumped on incompatible changes
+pub const PAYLOAD_VERSION: u32 = 1;
+
+/// The header containing the hex-encoded HMAC-SHA256 of the body,
+/// present only if the webhook has a secret
+pub const SIGNATURE_HEADER: &str = "X-Torimies-Signature";
+
+/// A listing as it is sent in the payload
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct WebhookItem {
+    pub site: String,
+    pub ad_id: i64,
+    pub title: String,
+    pub url: String,
+    pub img_url: String,
+    pub published: i64,
+    pub price: i64,
+    pub previous_price: Option<i64>,
+    pub seller_name: String,
+    pub seller_id: i32,
+    pub location: String,
+    pub ad_type: String,
+    pub vahti_url: Option<String>,
+}
+
+impl From<VahtiItem> for WebhookItem {
+    fn from(i: VahtiItem) -> WebhookItem {
+        let site = match i.site_id {
+            #[cfg(feature = "tori")]
+            crate::tori::ID => crate::tori::NAME,
+            #[cfg(feature = "huutonet")]
+            crate::huutonet::ID => crate::huutonet::NAME,
+            _ => "unknown",
+        };
+
+        WebhookItem {
+            site: site.to_string(),
+            ad_id: i.ad_id,
+            title: i.title,
+            url: i.url,
+            img_url: i.img_url,
+            published: i.published,
+            price: i.price,
+            previous_price: i.previous_price,
+            seller_name: i.seller_name,
+            seller_id: i.seller_id,
+            location: i.location,
+            ad_type: i.ad_type,
+            vahti_url: i.vahti_url,
+        }
+    }
+}
+
+/// The body of every request, `type` is one of `items`, `digest` or `notification`
+#[derive(Serialize, Debug)]
+pub struct Payload {
+    pub version: u32,
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub webhook_id: u64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub items: Vec<WebhookItem>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notification: Option<Notification>,
+}
+
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC can take a key of any size");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// POSTs `body` to `url`, retrying with an exponential backoff on connection
+/// errors, server errors and rate limits
+pub async fn post(
+    client: &Client,
+    url: &str,
+    secret: Option<&str>,
+    body: String,
+    retries: u32,
+    backoff: Duration,
+) -> Result<(), Error> {
+    let mut attempt = 0;
+    loop {
+        let mut request = client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.clone());
+        if let Some(secret) = secret {
+            request = request.header(SIGNATURE_HEADER, sign(secret, &body));
+        }
+
+        match request.send().await {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) => {
+                let status = response.status();
+                let retryable = status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS;
+                if !retryable || attempt >= retries {
+                    return Err(response.error_for_status().unwrap_err().into());
+                }
+                warn!("Webhook {} responded with {}", url, status);
+            }
+            Err(e) if attempt >= retries => return Err(e.into()),
+            Err(e) => warn!("Failed to send to webhook {}: {}", url, e),
+        }
+
+        tokio::time::sleep(backoff * 2u32.pow(attempt)).await;
+        attempt += 1;
+    }
+}
+
+pub struct Webhook {
+    pub client: Client,
+    pub db: Database,
+}
+
+/// This is the webhook delivery client, the `deliver_to` of the items
+/// is the id of the webhook
+impl Webhook {
+    pub async fn init(db: &Database) -> Result<Self, Error> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(*crate::WEBHOOK_TIMEOUT))
+            .build()?;
+        Ok(Self {
+            client,
+            db: db.clone(),
+        })
+    }
+
+    pub async fn destroy(self) {}
+
+    async fn send(&self, webhookid: u64, payload: Payload) -> Result<(), Error> {
+        let webhook = self.db.fetch_webhook(webhookid as i32).await?;
+        post(
+            &self.client,
+            &webhook.url,
+            webhook.secret.as_deref(),
+            serde_json::to_string(&payload)?,
+            *crate::WEBHOOK_RETRIES,
+            Duration::from_secs(1),
+        )
+        .await
+    }
+
+    async fn send_items(&self, kind: &'static str, items: Vec<VahtiItem>) -> Result<(), Error> {
+        let Some(fst) = items.first() else {
+            return Ok(());
+        };
+
+        let webhookid = fst.deliver_to.expect("bug: impossible");
+        info!("Delivering {} items to webhook {}", items.len(), webhookid);
+
+        self.send(
+            webhookid,
+            Payload {
+                version: PAYLOAD_VERSION,
+                kind,
+                webhook_id: webhookid,
+                items: items.into_iter().map(WebhookItem::from).collect(),
+                notification: None,
+            },
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl Delivery for Webhook {
+    async fn deliver(&self, items: Vec<VahtiItem>) -> Result<(), Error> {
+        self.send_items("items", items).await
+    }
+
+    async fn deliver_digest(&self, items: Vec<VahtiItem>) -> Result<(), Error> {
+        self.send_items("digest", items).await
+    }
+
+    async fn notify(&self, deliver_to: u64, notification: Notification) -> Result<(), Error> {
+        info!("Sending a notification to webhook {}", deliver_to);
+        self.send(
+            deliver_to,
+            Payload {
+                version: PAYLOAD_VERSION,
+                kind: "notification",
+                webhook_id: deliver_to,
+                items: vec![],
+                notification: Some(notification),
+            },
+        )
+        .await
+    }
+}
+
+/// Registers a new webhook for the user
+pub async fn add_webhook(
+    db: Database,
+    url: &str,
+    secret: Option<String>,
+    userid: u64,
+    delivery_method: i32,
+) -> Result<String, Error> {
+    if !(url.starts_with("https://") || url.starts_with("http://")) {
+        return Err(Error::InvalidDestination(url.to_string()));
+    }
+
+    let id = db
+        .add_webhook(NewWebhook {
+            user_id: userid as i64,
+            delivery_method,
+            url: url.to_string(),
+            secret,
+        })
+        .await?;
+
+    Ok(format!(
+        "Webhook added, use `{}:{}` as the destination of a Vahti",
+        NAME, id
+    ))
+}
+
+/// Removes a webhook of the user along with its Vahtis
+pub async fn remove_webhook(
+    db: Database,
+    webhookid: i32,
+    userid: u64,
+    delivery_method: i32,
+) -> Result<String, Error> {
+    match db.fetch_webhook(webhookid).await {
+        Ok(w) if w.user_id == userid as i64 && w.delivery_method == delivery_method => {}
+        _ => return Err(Error::InvalidDestination(format!("{}:{}", NAME, webhookid))),
+    }
+
+    for v in db
+        .fetch_vahti_entries_by_user_id(webhookid as i64, ID)
+        .await?
+    {
+        db.remove_vahti_entry(&v.url, v.user_id, ID).await?;
+    }
+    db.remove_webhook(webhookid).await?;
+
+    Ok(String::from("Webhook removed"))
+}
+
+/// Lists the webhooks of the user
+pub async fn list_webhooks(
+    db: Database,
+    userid: u64,
+    delivery_method: i32,
+) -> Result<String, Error> {
+    let webhooks = db
+        .fetch_webhooks_by_user_id(userid as i64, delivery_method)
+        .await?;
+
+    if webhooks.is_empty() {
+        return Ok(String::from("You have no webhooks"));
+    }
+
+    Ok(webhooks
+        .iter()
+        .map(|w| {
+            format!(
+                "`{}:{}` {}{}",
+                NAME,
+                w.id,
+                w.url,
+                if w.secret.is_some() { " (signed)" } else { "" }
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
@@ -0,0 +1,211 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveTime, TimeZone, Timelike, Weekday};
+
+use crate::database::Database;
+use crate::delivery::perform_digest_delivery;
+use crate::error::Error;
+use crate::settings::UserSettings;
+use crate::vahti::{canonicalize_url, VahtiItem};
+use crate::Torimies;
+
+/// When the buffered items of a Vahti are delivered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestSchedule {
+    Hourly,
+    Daily(NaiveTime),
+    Weekly(Weekday, NaiveTime),
+}
+
+impl DigestSchedule {
+    /// Returns the first time the digest should be sent after `after`
+    pub fn next_after<Tz: TimeZone>(&self, after: &DateTime<Tz>) -> DateTime<Tz> {
+        let tz = after.timezone();
+        let at = |date: chrono::NaiveDate, time: NaiveTime| {
+            let naive = date.and_time(time);
+            // NOTE: A time skipped by a DST transition is moved forward by an hour
+            tz.from_local_datetime(&naive)
+                .earliest()
+                .or_else(|| {
+                    tz.from_local_datetime(&(naive + Duration::hours(1)))
+                        .earliest()
+                })
+                .expect("bug: impossible")
+        };
+
+        match self {
+            DigestSchedule::Hourly => {
+                let hour = after.date_naive().and_hms_opt(after.hour(), 0, 0).unwrap();
+                at(hour.date(), hour.time()) + Duration::hours(1)
+            }
+            DigestSchedule::Daily(time) => {
+                let today = at(after.date_naive(), *time);
+                if today > *after {
+                    today
+                } else {
+                    at(after.date_naive() + Duration::days(1), *time)
+                }
+            }
+            DigestSchedule::Weekly(weekday, time) => {
+                let days = (7 + weekday.num_days_from_monday() as i64
+                    - after.weekday().num_days_from_monday() as i64)
+                    % 7;
+                let date = after.date_naive() + Duration::days(days);
+                let candidate = at(date, *time);
+                if candidate > *after {
+                    candidate
+                } else {
+                    at(date + Duration::days(7), *time)
+                }
+            }
+        }
+    }
+
+    pub fn is_due<Tz: TimeZone>(&self, last_sent: &DateTime<Tz>, now: &DateTime<Tz>) -> bool {
+        self.next_after(last_sent) <= *now
+    }
+}
+
+/// Parses a schedule like `hourly`, `daily@08:00` or `mon@08:00`
+impl FromStr for DigestSchedule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        if s == "hourly" {
+            return Ok(DigestSchedule::Hourly);
+        }
+
+        let invalid = || Error::InvalidDigest(s.clone());
+        let (day, time) = s.split_once('@').ok_or_else(invalid)?;
+        let time = NaiveTime::parse_from_str(time, "%H:%M").map_err(|_| invalid())?;
+        match day {
+            "daily" => Ok(DigestSchedule::Daily(time)),
+            _ => Ok(DigestSchedule::Weekly(
+                day.parse::<Weekday>().map_err(|_| invalid())?,
+                time,
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DigestSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DigestSchedule::Hourly => write!(f, "hourly"),
+            DigestSchedule::Daily(time) => write!(f, "daily@{}", time.format("%H:%M")),
+            DigestSchedule::Weekly(day, time) => write!(
+                f,
+                "{}@{}",
+                day.to_string().to_lowercase(),
+                time.format("%H:%M")
+            ),
+        }
+    }
+}
+
+/// Sets or clears (`None`) the digest schedule of an existing Vahti
+pub async fn set_vahti_digest(
+    db: Database,
+    url: &str,
+    userid: u64,
+    delivery_method: i32,
+    schedule: Option<DigestSchedule>,
+) -> Result<String, Error> {
+    let url = &canonicalize_url(url);
+    if db
+        .fetch_vahti(url, userid as i64, delivery_method)
+        .await
+        .is_err()
+    {
+        return Ok(
+            "A Vahti is not defined with that url. Make sure the url is correct".to_string(),
+        );
+    }
+
+    db.update_vahti_digest(
+        url,
+        userid as i64,
+        delivery_method,
+        schedule.map(|s| s.to_string()),
+    )
+    .await?;
+
+    match schedule {
+        Some(s) => Ok(format!(
+            "The items of the Vahti are now delivered as a digest ({})",
+            s
+        )),
+        None => Ok(String::from(
+            "The items of the Vahti are now delivered immediately",
+        )),
+    }
+}
+
+impl Torimies {
+    pub async fn send_digests(&self) -> Result<(), Error> {
+        let now = Local::now();
+
+        for v in self.database.fetch_digest_vahtis().await? {
+            let Some(Ok(schedule)) = v.digest.as_deref().map(DigestSchedule::from_str) else {
+                continue;
+            };
+
+            // NOTE: The schedule is in the timezone of the user
+            let settings = UserSettings::fetch(&self.database, v.user_id, v.delivery_method).await;
+            let due = match settings.timezone {
+                Some(tz) => schedule.is_due(
+                    &tz.timestamp_opt(v.last_digest, 0).unwrap(),
+                    &now.with_timezone(&tz),
+                ),
+                None => schedule.is_due(&Local.timestamp_opt(v.last_digest, 0).unwrap(), &now),
+            };
+            if !due {
+                continue;
+            }
+
+            let buffered = self.database.fetch_digest_items(v.id).await?;
+            let Some(up_to) = buffered.last().map(|i| i.id) else {
+                self.database.digest_sent(v.id, 0, now.timestamp()).await?;
+                continue;
+            };
+
+            let items = buffered
+                .iter()
+                .filter_map(|i| serde_json::from_str::<VahtiItem>(&i.item).ok())
+                .collect::<Vec<_>>();
+
+            // NOTE: The digest is queued for each of the linked endpoints the Vahti delivers to,
+            // the outbox retries the ones that fail
+            let destinations = match self.database.fetch_vahti_destinations(v.id).await? {
+                ds if ds.is_empty() => vec![(v.user_id, v.delivery_method)],
+                ds => ds,
+            };
+            for (deliver_to, delivery_method) in destinations {
+                let items = items
+                    .iter()
+                    .cloned()
+                    .map(|mut i| {
+                        i.deliver_to = Some(deliver_to as u64);
+                        i.delivery_method = Some(delivery_method);
+                        i
+                    })
+                    .collect::<Vec<_>>();
+
+                info!(
+                    "Queueing a digest of {} items to {}",
+                    items.len(),
+                    deliver_to
+                );
+                perform_digest_delivery(&self.database, items).await?;
+            }
+
+            self.database
+                .digest_sent(v.id, up_to, now.timestamp())
+                .await?;
+        }
+
+        Ok(())
+    }
+}
@@ -16,8 +16,99 @@ pub enum Error {
     Serde(#[from] serde_json::Error),
     #[error("Reqwest error: {0}")]
     Reqwest(#[from] reqwest::Error),
+    #[error("The site did not accept the url: {0}")]
+    InvalidUrl(String),
+    #[error(
+        "The {0} of the old tori.fi search can't be converted to the new site, \
+        add the Vahti again with a search url from the new tori.fi"
+    )]
+    LegacySearch(String),
+    #[error("The site could not be reached: {0}")]
+    SiteUnreachable(String),
     #[error("The specified Vahti already exists")]
     VahtiExists,
     #[error("Invalid Item passed")]
     InvalidItem,
+    #[error("Invalid filter: {0}")]
+    InvalidFilter(String),
+    #[error("Invalid digest schedule: {0}, use e.g. hourly, daily@08:00 or mon@08:00")]
+    InvalidDigest(String),
+    #[error("Invalid duration: {0}, use e.g. 30m, 12h, 3d or 2w")]
+    InvalidDuration(String),
+    #[error("Invalid setting: {0}")]
+    InvalidSetting(String),
+    #[error("Invalid destination: {0}")]
+    InvalidDestination(String),
+    #[error("Invalid or expired pairing code")]
+    InvalidPairingCode,
+    #[error("Email error {0}")]
+    Email(String),
+    #[error("Matrix error {0}")]
+    Matrix(String),
+    #[error("Telegram error {0}")]
+    Telegram(#[from] teloxide::RequestError),
+}
+
+/// Client errors other than timeouts and rate limits won't go away by retrying
+fn is_permanent_status(status: u16) -> bool {
+    (400..500).contains(&status) && status != 408 && status != 429
+}
+
+impl Error {
+    /// Whether the error means that the recipient can't be reached anymore,
+    /// e.g. the user has blocked the bot or the chat or channel has been deleted
+    pub fn is_unreachable(&self) -> bool {
+        use teloxide::{ApiError, RequestError};
+
+        match self {
+            // NOTE: Discord answers 403 when the user doesn't accept DMs from the bot
+            // and 404 when the user or the channel doesn't exist
+            Error::Discord(serenity::Error::Http(e)) => e
+                .status_code()
+                .is_some_and(|s| matches!(s.as_u16(), 403 | 404)),
+            Error::Telegram(RequestError::Api(e)) => matches!(
+                e,
+                ApiError::BotBlocked
+                    | ApiError::ChatNotFound
+                    | ApiError::UserNotFound
+                    | ApiError::UserDeactivated
+                    | ApiError::GroupDeactivated
+                    | ApiError::BotKicked
+                    | ApiError::BotKickedFromSupergroup
+                    | ApiError::CantInitiateConversation
+                    | ApiError::CantTalkWithBots
+                    | ApiError::NotEnoughRightsToPostMessages
+            ),
+            _ => false,
+        }
+    }
+
+    /// The new id of a recipient that has moved, i.e. a Telegram group upgraded to a supergroup
+    pub fn migrated_to(&self) -> Option<i64> {
+        match self {
+            Error::Telegram(teloxide::RequestError::MigrateToChatId(id)) => Some(*id),
+            _ => None,
+        }
+    }
+
+    /// Whether retrying a failed delivery is pointless, e.g. the recipient is unreachable
+    /// or the webhook or other destination no longer exists
+    pub fn is_permanent(&self) -> bool {
+        if self.is_unreachable() {
+            return true;
+        }
+
+        match self {
+            Error::Discord(serenity::Error::Http(e)) => e
+                .status_code()
+                .is_some_and(|s| is_permanent_status(s.as_u16())),
+            Error::Reqwest(e) => e.status().is_some_and(|s| is_permanent_status(s.as_u16())),
+            // NOTE: Telegram rejected the message itself, e.g. its formatting or image,
+            // the rate limits and connection errors are other variants of RequestError
+            Error::Telegram(teloxide::RequestError::Api(_)) => true,
+            Error::Database(diesel::result::Error::NotFound) => true,
+            Error::InvalidDestination(_) => true,
+            _ => false,
+        }
+    }
 }
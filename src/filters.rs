@@ -0,0 +1,198 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::vahti::VahtiItem;
+
+/// Filters that are applied to the items of a Vahti after parsing them.
+/// These are stored as json alongside the Vahti in the database.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct VahtiFilters {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_price: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_price: Option<i64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title_regex: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ad_types: Vec<String>,
+    #[serde(skip)]
+    pub compiled_regex: CompiledRegex,
+}
+
+/// The title regex compiled on its first use, so that it isn't compiled again for
+/// every item. It is left out of the comparisons of the filters
+#[derive(Debug, Clone, Default)]
+pub struct CompiledRegex(OnceLock<Option<Regex>>);
+
+impl PartialEq for CompiledRegex {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl VahtiFilters {
+    fn regex(&self) -> Option<&Regex> {
+        self.compiled_regex
+            .0
+            .get_or_init(|| {
+                self.title_regex
+                    .as_ref()
+                    .and_then(|r| RegexBuilder::new(r).case_insensitive(true).build().ok())
+            })
+            .as_ref()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn from_db(filters: Option<&str>) -> Result<Self, Error> {
+        match filters {
+            Some(f) => Ok(serde_json::from_str(f)?),
+            None => Ok(Self::default()),
+        }
+    }
+
+    pub fn to_db(&self) -> Option<String> {
+        if self.is_empty() {
+            None
+        } else {
+            serde_json::to_string(self).ok()
+        }
+    }
+
+    /// Checks that the filters are usable, i.e. the regex compiles
+    /// and the price range is not empty
+    pub fn validate(&self) -> Result<(), Error> {
+        if let Some(r) = &self.title_regex {
+            RegexBuilder::new(r)
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| Error::InvalidFilter(e.to_string()))?;
+        }
+
+        if let (Some(min), Some(max)) = (self.min_price, self.max_price) {
+            if min > max {
+                return Err(Error::InvalidFilter(format!(
+                    "min price {} is larger than max price {}",
+                    min, max
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    // NOTE: None of the supported sites include the description of the items
+    // in the search results, so only the title is matched against
+    pub fn matches(&self, item: &VahtiItem) -> bool {
+        if self.min_price.is_some_and(|min| item.price < min)
+            || self.max_price.is_some_and(|max| item.price > max)
+        {
+            return false;
+        }
+
+        let title = item.title.to_lowercase();
+        if !self
+            .include
+            .iter()
+            .all(|k| title.contains(&k.to_lowercase()))
+        {
+            return false;
+        }
+
+        if self
+            .exclude
+            .iter()
+            .any(|k| title.contains(&k.to_lowercase()))
+        {
+            return false;
+        }
+
+        if !self.ad_types.is_empty()
+            && !self
+                .ad_types
+                .iter()
+                .any(|t| t.to_lowercase() == item.ad_type.to_lowercase())
+        {
+            return false;
+        }
+
+        // NOTE: The regex is validated before saving, so it should always compile
+        self.regex().map_or(true, |r| r.is_match(&item.title))
+    }
+}
+
+/// Parses filters from a string of whitespace-separated terms, e.g.
+/// `min=100 max=500 +thinkpad -rikki regex=x2[0-9]0 type=Myydään`
+impl FromStr for VahtiFilters {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut filters = Self::default();
+        for term in s.split_whitespace() {
+            if let Some(k) = term.strip_prefix('+') {
+                filters.include.push(k.to_string());
+            } else if let Some(k) = term.strip_prefix('-') {
+                filters.exclude.push(k.to_string());
+            } else if let Some((key, value)) = term.split_once('=') {
+                let price = || {
+                    value
+                        .parse::<i64>()
+                        .map_err(|_| Error::InvalidFilter(format!("invalid price {}", value)))
+                };
+                match key {
+                    "min" => filters.min_price = Some(price()?),
+                    "max" => filters.max_price = Some(price()?),
+                    "regex" => filters.title_regex = Some(value.to_string()),
+                    "type" => filters.ad_types.push(value.to_string()),
+                    _ => return Err(Error::InvalidFilter(format!("unknown filter {}", key))),
+                }
+            } else {
+                return Err(Error::InvalidFilter(format!("unknown filter {}", term)));
+            }
+        }
+
+        filters.validate()?;
+        Ok(filters)
+    }
+}
+
+impl fmt::Display for VahtiFilters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "No filters");
+        }
+
+        let mut lines = vec![];
+        match (self.min_price, self.max_price) {
+            (Some(min), Some(max)) => lines.push(format!("Price: {}-{} €", min, max)),
+            (Some(min), None) => lines.push(format!("Price: at least {} €", min)),
+            (None, Some(max)) => lines.push(format!("Price: at most {} €", max)),
+            (None, None) => {}
+        }
+        if !self.include.is_empty() {
+            lines.push(format!("Must contain: {}", self.include.join(", ")));
+        }
+        if !self.exclude.is_empty() {
+            lines.push(format!("Must not contain: {}", self.exclude.join(", ")));
+        }
+        if let Some(r) = &self.title_regex {
+            lines.push(format!("Title regex: {}", r));
+        }
+        if !self.ad_types.is_empty() {
+            lines.push(format!("Ad types: {}", self.ad_types.join(", ")));
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
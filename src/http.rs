@@ -0,0 +1,85 @@
+use std::future::Future;
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::database::Database;
+use crate::error::Error;
+use crate::{State, Torimies};
+
+/// How long a client has to send its request before the connection is dropped
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A response to a request of one of the local HTTP endpoints
+pub struct Response {
+    pub status: &'static str,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+/// Serves the requests to `addr` with `handler` until the shutdown. The handler gets
+/// the beginning of the request, which is enough for the request line
+pub async fn serve<F, Fut>(man: &Torimies, addr: &str, handler: F)
+where
+    F: Fn(Database, String) -> Fut + Copy + Send + 'static,
+    Fut: Future<Output = Result<Response, Error>> + Send,
+{
+    let listener = TcpListener::bind(addr)
+        .await
+        .unwrap_or_else(|e| panic!("Failed to bind {}: {}", addr, e));
+
+    loop {
+        // NOTE: The timeout lets the loop notice the shutdown
+        let accepted = tokio::time::timeout(Duration::from_secs(1), listener.accept()).await;
+
+        if man
+            .state
+            .read()
+            .map(|s| *s == State::Shutdown)
+            .unwrap_or(true)
+        {
+            break;
+        }
+
+        match accepted {
+            Ok(Ok((stream, _))) => {
+                let db = man.database.clone();
+                let addr = addr.to_string();
+                tokio::task::spawn(async move {
+                    if let Err(e) = respond(db, stream, handler).await {
+                        error!("Error while serving a request to {}: {}", addr, e);
+                    }
+                });
+            }
+            Ok(Err(e)) => error!("Failed to accept a connection: {}", e),
+            Err(_) => {}
+        }
+    }
+}
+
+async fn respond<F, Fut>(db: Database, mut stream: TcpStream, handler: F) -> Result<(), Error>
+where
+    F: Fn(Database, String) -> Fut,
+    Fut: Future<Output = Result<Response, Error>>,
+{
+    let mut buf = [0; 2048];
+    // NOTE: A client that never sends its request would otherwise hold the connection forever
+    let Ok(read) = tokio::time::timeout(READ_TIMEOUT, stream.read(&mut buf)).await else {
+        return Ok(());
+    };
+    let n = read.unwrap_or(0);
+    let request = String::from_utf8_lossy(&buf[..n]).to_string();
+
+    let response = handler(db, request).await?;
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.content_type,
+        response.body.len(),
+        response.body
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+    Ok(())
+}
@@ -1,8 +1,52 @@
+use itertools::Itertools;
+use reqwest::StatusCode;
 use serde_json::Value;
+use url::form_urlencoded;
+
+use super::models::{Auction, FullHuutonetItem};
+use super::parse::api_parse_after;
+use crate::bookmark::{Listing, ListingStatus};
+use crate::error::Error;
+use crate::vahti::{fetch_search, sorted_query, SearchSummary, VahtiItem};
+
+/// Parses the seller id from a seller url like `https://www.huuto.net/kayttaja/123456`
+pub fn seller_id_from_url(vahti: &str) -> Option<i32> {
+    let (_, id) = vahti.split_once("huuto.net/kayttaja/")?;
+    id.trim_end_matches('/').parse().ok()
+}
+
+/// Canonicalizes a vahti url so that equivalent searches share the same url:
+/// the host is `www.huuto.net`, the arguments of `/haku/key/value` style urls are
+/// moved to the query and the query arguments are sorted with the empty ones dropped
+pub fn canonical_url(vahti: &str) -> String {
+    let vahti = vahti.trim();
+    if let Some(seller) = seller_id_from_url(vahti) {
+        return super::seller::seller_url(seller);
+    }
+
+    // NOTE: The arguments of `/haku/key/value` urls are encoded just like a query
+    let query = match vahti.split_once('?') {
+        Some((_, query)) => query.to_string(),
+        None => vahti
+            .trim_end_matches('/')
+            .split('/')
+            .skip(4)
+            .tuples()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .join("&"),
+    };
+    let args = form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .collect::<Vec<_>>();
+
+    format!("https://www.huuto.net/haku?{}", sorted_query(args))
+}
 
 pub fn vahti_to_api(vahti: &str) -> String {
     let mut url = String::from("https://api.huuto.net/1.1/items?");
-    if vahti.contains('?') {
+    if let Some(seller) = seller_id_from_url(vahti) {
+        url += &format!("seller_id={}", seller);
+    } else if vahti.contains('?') {
         // Easy parse
         url += &vahti[vahti.find('?').unwrap() + 1..];
     } else {
@@ -10,6 +54,7 @@ pub fn vahti_to_api(vahti: &str) -> String {
         let mut args: Vec<&str> = vahti.split('/').collect();
         let args: Vec<&str> = args.drain(4..).collect();
 
+        #[allow(clippy::format_collect)]
         let url_end: String = args
             .chunks_exact(2)
             .map(|arg| format!("&{}={}", arg[0], arg[1]))
@@ -23,13 +68,83 @@ pub fn vahti_to_api(vahti: &str) -> String {
     url
 }
 
-pub async fn is_valid_url(url: &str) -> bool {
-    let url = vahti_to_api(url);
-    let response = reqwest::get(&url)
-        .await
-        .unwrap()
-        .json::<Value>()
-        .await
-        .unwrap();
-    response["totalCount"].as_i64().unwrap() > 0
+/// Summarizes the search of a vahti url
+pub fn summarize_url(vahti: &str) -> SearchSummary {
+    let mut summary = SearchSummary {
+        site: "Huuto.net",
+        seller_id: seller_id_from_url(vahti),
+        ..Default::default()
+    };
+    if summary.seller_id.is_some() {
+        return summary;
+    }
+
+    let api = vahti_to_api(vahti);
+    let query = api.split_once('?').map(|(_, q)| q).unwrap_or_default();
+    for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+        if value.is_empty() {
+            continue;
+        }
+        match key.as_ref() {
+            "words" => summary.query = Some(value.into_owned()),
+            "price_min" => summary.price_from = Some(value.into_owned()),
+            "price_max" => summary.price_to = Some(value.into_owned()),
+            "area" => summary.regions.push(value.into_owned()),
+            _ => {}
+        }
+    }
+    summary
+}
+
+/// Parses the number of listings matching the search from an api response
+pub fn match_count(body: &str) -> Option<i64> {
+    let response = serde_json::from_str::<Value>(body).ok()?;
+    response["totalCount"].as_i64()
+}
+
+/// Fetches the search of the url from the api, returning the number of matches
+/// along with the newest matching items
+pub async fn validate_url(url: &str) -> Result<(i64, Vec<VahtiItem>), Error> {
+    let body = fetch_search(url, &vahti_to_api(url)).await?;
+    let count = match_count(&body).ok_or_else(|| Error::InvalidUrl(url.to_string()))?;
+    Ok((count, api_parse_after(&body, 0)?))
+}
+
+/// Parses the item id from an item url like `https://www.huuto.net/kohteet/otsikko/123456`
+/// or from a plain item id
+pub fn item_id_from_url(url: &str) -> Option<i64> {
+    url.trim()
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .and_then(|id| id.parse().ok())
+}
+
+pub async fn fetch_auction(id: i64) -> Result<Auction, Error> {
+    let url = format!("https://api.huuto.net/1.1/items/{}", id);
+    let response = reqwest::get(&url).await?.error_for_status()?.text().await?;
+    let item: FullHuutonetItem = serde_json::from_str(&response)?;
+    Ok(Auction::from(item))
+}
+
+pub async fn fetch_listing(id: i64) -> Result<Listing, Error> {
+    let url = format!("https://api.huuto.net/1.1/items/{}", id);
+    let response = reqwest::get(&url).await?;
+    if matches!(response.status(), StatusCode::NOT_FOUND | StatusCode::GONE) {
+        return Ok(Listing::removed());
+    }
+
+    let response = response.error_for_status()?.text().await?;
+    let auction = Auction::from(serde_json::from_str::<FullHuutonetItem>(&response)?);
+    let status = if auction.closing_time <= chrono::Local::now().timestamp() {
+        ListingStatus::Closed
+    } else {
+        ListingStatus::Active
+    };
+
+    Ok(Listing {
+        title: auction.title,
+        url: auction.url,
+        status,
+    })
 }
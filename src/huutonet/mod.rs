@@ -1,5 +1,5 @@
 pub mod api;
-mod models;
+pub mod models;
 pub mod parse;
 pub mod seller;
 pub mod vahti;
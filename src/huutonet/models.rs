@@ -61,6 +61,7 @@ impl From<FullHuutonetItem> for VahtiItem {
         }
         VahtiItem {
             delivery_method: None,
+            vahti_id: None,
             vahti_url: None,
             deliver_to: None,
             site_id: 2,
@@ -74,6 +75,40 @@ impl From<FullHuutonetItem> for VahtiItem {
             location: h.location,
             ad_type: h.sale_method,
             ad_id: h.id,
+            previous_price: None,
+        }
+    }
+}
+
+/// The information needed for reminding about an ending auction
+#[derive(Debug, Clone, PartialEq)]
+pub struct Auction {
+    pub id: i64,
+    pub title: String,
+    pub url: String,
+    pub closing_time: i64,
+    pub current_price: i64,
+    pub buy_now_price: Option<i64>,
+    pub bidder_count: i64,
+    pub has_reserve_price: bool,
+    pub has_reserve_price_exceeded: bool,
+}
+
+impl From<FullHuutonetItem> for Auction {
+    fn from(h: FullHuutonetItem) -> Auction {
+        let closing_time = chrono::DateTime::parse_from_str(&h.closing_time, "%FT%T%:z")
+            .unwrap()
+            .timestamp();
+        Auction {
+            id: h.id,
+            title: h.title,
+            url: h.links.alternative,
+            closing_time,
+            current_price: h.current_price.round() as i64,
+            buy_now_price: h.buy_now_price.map(|p| p.round() as i64),
+            bidder_count: h.bidder_count,
+            has_reserve_price: h.has_reserve_price,
+            has_reserve_price_exceeded: h.has_reserve_price_exceeded,
         }
     }
 }
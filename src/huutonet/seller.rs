@@ -8,3 +8,8 @@ pub async fn get_seller_name_from_id(sellerid: i32) -> Result<String, Error> {
     let response_json: Value = serde_json::from_str(&response)?;
     Ok(response_json["username"].to_string())
 }
+
+/// The url used for following the listings of a seller
+pub fn seller_url(sellerid: i32) -> String {
+    format!("https://www.huuto.net/kayttaja/{}", sellerid)
+}
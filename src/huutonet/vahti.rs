@@ -3,15 +3,17 @@ use std::sync::LazyLock;
 use async_trait::async_trait;
 use regex::Regex;
 
-pub static HUUTONET_REGEX: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"^https://(www\.)?huuto\.net/haku?.*$").unwrap());
+pub static HUUTONET_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^https://(www\.)?huuto\.net/(haku?.*|kayttaja/[0-9]+/?)$").unwrap()
+});
 
-use super::api::{is_valid_url, vahti_to_api};
+use super::api::{canonical_url, vahti_to_api, validate_url};
 use super::parse::api_parse_after;
 use crate::error::Error;
+use crate::filters::VahtiFilters;
 use crate::itemhistory::ItemHistoryStorage;
 use crate::models::DbVahti;
-use crate::vahti::{Vahti, VahtiItem};
+use crate::vahti::{is_price_drop, Vahti, VahtiItem};
 use crate::Database;
 
 #[derive(Debug, Clone)]
@@ -22,48 +24,78 @@ pub struct HuutonetVahti {
     pub last_updated: i64,
     pub site_id: i32,
     pub delivery_method: i32,
+    pub filters: VahtiFilters,
+    pub digest: Option<String>,
+    pub last_digest: i64,
 }
 
 #[async_trait]
 impl Vahti for HuutonetVahti {
+    async fn fetch_items(&self, after: i64) -> Result<Vec<VahtiItem>, Error> {
+        debug!("Fetching {}", self.url);
+        let res = reqwest::get(vahti_to_api(&self.url))
+            .await?
+            .text()
+            .await?
+            .to_string();
+
+        api_parse_after(&res, after)
+    }
+
     async fn update(
         &mut self,
         db: &Database,
         ihs: ItemHistoryStorage,
+        items: &[VahtiItem],
     ) -> Result<Vec<VahtiItem>, Error> {
         debug!("Updating {}", self.url);
         let ihref = ihs
             .get(&(self.user_id, self.delivery_method))
             .expect("bug: impossible");
 
-        let res = reqwest::get(vahti_to_api(&self.url))
-            .await?
-            .text()
-            .await?
-            .to_string();
+        let bind = |i: &VahtiItem| {
+            // FIXME: Somewhat sketchy
+            let mut newi = i.clone();
+            newi.vahti_url = Some(self.url.clone());
+            newi.deliver_to = Some(self.user_id);
+            newi.delivery_method = Some(self.delivery_method);
+            newi.vahti_id = Some(self.id);
+            newi
+        };
 
         let mut ih = ihref.lock().unwrap().clone();
-        let ret = api_parse_after(&res, self.last_updated)?
-            .into_iter()
+        let mut price_changes = vec![];
+        let ret = items
+            .iter()
+            .filter(|i| self.filters.matches(i))
             .filter_map(|i| {
-                if !ih.contains(i.ad_id, i.site_id) {
-                    ih.add_item(i.ad_id, i.site_id, chrono::Local::now().timestamp());
-                    let mut newi = i.clone();
-                    newi.vahti_url = Some(self.url.clone());
-                    newi.deliver_to = Some(self.user_id);
-                    newi.delivery_method = Some(self.delivery_method);
+                if let Some(old_price) = ih.price(i.ad_id, i.site_id) {
+                    if old_price == i.price {
+                        return None;
+                    }
+
+                    ih.set_price(i.ad_id, i.site_id, i.price);
+                    price_changes.push((i.ad_id, i.site_id, i.price));
 
+                    if !is_price_drop(old_price, i.price) {
+                        return None;
+                    }
+
+                    let mut newi = bind(i);
+                    newi.previous_price = Some(old_price);
                     Some(newi)
+                } else if i.published > self.last_updated {
+                    ih.add_item(
+                        i.ad_id,
+                        i.site_id,
+                        chrono::Local::now().timestamp(),
+                        i.price,
+                    );
+                    Some(bind(i))
                 } else {
                     None
                 }
             })
-            .map(|mut i| {
-                i.vahti_url = Some(self.url.clone());
-                i.deliver_to = Some(self.user_id);
-                i.delivery_method = Some(self.delivery_method);
-                i
-            })
             .collect::<Vec<_>>();
 
         {
@@ -72,11 +104,32 @@ impl Vahti for HuutonetVahti {
             *locked = ih;
         }
 
-        if ret.is_empty() {
-            return Ok(vec![]);
+        if !price_changes.is_empty() {
+            db.update_seen_item_prices(self.user_id as i64, self.delivery_method, &price_changes)
+                .await?;
         }
 
-        db.vahti_updated(self.to_db(), ret.iter().map(|i| i.published).max())
+        let new_items = ret
+            .iter()
+            .filter(|i| i.previous_price.is_none())
+            .collect::<Vec<_>>();
+
+        if new_items.is_empty() {
+            return Ok(ret);
+        }
+
+        db.add_seen_items(
+            self.user_id as i64,
+            self.delivery_method,
+            self.id,
+            &new_items
+                .iter()
+                .map(|i| (i.ad_id, i.site_id, i.price))
+                .collect::<Vec<_>>(),
+        )
+        .await?;
+
+        db.vahti_updated(self.to_db(), new_items.iter().map(|i| i.published).max())
             .await?;
 
         Ok(ret)
@@ -86,8 +139,12 @@ impl Vahti for HuutonetVahti {
         HUUTONET_REGEX.is_match(url)
     }
 
-    async fn validate_url(&self) -> Result<bool, Error> {
-        Ok(is_valid_url(&self.url).await)
+    fn canonicalize_url(url: &str) -> String {
+        canonical_url(url)
+    }
+
+    async fn validate_url(&self) -> Result<(i64, Vec<VahtiItem>), Error> {
+        validate_url(&self.url).await
     }
 
     fn from_db(v: DbVahti) -> Result<Self, Error> {
@@ -100,6 +157,9 @@ impl Vahti for HuutonetVahti {
             last_updated: v.last_updated,
             site_id: super::ID,
             delivery_method: v.delivery_method,
+            filters: VahtiFilters::from_db(v.filters.as_deref())?,
+            digest: v.digest,
+            last_digest: v.last_digest,
         })
     }
 
@@ -111,6 +171,15 @@ impl Vahti for HuutonetVahti {
             last_updated: self.last_updated,
             site_id: self.site_id,
             delivery_method: self.delivery_method,
+            filters: self.filters.to_db(),
+            digest: self.digest.clone(),
+            last_digest: self.last_digest,
+            // NOTE: Paused and snoozed Vahtis are never updated
+            paused: false,
+            last_error: None,
+            snoozed_until: None,
+            creator_id: None,
+            disabled: false,
         }
     }
 }
@@ -0,0 +1,202 @@
+use itertools::Itertools;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+
+use crate::database::Database;
+use crate::error::Error;
+use crate::models::{DbVahti, NewPairingCode};
+use crate::vahti::canonicalize_url;
+
+/// How long a pairing code can be used, in seconds
+const PAIRING_CODE_LIFETIME: i64 = 10 * 60;
+const PAIRING_CODE_LENGTH: usize = 8;
+
+/// The name of an endpoint that can be linked, i.e. a delivery method that reaches the user directly
+pub fn endpoint_name(delivery_method: i32) -> Option<&'static str> {
+    match delivery_method {
+        #[cfg(feature = "discord-delivery")]
+        crate::delivery::discord::ID => Some(crate::delivery::discord::NAME),
+        #[cfg(feature = "telegram-delivery")]
+        crate::delivery::telegram::ID => Some(crate::delivery::telegram::NAME),
+        #[cfg(feature = "matrix-delivery")]
+        crate::delivery::matrix::ID => Some(crate::delivery::matrix::NAME),
+        _ => None,
+    }
+}
+
+fn describe(endpoints: &[(i64, i32)]) -> String {
+    endpoints
+        .iter()
+        .filter_map(|(_, d)| endpoint_name(*d))
+        .unique()
+        .join(", ")
+}
+
+fn pairing_code() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(PAIRING_CODE_LENGTH)
+        .map(char::from)
+        .collect::<String>()
+        .to_uppercase()
+}
+
+/// Creates a one-time code that links another account with this one when it is used there
+pub async fn create_pairing_code(
+    db: &Database,
+    userid: u64,
+    delivery_method: i32,
+) -> Result<String, Error> {
+    if endpoint_name(delivery_method).is_none() {
+        return Err(Error::InvalidDestination(delivery_method.to_string()));
+    }
+
+    let code = pairing_code();
+    db.add_pairing_code(NewPairingCode {
+        code: code.clone(),
+        user_id: userid as i64,
+        delivery_method,
+        expires_at: chrono::Local::now().timestamp() + PAIRING_CODE_LIFETIME,
+    })
+    .await?;
+
+    Ok(format!(
+        "Your pairing code is {}. Give it to the link command (/linkita) on the other platform within {} minutes",
+        code,
+        PAIRING_CODE_LIFETIME / 60
+    ))
+}
+
+/// Links this account with the one that created the pairing code
+pub async fn pair(
+    db: &Database,
+    code: &str,
+    userid: u64,
+    delivery_method: i32,
+) -> Result<String, Error> {
+    if endpoint_name(delivery_method).is_none() {
+        return Err(Error::InvalidDestination(delivery_method.to_string()));
+    }
+
+    let now = chrono::Local::now().timestamp();
+    let pairing = match db
+        .take_pairing_code(&code.trim().to_uppercase(), now)
+        .await?
+    {
+        Some(p) if (p.user_id, p.delivery_method) != (userid as i64, delivery_method) => p,
+        _ => return Err(Error::InvalidPairingCode),
+    };
+
+    db.link_endpoints(
+        (pairing.user_id, pairing.delivery_method),
+        (userid as i64, delivery_method),
+    )
+    .await?;
+
+    let linked = db
+        .fetch_linked_endpoints(userid as i64, delivery_method)
+        .await?;
+    Ok(format!("Accounts linked: {}", describe(&linked)))
+}
+
+pub async fn unlink(db: &Database, userid: u64, delivery_method: i32) -> Result<String, Error> {
+    match db.unlink_endpoint(userid as i64, delivery_method).await? {
+        0 => Ok(String::from("The account is not linked")),
+        _ => Ok(String::from("The account is no longer linked")),
+    }
+}
+
+pub async fn list_linked(
+    db: &Database,
+    userid: u64,
+    delivery_method: i32,
+) -> Result<String, Error> {
+    let linked = db
+        .fetch_linked_endpoints(userid as i64, delivery_method)
+        .await?;
+    if linked.len() < 2 {
+        return Ok(String::from("No linked accounts"));
+    }
+    Ok(format!("Linked accounts: {}", describe(&linked)))
+}
+
+/// Parses the endpoint names like `discord,telegram` into the matching linked endpoints
+pub fn parse_endpoints(linked: &[(i64, i32)], spec: &str) -> Result<Vec<(i64, i32)>, Error> {
+    let mut endpoints = vec![];
+    for name in spec.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        let matching = linked
+            .iter()
+            .filter(|(_, d)| endpoint_name(*d) == Some(name))
+            .collect::<Vec<_>>();
+        if matching.is_empty() {
+            return Err(Error::InvalidDestination(name.to_string()));
+        }
+        endpoints.extend(matching);
+    }
+
+    if endpoints.is_empty() {
+        return Err(Error::InvalidDestination(spec.to_string()));
+    }
+    Ok(endpoints.into_iter().unique().collect())
+}
+
+/// Chooses the linked endpoints the items of an existing Vahti are delivered to
+pub async fn set_vahti_endpoints(
+    db: Database,
+    url: &str,
+    userid: u64,
+    delivery_method: i32,
+    spec: &str,
+) -> Result<String, Error> {
+    let url = &canonicalize_url(url);
+    let Ok(vahti) = db.fetch_vahti(url, userid as i64, delivery_method).await else {
+        return Ok(
+            "A Vahti is not defined with that url. Make sure the url is correct".to_string(),
+        );
+    };
+
+    let linked = db
+        .fetch_linked_endpoints(userid as i64, delivery_method)
+        .await?;
+    let endpoints = parse_endpoints(&linked, spec)?;
+
+    // NOTE: Delivering to the owner only is the default and is stored as no destinations
+    if endpoints == [(userid as i64, delivery_method)] {
+        db.set_vahti_destinations(vahti.id, &[]).await?;
+    } else {
+        db.set_vahti_destinations(vahti.id, &endpoints).await?;
+    }
+
+    Ok(format!(
+        "The items of the Vahti are delivered to {}",
+        describe(&endpoints)
+    ))
+}
+
+/// Whether the Vahti belongs to this account or an account linked with it
+pub async fn owns_vahti(db: &Database, vahti: &DbVahti, userid: u64, delivery_method: i32) -> bool {
+    db.fetch_linked_endpoints(userid as i64, delivery_method)
+        .await
+        .is_ok_and(|l| l.contains(&(vahti.user_id, vahti.delivery_method)))
+}
+
+/// Finds the account owning the Vahti with the url among the accounts linked with this one,
+/// defaulting to this account
+pub async fn linked_vahti_owner(
+    db: &Database,
+    url: &str,
+    userid: u64,
+    delivery_method: i32,
+) -> (u64, i32) {
+    let url = &canonicalize_url(url);
+    let linked = db
+        .fetch_linked_endpoints(userid as i64, delivery_method)
+        .await
+        .unwrap_or_default();
+    for (u, d) in linked {
+        if db.fetch_vahti(url, u, d).await.is_ok() {
+            return (u as u64, d);
+        }
+    }
+    (userid, delivery_method)
+}
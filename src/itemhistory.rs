@@ -5,11 +5,12 @@ use dashmap::DashMap;
 
 #[derive(Debug, Clone)]
 pub struct ItemHistory {
-    // (item_id, site_id), timestamp
-    items: HashMap<(i64, i32), i64>,
+    // (item_id, site_id), (timestamp, last seen price)
+    items: HashMap<(i64, i32), (i64, i64)>,
 }
 
 // (user_id, delivery_method) => ItemHistory
+// NOTE: This only works as a cache, the persistent history lives in the database
 pub type ItemHistoryStorage = Arc<DashMap<(u64, i32), Arc<Mutex<ItemHistory>>>>;
 
 impl ItemHistory {
@@ -19,10 +20,20 @@ impl ItemHistory {
         }
     }
 
-    pub fn add_item(&mut self, id: i64, site_id: i32, timestamp: i64) {
+    /// Constructs an ItemHistory from (item_id, site_id, timestamp, price)-tuples
+    pub fn from_items(items: Vec<(i64, i32, i64, i64)>) -> ItemHistory {
+        let mut ih = Self::new();
+        for (id, site_id, timestamp, price) in items {
+            ih.add_item(id, site_id, timestamp, price);
+        }
+        ih.purge_old();
+        ih
+    }
+
+    pub fn add_item(&mut self, id: i64, site_id: i32, timestamp: i64, price: i64) {
         if !self.contains(id, site_id) {
             debug!("Adding id: {},{}, timestamp: {}", id, site_id, timestamp);
-            self.items.insert((id, site_id), timestamp);
+            self.items.insert((id, site_id), (timestamp, price));
         }
     }
 
@@ -30,13 +41,28 @@ impl ItemHistory {
         self.items.contains_key(&(id, site_id))
     }
 
+    /// The last seen price of the item
+    pub fn price(&self, id: i64, site_id: i32) -> Option<i64> {
+        self.items.get(&(id, site_id)).map(|(_, p)| *p)
+    }
+
+    pub fn set_price(&mut self, id: i64, site_id: i32, price: i64) {
+        if let Some((_, p)) = self.items.get_mut(&(id, site_id)) {
+            *p = price;
+        }
+    }
+
     pub fn purge_old(&mut self) {
+        let oldest = chrono::Local::now().timestamp() - *crate::ITEM_HISTORY_RETENTION;
         self.items
-            .retain(|(_, _), timestamp| timestamp > &mut (chrono::Local::now().timestamp() - 1000));
+            .retain(|(_, _), (timestamp, _)| *timestamp > oldest);
     }
 
+    /// Adds the items of `other` that are missing from `self`
     pub fn extend(&mut self, other: &Self) {
-        self.items.extend(other.items.iter());
+        for (k, v) in other.items.iter() {
+            self.items.entry(*k).or_insert(*v);
+        }
         self.purge_old()
     }
 }
@@ -4,20 +4,33 @@
 #[cfg(test)]
 mod tests;
 
+mod blacklist;
+mod bookmark;
 mod itemhistory;
 #[cfg(feature = "tori")]
 mod tori;
 
+mod http;
 #[cfg(feature = "huutonet")]
 mod huutonet;
+mod identity;
+mod outbox;
 
 mod error;
+mod filters;
+#[cfg(any(feature = "matrix-delivery", feature = "matrix-command"))]
+mod matrix;
 pub mod models;
 pub mod schema;
+mod settings;
 
 pub mod command;
 pub mod database;
 pub mod delivery;
+mod digest;
+#[cfg(feature = "huutonet")]
+mod reminder;
+mod unreachable;
 mod vahti;
 
 #[macro_use]
@@ -48,6 +61,90 @@ static FUTURES_MAX_BUFFER_SIZE: LazyLock<usize> = LazyLock::new(|| {
         .expect("Invalid FUTURES_MAX_BUFFER_SIZE")
 });
 
+static ITEM_HISTORY_RETENTION: LazyLock<i64> = LazyLock::new(|| {
+    std::env::var("ITEM_HISTORY_RETENTION")
+        .unwrap_or(String::from("2592000"))
+        .parse()
+        .expect("Invalid ITEM_HISTORY_RETENTION")
+});
+
+static PRICE_DROP_PERCENT: LazyLock<i64> = LazyLock::new(|| {
+    std::env::var("PRICE_DROP_PERCENT")
+        .unwrap_or(String::from("10"))
+        .parse()
+        .expect("Invalid PRICE_DROP_PERCENT")
+});
+
+static PRICE_DROP_EUROS: LazyLock<i64> = LazyLock::new(|| {
+    std::env::var("PRICE_DROP_EUROS")
+        .unwrap_or(String::from("20"))
+        .parse()
+        .expect("Invalid PRICE_DROP_EUROS")
+});
+
+static AUCTION_REMINDER_BEFORE: LazyLock<i64> = LazyLock::new(|| {
+    std::env::var("AUCTION_REMINDER_BEFORE")
+        .unwrap_or(String::from("3600"))
+        .parse()
+        .expect("Invalid AUCTION_REMINDER_BEFORE")
+});
+
+static BOOKMARK_CHECK_INTERVAL: LazyLock<u64> = LazyLock::new(|| {
+    std::env::var("BOOKMARK_CHECK_INTERVAL")
+        .unwrap_or(String::from("600"))
+        .parse()
+        .expect("Invalid BOOKMARK_CHECK_INTERVAL")
+});
+
+static OUTBOX_INTERVAL: LazyLock<u64> = LazyLock::new(|| {
+    std::env::var("OUTBOX_INTERVAL")
+        .unwrap_or(String::from("10"))
+        .parse()
+        .expect("Invalid OUTBOX_INTERVAL")
+});
+
+static OUTBOX_MAX_ATTEMPTS: LazyLock<i32> = LazyLock::new(|| {
+    std::env::var("OUTBOX_MAX_ATTEMPTS")
+        .unwrap_or(String::from("20"))
+        .parse()
+        .expect("Invalid OUTBOX_MAX_ATTEMPTS")
+});
+
+static UNREACHABLE_FAILURE_LIMIT: LazyLock<i32> = LazyLock::new(|| {
+    std::env::var("UNREACHABLE_FAILURE_LIMIT")
+        .unwrap_or(String::from("3"))
+        .parse()
+        .expect("Invalid UNREACHABLE_FAILURE_LIMIT")
+});
+
+static METRICS_LISTEN_ADDR: LazyLock<Option<String>> =
+    LazyLock::new(|| std::env::var("METRICS_LISTEN_ADDR").ok());
+
+#[cfg(feature = "webhook-delivery")]
+static WEBHOOK_TIMEOUT: LazyLock<u64> = LazyLock::new(|| {
+    std::env::var("WEBHOOK_TIMEOUT")
+        .unwrap_or(String::from("10"))
+        .parse()
+        .expect("Invalid WEBHOOK_TIMEOUT")
+});
+
+#[cfg(feature = "webhook-delivery")]
+static WEBHOOK_RETRIES: LazyLock<u32> = LazyLock::new(|| {
+    std::env::var("WEBHOOK_RETRIES")
+        .unwrap_or(String::from("3"))
+        .parse()
+        .expect("Invalid WEBHOOK_RETRIES")
+});
+
+#[cfg(feature = "email-delivery")]
+static EMAIL_PUBLIC_URL: LazyLock<String> = LazyLock::new(|| {
+    std::env::var("EMAIL_PUBLIC_URL").expect("Expected EMAIL_PUBLIC_URL in the environment")
+});
+
+#[cfg(feature = "email-delivery")]
+static EMAIL_LISTEN_ADDR: LazyLock<String> =
+    LazyLock::new(|| std::env::var("EMAIL_LISTEN_ADDR").unwrap_or(String::from("0.0.0.0:8080")));
+
 #[derive(PartialEq, Clone)]
 enum State {
     Running,
@@ -100,6 +197,120 @@ async fn update_loop(man: &mut Torimies) {
     info!("Update loop exited")
 }
 
+#[cfg(feature = "huutonet")]
+async fn reminder_loop(man: &Torimies) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+    loop {
+        interval.tick().await;
+
+        if man
+            .state
+            .read()
+            .map(|s| *s == State::Shutdown)
+            .unwrap_or(true)
+        {
+            break;
+        }
+
+        if let Err(e) = man.send_auction_reminders().await {
+            error!("Error while sending auction reminders: {}", e);
+        }
+    }
+
+    info!("Reminder loop exited")
+}
+
+async fn bookmark_loop(man: &Torimies) {
+    let mut interval =
+        tokio::time::interval(std::time::Duration::from_secs(*BOOKMARK_CHECK_INTERVAL));
+    loop {
+        interval.tick().await;
+
+        if man
+            .state
+            .read()
+            .map(|s| *s == State::Shutdown)
+            .unwrap_or(true)
+        {
+            break;
+        }
+
+        if let Err(e) = man.check_bookmarks().await {
+            error!("Error while checking bookmarks: {}", e);
+        }
+    }
+
+    info!("Bookmark loop exited")
+}
+
+/// Sends the digests and the items held during quiet hours when they are due
+async fn schedule_loop(man: &Torimies) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+    loop {
+        interval.tick().await;
+
+        if man
+            .state
+            .read()
+            .map(|s| *s == State::Shutdown)
+            .unwrap_or(true)
+        {
+            break;
+        }
+
+        if let Err(e) = man.send_digests().await {
+            error!("Error while sending digests: {}", e);
+        }
+
+        if let Err(e) = man.release_held_items().await {
+            error!("Error while releasing held items: {}", e);
+        }
+    }
+
+    info!("Schedule loop exited")
+}
+
+/// Delivers the queued items, retrying the failed deliveries
+async fn outbox_loop(man: &Torimies) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(*OUTBOX_INTERVAL));
+    loop {
+        interval.tick().await;
+
+        if man
+            .state
+            .read()
+            .map(|s| *s == State::Shutdown)
+            .unwrap_or(true)
+        {
+            break;
+        }
+
+        if let Err(e) = man.drain_outbox().await {
+            error!("Error while draining the outbox: {}", e);
+        }
+    }
+
+    info!("Outbox loop exited")
+}
+
+/// Serves the metrics if METRICS_LISTEN_ADDR is set
+async fn metrics_loop(man: &Torimies) {
+    let Some(addr) = METRICS_LISTEN_ADDR.as_ref() else {
+        return;
+    };
+    http::serve(man, addr, outbox::handle_metrics).await;
+
+    info!("Metrics loop exited")
+}
+
+/// Serves the unsubscribe links of the emails
+#[cfg(feature = "email-delivery")]
+async fn unsubscribe_loop(man: &Torimies) {
+    http::serve(man, &EMAIL_LISTEN_ADDR, delivery::email::handle_unsubscribe).await;
+
+    info!("Unsubscribe loop exited")
+}
+
 async fn command_loop(man: &Torimies) {
     let mut balls = man.command.iter_mut().collect::<Vec<_>>();
     let fs = stream::iter(balls.iter_mut())
@@ -173,17 +384,49 @@ async fn main() {
 
     let database = Database::new().await;
 
+    if let Err(e) = crate::vahti::canonicalize_vahtis(&database).await {
+        error!("Failed to canonicalize the Vahtis: {}", e);
+    }
+
     let mut the_man = Torimies::new(database);
 
     #[cfg(feature = "discord-delivery")]
     {
-        let dc = crate::delivery::discord::Discord::init()
+        let dc = crate::delivery::discord::Discord::init(&the_man.database.clone())
             .await
             .expect("Discord delivery initialization failed");
 
+        the_man.register_deliverer(crate::delivery::discord::CHANNEL_ID, dc.for_channels());
         the_man.register_deliverer(crate::delivery::discord::ID, dc);
     }
 
+    #[cfg(feature = "webhook-delivery")]
+    {
+        let wh = crate::delivery::webhook::Webhook::init(&the_man.database.clone())
+            .await
+            .expect("Webhook delivery initialization failed");
+
+        the_man.register_deliverer(crate::delivery::webhook::ID, wh);
+    }
+
+    #[cfg(feature = "push-delivery")]
+    {
+        let push = crate::delivery::push::Push::init(&the_man.database.clone())
+            .await
+            .expect("Push delivery initialization failed");
+
+        the_man.register_deliverer(crate::delivery::push::ID, push);
+    }
+
+    #[cfg(feature = "email-delivery")]
+    {
+        let email = crate::delivery::email::Email::init(&the_man.database.clone())
+            .await
+            .expect("Email delivery initialization failed");
+
+        the_man.register_deliverer(crate::delivery::email::ID, email);
+    }
+
     #[cfg(feature = "discord-command")]
     {
         let dc = crate::command::discord::Discord::init(&the_man.database.clone())
@@ -195,7 +438,7 @@ async fn main() {
 
     #[cfg(feature = "telegram-delivery")]
     {
-        let tg = crate::delivery::telegram::Telegram::init()
+        let tg = crate::delivery::telegram::Telegram::init(&the_man.database.clone())
             .await
             .expect("Telegram delivery initialization failed");
 
@@ -211,12 +454,63 @@ async fn main() {
         the_man.register_commander(crate::command::telegram::NAME, tg);
     }
 
-    let the_man2 = the_man.clone();
-    let the_man3 = the_man.clone();
+    #[cfg(feature = "matrix-delivery")]
+    {
+        let mx = crate::delivery::matrix::Matrix::init(&the_man.database.clone())
+            .await
+            .expect("Matrix delivery initialization failed");
+
+        the_man.register_deliverer(crate::delivery::matrix::ID, mx);
+    }
 
-    let update = tokio::task::spawn(async move { update_loop(&mut the_man).await });
-    let command = tokio::task::spawn(async move { command_loop(&the_man2).await });
-    let ctrl_c = tokio::task::spawn(async move { ctrl_c_handler(&the_man3).await });
+    #[cfg(feature = "matrix-command")]
+    {
+        let mx = crate::command::matrix::Matrix::init(&the_man.database.clone())
+            .await
+            .expect("Matrix commmand initialization failed");
 
-    let _ = futures::join!(update, command, ctrl_c);
+        the_man.register_commander(crate::command::matrix::NAME, mx);
+    }
+
+    let the_man2 = the_man.clone();
+    let the_man3 = the_man.clone();
+    let the_man5 = the_man.clone();
+    let the_man6 = the_man.clone();
+    let the_man8 = the_man.clone();
+    let the_man9 = the_man.clone();
+    #[cfg(feature = "huutonet")]
+    let the_man4 = the_man.clone();
+    #[cfg(feature = "email-delivery")]
+    let the_man7 = the_man.clone();
+
+    let mut tasks = vec![
+        tokio::task::spawn(async move { update_loop(&mut the_man).await }),
+        tokio::task::spawn(async move { command_loop(&the_man2).await }),
+        tokio::task::spawn(async move { ctrl_c_handler(&the_man3).await }),
+    ];
+
+    tasks.push(tokio::task::spawn(
+        async move { bookmark_loop(&the_man5).await },
+    ));
+    tasks.push(tokio::task::spawn(
+        async move { schedule_loop(&the_man6).await },
+    ));
+    tasks.push(tokio::task::spawn(
+        async move { outbox_loop(&the_man8).await },
+    ));
+    tasks.push(tokio::task::spawn(
+        async move { metrics_loop(&the_man9).await },
+    ));
+
+    #[cfg(feature = "huutonet")]
+    tasks.push(tokio::task::spawn(
+        async move { reminder_loop(&the_man4).await },
+    ));
+
+    #[cfg(feature = "email-delivery")]
+    tasks.push(tokio::task::spawn(async move {
+        unsubscribe_loop(&the_man7).await
+    }));
+
+    let _ = join_all(tasks).await;
 }
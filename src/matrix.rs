@@ -0,0 +1,197 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use reqwest::{Client, Method, RequestBuilder};
+use serde_json::{json, Value};
+
+use crate::error::Error;
+
+/// A minimal client for the Matrix client-server API, shared by the
+/// delivery and the command handling
+#[derive(Clone)]
+pub struct MatrixClient {
+    pub client: Client,
+    pub homeserver: url::Url,
+    pub access_token: String,
+}
+
+/// A text message sent to a room the bot is in
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoomMessage {
+    pub room_id: String,
+    pub sender: String,
+    pub body: String,
+}
+
+static TRANSACTION_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+fn transaction_id() -> String {
+    format!(
+        "torimies-{}-{}",
+        chrono::Utc::now().timestamp_millis(),
+        TRANSACTION_COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+impl MatrixClient {
+    pub fn new(homeserver: &str, access_token: &str) -> Result<Self, Error> {
+        Ok(Self {
+            client: Client::new(),
+            homeserver: url::Url::parse(homeserver)
+                .map_err(|e| Error::Matrix(format!("invalid homeserver: {}", e)))?,
+            access_token: access_token.to_string(),
+        })
+    }
+
+    pub fn from_env() -> Result<Self, Error> {
+        let homeserver = std::env::var("MATRIX_HOMESERVER")
+            .expect("Expected MATRIX_HOMESERVER in the environment");
+        let token = std::env::var("MATRIX_ACCESS_TOKEN")
+            .expect("Expected MATRIX_ACCESS_TOKEN in the environment");
+        Self::new(&homeserver, &token)
+    }
+
+    /// Builds a request to the path, the segments are percent-encoded
+    fn request(&self, method: Method, segments: &[&str]) -> RequestBuilder {
+        let mut url = self.homeserver.clone();
+        url.path_segments_mut()
+            .expect("bug: impossible")
+            .pop_if_empty()
+            .extend(segments);
+        self.client
+            .request(method, url)
+            .bearer_auth(&self.access_token)
+    }
+
+    async fn send(request: RequestBuilder) -> Result<Value, Error> {
+        let response = request.send().await?;
+        let status = response.status();
+        let body = response.json::<Value>().await.unwrap_or_default();
+        if !status.is_success() {
+            return Err(Error::Matrix(format!(
+                "{} {}",
+                status,
+                body["error"].as_str().unwrap_or_default()
+            )));
+        }
+        Ok(body)
+    }
+
+    /// Returns the user id of the bot
+    pub async fn whoami(&self) -> Result<String, Error> {
+        let body = Self::send(self.request(
+            Method::GET,
+            &["_matrix", "client", "v3", "account", "whoami"],
+        ))
+        .await?;
+        body["user_id"]
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| Error::Matrix(String::from("whoami returned no user_id")))
+    }
+
+    pub async fn join(&self, room_id: &str) -> Result<(), Error> {
+        Self::send(
+            self.request(Method::POST, &["_matrix", "client", "v3", "join", room_id])
+                .json(&json!({})),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Sends a message with both a plain-text and a HTML body
+    pub async fn send_message(&self, room_id: &str, body: &str, html: &str) -> Result<(), Error> {
+        let txn = transaction_id();
+        Self::send(
+            self.request(
+                Method::PUT,
+                &[
+                    "_matrix",
+                    "client",
+                    "v3",
+                    "rooms",
+                    room_id,
+                    "send",
+                    "m.room.message",
+                    &txn,
+                ],
+            )
+            .json(&json!({
+                "msgtype": "m.text",
+                "body": body,
+                "format": "org.matrix.custom.html",
+                "formatted_body": html,
+            })),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Uploads the image behind `url` to the homeserver, returning its `mxc://` uri
+    pub async fn upload_image(&self, url: &str) -> Result<String, Error> {
+        let response = self.client.get(url).send().await?.error_for_status()?;
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|c| c.to_str().ok())
+            .unwrap_or("image/jpeg")
+            .to_string();
+        let bytes = response.bytes().await?;
+
+        let body = Self::send(
+            self.request(Method::POST, &["_matrix", "media", "v3", "upload"])
+                .header(reqwest::header::CONTENT_TYPE, content_type)
+                .body(bytes),
+        )
+        .await?;
+        body["content_uri"]
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| Error::Matrix(String::from("upload returned no content_uri")))
+    }
+
+    /// Long-polls for new events, `since` is the `next_batch` of the previous sync
+    pub async fn sync(&self, since: Option<&str>, timeout_ms: u64) -> Result<Value, Error> {
+        let mut request = self
+            .request(Method::GET, &["_matrix", "client", "v3", "sync"])
+            .query(&[("timeout", timeout_ms.to_string())]);
+        if let Some(since) = since {
+            request = request.query(&[("since", since)]);
+        }
+        Self::send(request).await
+    }
+}
+
+/// Parses the text messages of the joined rooms from a sync response
+pub fn messages_from_sync(sync: &Value) -> Vec<RoomMessage> {
+    let Some(rooms) = sync["rooms"]["join"].as_object() else {
+        return vec![];
+    };
+
+    rooms
+        .iter()
+        .flat_map(|(room_id, room)| {
+            room["timeline"]["events"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|e| e["type"] == "m.room.message" && e["content"]["msgtype"] == "m.text")
+                .filter_map(|e| {
+                    Some(RoomMessage {
+                        room_id: room_id.clone(),
+                        sender: e["sender"].as_str()?.to_string(),
+                        body: e["content"]["body"].as_str()?.to_string(),
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Parses the ids of the rooms the bot has been invited to from a sync response
+pub fn invites_from_sync(sync: &Value) -> Vec<String> {
+    sync["rooms"]["invite"]
+        .as_object()
+        .map(|rooms| rooms.keys().cloned().collect())
+        .unwrap_or_default()
+}
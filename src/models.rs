@@ -6,6 +6,18 @@ pub struct DbVahti {
     pub last_updated: i64,
     pub site_id: i32,
     pub delivery_method: i32,
+    pub filters: Option<String>,
+    pub digest: Option<String>,
+    pub last_digest: i64,
+    pub paused: bool,
+    /// The error of the latest failed fetch, cleared once a fetch succeeds
+    pub last_error: Option<String>,
+    /// The Vahti is not updated until this time
+    pub snoozed_until: Option<i64>,
+    /// The user who added the Vahti, differs from `user_id` for group and channel Vahtis
+    pub creator_id: Option<i64>,
+    /// The Vahti is not updated as its items could not be delivered to the user
+    pub disabled: bool,
 }
 
 use crate::schema::Vahdit;
@@ -18,6 +30,8 @@ pub struct NewVahti {
     pub last_updated: i64,
     pub site_id: i32,
     pub delivery_method: i32,
+    pub filters: Option<String>,
+    pub creator_id: Option<i64>,
 }
 
 #[derive(Queryable, Clone, Debug)]
@@ -26,6 +40,7 @@ pub struct Blacklist {
     pub user_id: i64,
     pub seller_id: i32,
     pub site_id: i32,
+    pub delivery_method: i32,
 }
 
 use crate::schema::Blacklists;
@@ -36,4 +51,314 @@ pub struct NewBlacklist {
     pub user_id: i64,
     pub seller_id: i32,
     pub site_id: i32,
+    pub delivery_method: i32,
+}
+
+#[derive(Queryable, Clone, Debug)]
+pub struct SeenItem {
+    pub id: i32,
+    pub user_id: i64,
+    pub delivery_method: i32,
+    pub site_id: i32,
+    pub ad_id: i64,
+    pub seen_at: i64,
+    pub price: i64,
+    pub vahti_id: Option<i32>,
+}
+
+use crate::schema::SeenItems;
+
+#[derive(Insertable)]
+#[table_name = "SeenItems"]
+pub struct NewSeenItem {
+    pub user_id: i64,
+    pub delivery_method: i32,
+    pub site_id: i32,
+    pub ad_id: i64,
+    pub seen_at: i64,
+    pub price: i64,
+    pub vahti_id: Option<i32>,
+}
+
+#[derive(Queryable, Clone, Debug)]
+pub struct AuctionWatch {
+    pub id: i32,
+    pub user_id: i64,
+    pub delivery_method: i32,
+    pub item_id: i64,
+    pub title: String,
+    pub url: String,
+    pub closing_time: i64,
+    pub remind_before: i64,
+    pub reminded: bool,
+}
+
+use crate::schema::AuctionWatches;
+
+#[derive(Insertable)]
+#[table_name = "AuctionWatches"]
+pub struct NewAuctionWatch {
+    pub user_id: i64,
+    pub delivery_method: i32,
+    pub item_id: i64,
+    pub title: String,
+    pub url: String,
+    pub closing_time: i64,
+    pub remind_before: i64,
+}
+
+#[derive(Queryable, Clone, Debug)]
+pub struct Bookmark {
+    pub id: i32,
+    pub user_id: i64,
+    pub delivery_method: i32,
+    pub site_id: i32,
+    pub ad_id: i64,
+    pub title: String,
+    pub url: String,
+}
+
+use crate::schema::Bookmarks;
+
+#[derive(Insertable)]
+#[table_name = "Bookmarks"]
+pub struct NewBookmark {
+    pub user_id: i64,
+    pub delivery_method: i32,
+    pub site_id: i32,
+    pub ad_id: i64,
+    pub title: String,
+    pub url: String,
+}
+
+#[derive(Queryable, Clone, Debug)]
+pub struct DigestItem {
+    pub id: i32,
+    pub vahti_id: i32,
+    pub item: String,
+    pub added_at: i64,
+}
+
+use crate::schema::DigestItems;
+
+#[derive(Insertable)]
+#[table_name = "DigestItems"]
+pub struct NewDigestItem {
+    pub vahti_id: i32,
+    pub item: String,
+    pub added_at: i64,
+}
+
+#[derive(Queryable, Clone, Debug)]
+pub struct DbUserSettings {
+    pub id: i32,
+    pub user_id: i64,
+    pub delivery_method: i32,
+    pub timezone: Option<String>,
+    pub quiet_start: Option<String>,
+    pub quiet_end: Option<String>,
+}
+
+use crate::schema::UserSettings;
+
+#[derive(Insertable)]
+#[table_name = "UserSettings"]
+pub struct NewUserSettings {
+    pub user_id: i64,
+    pub delivery_method: i32,
+    pub timezone: Option<String>,
+    pub quiet_start: Option<String>,
+    pub quiet_end: Option<String>,
+}
+
+#[derive(Queryable, Clone, Debug)]
+pub struct HeldItem {
+    pub id: i32,
+    pub user_id: i64,
+    pub delivery_method: i32,
+    pub item: String,
+    pub added_at: i64,
+}
+
+use crate::schema::HeldItems;
+
+#[derive(Insertable)]
+#[table_name = "HeldItems"]
+pub struct NewHeldItem {
+    pub user_id: i64,
+    pub delivery_method: i32,
+    pub item: String,
+    pub added_at: i64,
+}
+
+#[derive(Queryable, Clone, Debug)]
+pub struct Webhook {
+    pub id: i32,
+    pub user_id: i64,
+    pub delivery_method: i32,
+    pub url: String,
+    pub secret: Option<String>,
+}
+
+use crate::schema::Webhooks;
+
+#[derive(Insertable)]
+#[table_name = "Webhooks"]
+pub struct NewWebhook {
+    pub user_id: i64,
+    pub delivery_method: i32,
+    pub url: String,
+    pub secret: Option<String>,
+}
+
+#[derive(Queryable, Clone, Debug)]
+pub struct EmailAddress {
+    pub id: i32,
+    pub user_id: i64,
+    pub delivery_method: i32,
+    pub address: String,
+    pub verification_code: String,
+    pub verified: bool,
+    pub unsubscribe_token: String,
+}
+
+use crate::schema::EmailAddresses;
+
+#[derive(Insertable)]
+#[table_name = "EmailAddresses"]
+pub struct NewEmailAddress {
+    pub user_id: i64,
+    pub delivery_method: i32,
+    pub address: String,
+    pub verification_code: String,
+    pub unsubscribe_token: String,
+}
+
+/// Maps a Matrix user to the numeric id used as the `user_id` of their Vahtis
+#[derive(Queryable, Clone, Debug)]
+pub struct MatrixUser {
+    pub id: i32,
+    pub matrix_id: String,
+    /// The room the items are delivered to, i.e. the room of the latest command
+    pub room_id: String,
+}
+
+use crate::schema::MatrixUsers;
+
+#[derive(Insertable)]
+#[table_name = "MatrixUsers"]
+pub struct NewMatrixUser {
+    pub matrix_id: String,
+    pub room_id: String,
+}
+
+#[derive(Queryable, Clone, Debug)]
+pub struct PushTarget {
+    pub id: i32,
+    pub user_id: i64,
+    pub delivery_method: i32,
+    /// Either `ntfy` or `gotify`
+    pub service: String,
+    pub server: String,
+    /// The ntfy topic, unused with Gotify
+    pub topic: Option<String>,
+    /// The ntfy access token or the Gotify application token
+    pub token: Option<String>,
+}
+
+use crate::schema::PushTargets;
+
+#[derive(Insertable)]
+#[table_name = "PushTargets"]
+pub struct NewPushTarget {
+    pub user_id: i64,
+    pub delivery_method: i32,
+    pub service: String,
+    pub server: String,
+    pub topic: Option<String>,
+    pub token: Option<String>,
+}
+
+#[derive(Queryable, Clone, Debug)]
+pub struct Endpoint {
+    pub id: i32,
+    /// Shared by all the endpoints linked together
+    pub identity_id: i32,
+    pub user_id: i64,
+    pub delivery_method: i32,
+}
+
+use crate::schema::Endpoints;
+
+#[derive(Insertable)]
+#[table_name = "Endpoints"]
+pub struct NewEndpoint {
+    pub identity_id: i32,
+    pub user_id: i64,
+    pub delivery_method: i32,
+}
+
+#[derive(Queryable, Clone, Debug)]
+pub struct PairingCode {
+    pub code: String,
+    pub user_id: i64,
+    pub delivery_method: i32,
+    pub expires_at: i64,
+}
+
+use crate::schema::PairingCodes;
+
+#[derive(Insertable)]
+#[table_name = "PairingCodes"]
+pub struct NewPairingCode {
+    pub code: String,
+    pub user_id: i64,
+    pub delivery_method: i32,
+    pub expires_at: i64,
+}
+
+#[derive(Queryable, Clone, Debug)]
+pub struct VahtiDestination {
+    pub id: i32,
+    pub vahti_id: i32,
+    pub user_id: i64,
+    pub delivery_method: i32,
+}
+
+use crate::schema::VahtiDestinations;
+
+#[derive(Insertable)]
+#[table_name = "VahtiDestinations"]
+pub struct NewVahtiDestination {
+    pub vahti_id: i32,
+    pub user_id: i64,
+    pub delivery_method: i32,
+}
+
+#[derive(Queryable, Clone, Debug)]
+pub struct OutboxEntry {
+    pub id: i32,
+    pub user_id: i64,
+    pub delivery_method: i32,
+    pub items: String,
+    pub created_at: i64,
+    pub attempts: i32,
+    pub next_attempt: i64,
+    pub last_error: Option<String>,
+    pub failed: bool,
+    /// The items are delivered as a single digest
+    pub digest: bool,
+}
+
+use crate::schema::Outbox;
+
+#[derive(Insertable)]
+#[table_name = "Outbox"]
+pub struct NewOutboxEntry {
+    pub user_id: i64,
+    pub delivery_method: i32,
+    pub items: String,
+    pub created_at: i64,
+    pub next_attempt: i64,
+    pub digest: bool,
 }
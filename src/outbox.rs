@@ -0,0 +1,234 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use futures::stream::{self, StreamExt};
+use itertools::Itertools;
+
+use crate::database::Database;
+use crate::delivery::{attempt_delivery, attempt_digest_delivery};
+use crate::error::Error;
+use crate::http::Response;
+use crate::models::OutboxEntry;
+use crate::unreachable::{record_delivery, report};
+use crate::vahti::VahtiItem;
+use crate::Torimies;
+
+/// The delay before the first retry in seconds, doubled after every failed attempt
+const BASE_RETRY_DELAY: i64 = 30;
+const MAX_RETRY_DELAY: i64 = 60 * 60;
+/// The amount of entries attempted on a single drain, the rest wait for the next one
+const DRAIN_BATCH_SIZE: i64 = 500;
+
+static DELIVERED: AtomicU64 = AtomicU64::new(0);
+static RETRIED: AtomicU64 = AtomicU64::new(0);
+static FAILED: AtomicU64 = AtomicU64::new(0);
+
+/// When a delivery that has failed `attempts` times should be attempted again,
+/// `None` once it is time to give up
+pub fn next_attempt(attempts: i32, now: i64) -> Option<i64> {
+    if attempts >= *crate::OUTBOX_MAX_ATTEMPTS {
+        return None;
+    }
+
+    let delay = BASE_RETRY_DELAY << (attempts.clamp(1, 16) - 1);
+    Some(now + delay.min(MAX_RETRY_DELAY))
+}
+
+impl Torimies {
+    /// Attempts the due deliveries of the outbox. The entries of a recipient are
+    /// delivered in order and the rest of them are left for later if one is retried
+    pub async fn drain_outbox(&self) -> Result<(), Error> {
+        let now = chrono::Local::now().timestamp();
+        self.database
+            .purge_failed_outbox_entries(now - *crate::ITEM_HISTORY_RETENTION)
+            .await?;
+
+        let entries = self
+            .database
+            .fetch_due_outbox_entries(now, DRAIN_BATCH_SIZE)
+            .await?;
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        let groups = entries
+            .into_iter()
+            .into_group_map_by(|e| (e.user_id, e.delivery_method))
+            .into_values()
+            .map(|mut g| {
+                g.sort_by_key(|e| e.id);
+                g
+            })
+            .collect::<Vec<_>>();
+
+        stream::iter(groups)
+            .for_each_concurrent(*crate::FUTURES_MAX_BUFFER_SIZE, |g| async move {
+                for entry in g {
+                    match self.attempt_entry(&entry, now).await {
+                        Ok(true) => {}
+                        Ok(false) => break,
+                        Err(e) => {
+                            error!("Failed to update outbox entry {}: {}", entry.id, e);
+                            break;
+                        }
+                    }
+                }
+            })
+            .await;
+
+        let (pending, failed, oldest) = self.database.outbox_stats().await?;
+        info!(
+            "Outbox: {} pending (oldest {}s), {} failed",
+            pending,
+            oldest.map(|o| now - o).unwrap_or(0),
+            failed
+        );
+
+        Ok(())
+    }
+
+    /// Attempts a single entry, returning whether it is done with, i.e. it was delivered
+    /// or given up on. A message that can't be delivered mustn't hold up the ones after it
+    async fn attempt_entry(&self, entry: &OutboxEntry, now: i64) -> Result<bool, Error> {
+        // NOTE: The recipient of the entry is the one to deliver to, it may have been
+        // migrated after the items were queued
+        let res = match serde_json::from_str::<Vec<VahtiItem>>(&entry.items) {
+            Ok(items) => {
+                let items = items
+                    .into_iter()
+                    .map(|mut i| {
+                        i.deliver_to = Some(entry.user_id as u64);
+                        i
+                    })
+                    .collect();
+                if entry.digest {
+                    attempt_digest_delivery(self.delivery.clone(), items).await
+                } else {
+                    attempt_delivery(self.delivery.clone(), items).await
+                }
+            }
+            Err(e) => Err(e.into()),
+        };
+
+        if let Err(e) =
+            record_delivery(&self.database, entry.user_id, entry.delivery_method, &res).await
+        {
+            error!("Failed to record the delivery to {}: {}", entry.user_id, e);
+        }
+
+        let e = match res {
+            Ok(()) => {
+                DELIVERED.fetch_add(1, Ordering::Relaxed);
+                self.database.remove_outbox_entry(entry.id).await?;
+                return Ok(true);
+            }
+            Err(e) => e,
+        };
+
+        let attempts = entry.attempts + 1;
+        let retry_at = next_attempt(attempts, now).filter(|_| !e.is_permanent());
+        match retry_at {
+            Some(at) => {
+                warn!(
+                    "Delivery to {} ({}) failed {} times, retrying in {}s: {}",
+                    entry.user_id,
+                    entry.delivery_method,
+                    attempts,
+                    at - now,
+                    e
+                );
+                RETRIED.fetch_add(1, Ordering::Relaxed);
+                self.database
+                    .reschedule_outbox_entry(entry.id, at, &e.to_string())
+                    .await?;
+            }
+            None => {
+                error!(
+                    "Giving up on the delivery to {} ({}) after {} attempts: {}",
+                    entry.user_id, entry.delivery_method, attempts, e
+                );
+                FAILED.fetch_add(1, Ordering::Relaxed);
+                self.database
+                    .fail_outbox_entry(entry.id, &e.to_string())
+                    .await?;
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+}
+
+/// The state of the outbox in the Prometheus text format
+pub async fn metrics(db: &Database) -> Result<String, Error> {
+    let now = chrono::Local::now().timestamp();
+    let (pending, failed, oldest) = db.outbox_stats().await?;
+
+    let mut out = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+        out.push_str(&format!(
+            "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}\n"
+        ));
+    };
+    metric(
+        "torimies_outbox_pending",
+        "gauge",
+        "Deliveries waiting in the outbox",
+        pending.to_string(),
+    );
+    metric(
+        "torimies_outbox_failed",
+        "gauge",
+        "Deliveries that were given up on",
+        failed.to_string(),
+    );
+    metric(
+        "torimies_outbox_oldest_seconds",
+        "gauge",
+        "Age of the oldest pending delivery",
+        oldest.map(|o| now - o).unwrap_or(0).to_string(),
+    );
+    metric(
+        "torimies_deliveries_total",
+        "counter",
+        "Deliveries completed since startup",
+        DELIVERED.load(Ordering::Relaxed).to_string(),
+    );
+    metric(
+        "torimies_delivery_retries_total",
+        "counter",
+        "Failed delivery attempts that will be retried",
+        RETRIED.load(Ordering::Relaxed).to_string(),
+    );
+    metric(
+        "torimies_delivery_failures_total",
+        "counter",
+        "Deliveries given up on since startup",
+        FAILED.load(Ordering::Relaxed).to_string(),
+    );
+
+    out.push_str(
+        "# HELP torimies_disabled_vahtis Vahtis disabled because their users could not be reached\n\
+        # TYPE torimies_disabled_vahtis gauge\n",
+    );
+    for (delivery_method, count) in db.count_disabled_vahtis().await? {
+        out.push_str(&format!(
+            "torimies_disabled_vahtis{{delivery_method=\"{}\"}} {}\n",
+            delivery_method, count
+        ));
+    }
+    Ok(out)
+}
+
+/// Answers `/report` with the report of the disabled Vahtis and anything else with the metrics
+pub async fn handle_metrics(db: Database, request: String) -> Result<Response, Error> {
+    let body = if request.starts_with("GET /report ") {
+        report(&db).await? + "\n"
+    } else {
+        metrics(&db).await?
+    };
+    Ok(Response {
+        status: "200 OK",
+        content_type: "text/plain; version=0.0.4",
+        body,
+    })
+}
@@ -0,0 +1,103 @@
+use chrono::Local;
+
+use crate::database::Database;
+use crate::delivery::{perform_notification, Notification};
+use crate::error::Error;
+use crate::huutonet::api::fetch_auction;
+use crate::models::NewAuctionWatch;
+use crate::settings::UserSettings;
+use crate::Torimies;
+
+/// Starts watching a huuto.net auction, the user is reminded `remind_before`
+/// seconds before the auction closes
+pub async fn watch_auction(
+    db: Database,
+    item_id: i64,
+    userid: u64,
+    delivery_method: i32,
+    remind_before: i64,
+) -> Result<String, Error> {
+    let auction = fetch_auction(item_id).await?;
+
+    if auction.closing_time <= Local::now().timestamp() {
+        return Ok(String::from("The auction has already closed"));
+    }
+
+    let settings = UserSettings::fetch(&db, userid as i64, delivery_method).await;
+    db.add_auction_watch(NewAuctionWatch {
+        user_id: userid as i64,
+        delivery_method,
+        item_id,
+        title: auction.title.clone(),
+        url: auction.url,
+        closing_time: auction.closing_time,
+        remind_before,
+    })
+    .await?;
+
+    Ok(format!(
+        "You will be reminded about `{}` {} minutes before it closes at {}",
+        auction.title,
+        remind_before / 60,
+        settings.format_time(auction.closing_time)
+    ))
+}
+
+impl Torimies {
+    pub async fn send_auction_reminders(&self) -> Result<(), Error> {
+        let now = Local::now().timestamp();
+
+        self.database.purge_auction_watches(now).await?;
+
+        let watches = self.database.fetch_due_auction_watches(now).await?;
+        if !watches.is_empty() {
+            info!("Sending {} auction reminders", watches.len());
+        }
+
+        for w in watches {
+            let auction = match fetch_auction(w.item_id).await {
+                Ok(a) => a,
+                Err(e) => {
+                    error!("Failed to fetch auction {}: {}", w.item_id, e);
+                    continue;
+                }
+            };
+
+            // NOTE: The closing time may have been extended after the watch was added
+            if auction.closing_time - w.remind_before > now {
+                self.database
+                    .update_auction_closing_time(w.id, auction.closing_time)
+                    .await?;
+                continue;
+            }
+
+            let notification = Notification::AuctionEnding {
+                title: auction.title,
+                url: auction.url,
+                closing_time: auction.closing_time,
+                current_price: auction.current_price,
+                buy_now_price: auction.buy_now_price,
+                bidder_count: auction.bidder_count,
+                reserve_price_exceeded: auction
+                    .has_reserve_price
+                    .then_some(auction.has_reserve_price_exceeded),
+            };
+
+            if let Err(e) = perform_notification(
+                self.delivery.clone(),
+                w.user_id as u64,
+                w.delivery_method,
+                notification,
+            )
+            .await
+            {
+                error!("Failed to send an auction reminder to {}: {}", w.user_id, e);
+                continue;
+            }
+
+            self.database.mark_auction_reminded(w.id).await?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,12 +1,97 @@
 #![allow(non_snake_case)]
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    AuctionWatches (id) {
+        id -> Integer,
+        user_id -> BigInt,
+        delivery_method -> Integer,
+        item_id -> BigInt,
+        title -> Text,
+        url -> Text,
+        closing_time -> BigInt,
+        remind_before -> BigInt,
+        reminded -> Bool,
+    }
+}
+
 diesel::table! {
     Blacklists (id) {
         id -> Integer,
         user_id -> BigInt,
         seller_id -> Integer,
         site_id -> Integer,
+        delivery_method -> Integer,
+    }
+}
+
+diesel::table! {
+    Bookmarks (id) {
+        id -> Integer,
+        user_id -> BigInt,
+        delivery_method -> Integer,
+        site_id -> Integer,
+        ad_id -> BigInt,
+        title -> Text,
+        url -> Text,
+    }
+}
+
+diesel::table! {
+    DataVersions (name) {
+        name -> Text,
+        version -> Integer,
+    }
+}
+
+diesel::table! {
+    DeliveryFailures (user_id, delivery_method) {
+        user_id -> BigInt,
+        delivery_method -> Integer,
+        failures -> Integer,
+    }
+}
+
+diesel::table! {
+    DigestItems (id) {
+        id -> Integer,
+        vahti_id -> Integer,
+        item -> Text,
+        added_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    HeldItems (id) {
+        id -> Integer,
+        user_id -> BigInt,
+        delivery_method -> Integer,
+        item -> Text,
+        added_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    SeenItems (id) {
+        id -> Integer,
+        user_id -> BigInt,
+        delivery_method -> Integer,
+        site_id -> Integer,
+        ad_id -> BigInt,
+        seen_at -> BigInt,
+        price -> BigInt,
+        vahti_id -> Nullable<Integer>,
+    }
+}
+
+diesel::table! {
+    UserSettings (id) {
+        id -> Integer,
+        user_id -> BigInt,
+        delivery_method -> Integer,
+        timezone -> Nullable<Text>,
+        quiet_start -> Nullable<Text>,
+        quiet_end -> Nullable<Text>,
     }
 }
 
@@ -18,7 +103,118 @@ diesel::table! {
         last_updated -> BigInt,
         site_id -> Integer,
         delivery_method -> Integer,
+        filters -> Nullable<Text>,
+        digest -> Nullable<Text>,
+        last_digest -> BigInt,
+        paused -> Bool,
+        last_error -> Nullable<Text>,
+        snoozed_until -> Nullable<BigInt>,
+        creator_id -> Nullable<BigInt>,
+        disabled -> Bool,
+    }
+}
+
+diesel::table! {
+    Webhooks (id) {
+        id -> Integer,
+        user_id -> BigInt,
+        delivery_method -> Integer,
+        url -> Text,
+        secret -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    EmailAddresses (id) {
+        id -> Integer,
+        user_id -> BigInt,
+        delivery_method -> Integer,
+        address -> Text,
+        verification_code -> Text,
+        verified -> Bool,
+        unsubscribe_token -> Text,
+    }
+}
+
+diesel::table! {
+    MatrixUsers (id) {
+        id -> Integer,
+        matrix_id -> Text,
+        room_id -> Text,
+    }
+}
+
+diesel::table! {
+    PushTargets (id) {
+        id -> Integer,
+        user_id -> BigInt,
+        delivery_method -> Integer,
+        service -> Text,
+        server -> Text,
+        topic -> Nullable<Text>,
+        token -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    Endpoints (id) {
+        id -> Integer,
+        identity_id -> Integer,
+        user_id -> BigInt,
+        delivery_method -> Integer,
+    }
+}
+
+diesel::table! {
+    Outbox (id) {
+        id -> Integer,
+        user_id -> BigInt,
+        delivery_method -> Integer,
+        items -> Text,
+        created_at -> BigInt,
+        attempts -> Integer,
+        next_attempt -> BigInt,
+        last_error -> Nullable<Text>,
+        failed -> Bool,
+        digest -> Bool,
+    }
+}
+
+diesel::table! {
+    PairingCodes (code) {
+        code -> Text,
+        user_id -> BigInt,
+        delivery_method -> Integer,
+        expires_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    VahtiDestinations (id) {
+        id -> Integer,
+        vahti_id -> Integer,
+        user_id -> BigInt,
+        delivery_method -> Integer,
     }
 }
 
-diesel::allow_tables_to_appear_in_same_query!(Blacklists, Vahdit,);
+diesel::allow_tables_to_appear_in_same_query!(
+    AuctionWatches,
+    Blacklists,
+    Bookmarks,
+    DataVersions,
+    DeliveryFailures,
+    DigestItems,
+    EmailAddresses,
+    Endpoints,
+    HeldItems,
+    MatrixUsers,
+    Outbox,
+    PairingCodes,
+    PushTargets,
+    SeenItems,
+    UserSettings,
+    Vahdit,
+    VahtiDestinations,
+    Webhooks,
+);
@@ -0,0 +1,194 @@
+use std::fmt;
+
+use chrono::{Local, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::database::Database;
+use crate::delivery::perform_delivery;
+use crate::error::Error;
+use crate::models::{DbUserSettings, NewUserSettings};
+use crate::vahti::VahtiItem;
+use crate::Torimies;
+
+/// Per-user settings, the defaults are the local timezone of the server
+/// and no quiet hours
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UserSettings {
+    pub timezone: Option<Tz>,
+    /// Items are held between these times and delivered afterwards
+    pub quiet_hours: Option<(NaiveTime, NaiveTime)>,
+}
+
+fn parse_time(s: &str) -> Result<NaiveTime, Error> {
+    NaiveTime::parse_from_str(s.trim(), "%H:%M")
+        .map_err(|_| Error::InvalidSetting(format!("invalid time {}", s)))
+}
+
+pub fn parse_timezone(s: &str) -> Result<Tz, Error> {
+    s.trim()
+        .parse::<Tz>()
+        .map_err(|_| Error::InvalidSetting(format!("unknown timezone {}", s)))
+}
+
+/// Parses quiet hours like `22:00-07:00`
+pub fn parse_quiet_hours(s: &str) -> Result<(NaiveTime, NaiveTime), Error> {
+    let (start, end) = s
+        .split_once('-')
+        .ok_or_else(|| Error::InvalidSetting(format!("invalid quiet hours {}", s)))?;
+    Ok((parse_time(start)?, parse_time(end)?))
+}
+
+impl UserSettings {
+    pub fn from_db(s: Option<DbUserSettings>) -> Self {
+        let Some(s) = s else {
+            return Self::default();
+        };
+
+        let quiet_hours = match (s.quiet_start, s.quiet_end) {
+            (Some(start), Some(end)) => parse_time(&start).ok().zip(parse_time(&end).ok()),
+            _ => None,
+        };
+
+        Self {
+            timezone: s.timezone.and_then(|t| parse_timezone(&t).ok()),
+            quiet_hours,
+        }
+    }
+
+    pub fn to_db(&self, userid: i64, delivery_method: i32) -> NewUserSettings {
+        NewUserSettings {
+            user_id: userid,
+            delivery_method,
+            timezone: self.timezone.map(|t| t.name().to_string()),
+            quiet_start: self.quiet_hours.map(|(s, _)| s.format("%H:%M").to_string()),
+            quiet_end: self.quiet_hours.map(|(_, e)| e.format("%H:%M").to_string()),
+        }
+    }
+
+    pub async fn fetch(db: &Database, userid: i64, delivery_method: i32) -> Self {
+        // NOTE: If db fails, the defaults are used
+        Self::from_db(
+            db.fetch_user_settings(userid, delivery_method)
+                .await
+                .ok()
+                .flatten(),
+        )
+    }
+
+    /// The time of the day at `timestamp` in the timezone of the user
+    fn time_of_day(&self, timestamp: i64) -> NaiveTime {
+        match self.timezone {
+            Some(tz) => tz.timestamp_opt(timestamp, 0).unwrap().time(),
+            None => Local.timestamp_opt(timestamp, 0).unwrap().time(),
+        }
+    }
+
+    pub fn is_quiet(&self, timestamp: i64) -> bool {
+        let Some((start, end)) = self.quiet_hours else {
+            return false;
+        };
+
+        let time = self.time_of_day(timestamp);
+        if start <= end {
+            start <= time && time < end
+        } else {
+            // The quiet hours continue over midnight
+            time >= start || time < end
+        }
+    }
+
+    /// Formats `timestamp` in the timezone of the user
+    pub fn format_time(&self, timestamp: i64) -> String {
+        match self.timezone {
+            Some(tz) => tz
+                .timestamp_opt(timestamp, 0)
+                .unwrap()
+                .format("%d/%m/%Y %R")
+                .to_string(),
+            None => Local
+                .timestamp_opt(timestamp, 0)
+                .unwrap()
+                .format("%d/%m/%Y %R")
+                .to_string(),
+        }
+    }
+}
+
+impl fmt::Display for UserSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.timezone {
+            Some(tz) => writeln!(f, "Timezone: {}", tz.name())?,
+            None => writeln!(f, "Timezone: server default")?,
+        }
+        match self.quiet_hours {
+            Some((start, end)) => write!(
+                f,
+                "Quiet hours: {}-{}",
+                start.format("%H:%M"),
+                end.format("%H:%M")
+            ),
+            None => write!(f, "Quiet hours: off"),
+        }
+    }
+}
+
+/// Updates the settings that are given, `Some(None)` resets the setting
+pub async fn update_user_settings(
+    db: Database,
+    userid: u64,
+    delivery_method: i32,
+    timezone: Option<Option<Tz>>,
+    quiet_hours: Option<Option<(NaiveTime, NaiveTime)>>,
+) -> Result<String, Error> {
+    let mut settings = UserSettings::fetch(&db, userid as i64, delivery_method).await;
+
+    if let Some(tz) = timezone {
+        settings.timezone = tz;
+    }
+    if let Some(q) = quiet_hours {
+        settings.quiet_hours = q;
+    }
+
+    db.set_user_settings(settings.to_db(userid as i64, delivery_method))
+        .await?;
+    Ok(format!("Settings updated\n{}", settings))
+}
+
+impl Torimies {
+    /// Delivers the items that were held during the quiet hours of the users
+    pub async fn release_held_items(&self) -> Result<(), Error> {
+        let now = Utc::now().timestamp();
+
+        for (userid, delivery_method) in self.database.fetch_held_item_users().await? {
+            let settings = UserSettings::fetch(&self.database, userid, delivery_method).await;
+            if settings.is_quiet(now) {
+                continue;
+            }
+
+            let held = self
+                .database
+                .fetch_held_items(userid, delivery_method)
+                .await?;
+            let Some(up_to) = held.last().map(|i| i.id) else {
+                continue;
+            };
+
+            let items = held
+                .iter()
+                .filter_map(|i| serde_json::from_str::<VahtiItem>(&i.item).ok())
+                .collect::<Vec<_>>();
+
+            info!("Releasing {} held items to {}", items.len(), userid);
+            if let Err(e) = perform_delivery(&self.database, items).await {
+                error!("Failed to queue held items for {}: {}", userid, e);
+                continue;
+            }
+
+            self.database
+                .remove_held_items(userid, delivery_method, up_to)
+                .await?;
+        }
+
+        Ok(())
+    }
+}
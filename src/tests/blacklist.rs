@@ -0,0 +1,32 @@
+use crate::blacklist::{seller_from_url, seller_url};
+
+#[test]
+fn seller_urls() {
+    assert_eq!(
+        seller_from_url("https://www.tori.fi/profile/ads?userId=123"),
+        Some((crate::tori::ID, 123))
+    );
+    assert_eq!(
+        seller_from_url("https://www.tori.fi/li?&aid=456"),
+        Some((crate::tori::ID, 456))
+    );
+    assert_eq!(
+        seller_from_url("https://www.huuto.net/kayttaja/789/"),
+        Some((crate::huutonet::ID, 789))
+    );
+    assert_eq!(
+        seller_from_url("https://www.tori.fi/recommerce/forsale/search"),
+        None
+    );
+    assert_eq!(seller_from_url("https://example.com/kayttaja/1"), None);
+}
+
+#[test]
+fn seller_url_roundtrip() {
+    for (siteid, sellerid) in [(crate::tori::ID, 123), (crate::huutonet::ID, 789)] {
+        assert_eq!(
+            seller_from_url(&seller_url(sellerid, siteid)),
+            Some((siteid, sellerid))
+        );
+    }
+}
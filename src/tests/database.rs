@@ -0,0 +1,245 @@
+use std::path::PathBuf;
+
+use diesel::connection::SimpleConnection;
+use diesel::{Connection, SqliteConnection};
+
+use crate::database::Database;
+use crate::error::Error;
+
+const URL: &str = "https://www.tori.fi/recommerce/forsale/search?q=thinkpad";
+const USER: i64 = 123456789;
+const DISCORD: i32 = crate::delivery::discord::ID;
+const TELEGRAM: i32 = crate::delivery::telegram::ID;
+
+/// Creates a fresh database with every migration applied
+pub fn test_db(name: &str) -> (Database, PathBuf) {
+    let path =
+        std::env::temp_dir().join(format!("torimies-{}-{}.sqlite", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let mut migrations = std::fs::read_dir("migrations")
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect::<Vec<_>>();
+    migrations.sort_by_key(|p| {
+        p.file_name()
+            .unwrap()
+            .to_string_lossy()
+            .split('_')
+            .next()
+            .unwrap()
+            .replace('-', "")
+    });
+
+    let conn = SqliteConnection::establish(path.to_str().unwrap()).unwrap();
+    for m in migrations {
+        conn.batch_execute(&std::fs::read_to_string(m.join("up.sql")).unwrap())
+            .unwrap();
+    }
+
+    (Database::from_url(path.to_str().unwrap()), path)
+}
+
+#[tokio::test]
+async fn same_url_on_two_platforms() {
+    let (db, path) = test_db("two-platforms");
+
+    db.add_vahti_entry(URL, USER, crate::tori::ID, DISCORD, None, None)
+        .await
+        .unwrap();
+    db.add_vahti_entry(URL, USER, crate::tori::ID, TELEGRAM, None, None)
+        .await
+        .unwrap();
+    assert!(matches!(
+        db.add_vahti_entry(URL, USER, crate::tori::ID, TELEGRAM, None, None)
+            .await,
+        Err(Error::VahtiExists)
+    ));
+
+    let discord = db.fetch_vahti(URL, USER, DISCORD).await.unwrap();
+    let telegram = db.fetch_vahti(URL, USER, TELEGRAM).await.unwrap();
+    assert_ne!(discord.id, telegram.id);
+    assert_eq!(discord.delivery_method, DISCORD);
+    assert_eq!(telegram.delivery_method, TELEGRAM);
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn updates_do_not_cross_platforms() {
+    let (db, path) = test_db("cross-platform");
+
+    db.add_vahti_entry(URL, USER, crate::tori::ID, DISCORD, None, None)
+        .await
+        .unwrap();
+    db.add_vahti_entry(URL, USER, crate::tori::ID, TELEGRAM, None, None)
+        .await
+        .unwrap();
+
+    let discord = db.fetch_vahti(URL, USER, DISCORD).await.unwrap();
+    let telegram = db.fetch_vahti(URL, USER, TELEGRAM).await.unwrap();
+    db.vahti_updated(discord.clone(), Some(discord.last_updated + 100))
+        .await
+        .unwrap();
+    assert_eq!(
+        db.fetch_vahti(URL, USER, DISCORD)
+            .await
+            .unwrap()
+            .last_updated,
+        discord.last_updated + 100
+    );
+    assert_eq!(
+        db.fetch_vahti(URL, USER, TELEGRAM)
+            .await
+            .unwrap()
+            .last_updated,
+        telegram.last_updated
+    );
+
+    db.update_vahti_filters(URL, USER, TELEGRAM, Some("{}".to_string()))
+        .await
+        .unwrap();
+    assert!(db
+        .fetch_vahti(URL, USER, DISCORD)
+        .await
+        .unwrap()
+        .filters
+        .is_none());
+
+    db.remove_vahti_entry(URL, USER, DISCORD).await.unwrap();
+    assert!(db.fetch_vahti(URL, USER, DISCORD).await.is_err());
+    assert!(db.fetch_vahti(URL, USER, TELEGRAM).await.is_ok());
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn user_lists_do_not_cross_platforms() {
+    let (db, path) = test_db("user-lists");
+
+    db.add_vahti_entry(URL, USER, crate::tori::ID, DISCORD, None, None)
+        .await
+        .unwrap();
+    db.add_seller_to_blacklist(USER, TELEGRAM, 42, crate::tori::ID)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        db.fetch_vahti_entries_by_user_id(USER, DISCORD)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+    assert!(db
+        .fetch_vahti_entries_by_user_id(USER, TELEGRAM)
+        .await
+        .unwrap()
+        .is_empty());
+
+    assert!(db
+        .fetch_user_blacklist(USER, DISCORD)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(db
+        .fetch_shared_blacklist(USER, DISCORD)
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        db.fetch_user_blacklist(USER, TELEGRAM).await.unwrap(),
+        [(42, crate::tori::ID)]
+    );
+
+    db.remove_seller_from_blacklist(USER, DISCORD, 42, crate::tori::ID)
+        .await
+        .unwrap();
+    assert_eq!(
+        db.fetch_shared_blacklist(USER, TELEGRAM).await.unwrap(),
+        [(42, crate::tori::ID)]
+    );
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn creator_is_kept_apart_from_the_chat() {
+    let (db, path) = test_db("creator");
+    let group = -1001234567890;
+
+    db.add_vahti_entry(URL, group, crate::tori::ID, TELEGRAM, None, Some(USER))
+        .await
+        .unwrap();
+
+    let vahti = db.fetch_vahti(URL, group, TELEGRAM).await.unwrap();
+    assert_eq!(vahti.user_id, group);
+    assert_eq!(vahti.creator_id, Some(USER));
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn canonicalization_runs_once() {
+    let (db, path) = test_db("canonicalization");
+    let mobile = "https://m.tori.fi/recommerce/forsale/search?q=thinkpad";
+
+    db.add_vahti_entry(mobile, USER, crate::tori::ID, DISCORD, None, None)
+        .await
+        .unwrap();
+    crate::vahti::canonicalize_vahtis(&db).await.unwrap();
+    assert!(db.fetch_vahti(URL, USER, DISCORD).await.is_ok());
+    assert_eq!(db.fetch_data_version("canonical_urls").await.unwrap(), 1);
+
+    // NOTE: Later runs leave the Vahtis alone, the commands canonicalize the new ones
+    db.add_vahti_entry(mobile, USER, crate::tori::ID, TELEGRAM, None, None)
+        .await
+        .unwrap();
+    crate::vahti::canonicalize_vahtis(&db).await.unwrap();
+    assert!(db.fetch_vahti(mobile, USER, TELEGRAM).await.is_ok());
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn seen_items_survive_restart() {
+    use crate::itemhistory::ItemHistory;
+
+    let (db, path) = test_db("seen-items");
+    db.add_vahti_entry(URL, USER, crate::tori::ID, TELEGRAM, None, None)
+        .await
+        .unwrap();
+    let vahti = db.fetch_vahti(URL, USER, TELEGRAM).await.unwrap();
+    db.add_seen_items(USER, TELEGRAM, vahti.id, &[(1, crate::tori::ID, 80)])
+        .await
+        .unwrap();
+    drop(db);
+
+    let db = Database::from_url(path.to_str().unwrap());
+    let ih = ItemHistory::from_items(db.fetch_seen_items(USER, TELEGRAM).await.unwrap());
+    assert!(ih.contains(1, crate::tori::ID));
+    assert_eq!(ih.price(1, crate::tori::ID), Some(80));
+    assert!(db.fetch_seen_items(USER, DISCORD).await.unwrap().is_empty());
+
+    // NOTE: The items are purged the same way as in `update_all_vahtis`
+    let now = chrono::Local::now().timestamp();
+    db.purge_seen_items(now - *crate::ITEM_HISTORY_RETENTION)
+        .await
+        .unwrap();
+    assert_eq!(db.fetch_seen_items(USER, TELEGRAM).await.unwrap().len(), 1);
+
+    let later = now + *crate::ITEM_HISTORY_RETENTION + 1;
+    db.purge_seen_items(later - *crate::ITEM_HISTORY_RETENTION)
+        .await
+        .unwrap();
+    drop(db);
+
+    let db = Database::from_url(path.to_str().unwrap());
+    assert!(db
+        .fetch_seen_items(USER, TELEGRAM)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let _ = std::fs::remove_file(path);
+}
@@ -0,0 +1,65 @@
+use chrono::{FixedOffset, NaiveTime, TimeZone, Weekday};
+
+use crate::digest::DigestSchedule;
+
+fn time(h: u32, m: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(h, m, 0).unwrap()
+}
+
+#[test]
+fn parse_and_display() {
+    for (s, expected) in [
+        ("hourly", DigestSchedule::Hourly),
+        ("daily@08:00", DigestSchedule::Daily(time(8, 0))),
+        (
+            "mon@18:30",
+            DigestSchedule::Weekly(Weekday::Mon, time(18, 30)),
+        ),
+    ] {
+        let schedule = s.parse::<DigestSchedule>().unwrap();
+        assert_eq!(schedule, expected);
+        assert_eq!(schedule.to_string(), s);
+    }
+
+    assert!("daily".parse::<DigestSchedule>().is_err());
+    assert!("daily@25:00".parse::<DigestSchedule>().is_err());
+    assert!("someday@08:00".parse::<DigestSchedule>().is_err());
+}
+
+#[test]
+fn next_after() {
+    let tz = FixedOffset::east_opt(2 * 3600).unwrap();
+    // Wednesday
+    let now = tz.with_ymd_and_hms(2026, 10, 14, 9, 15, 0).unwrap();
+
+    assert_eq!(
+        DigestSchedule::Hourly.next_after(&now),
+        tz.with_ymd_and_hms(2026, 10, 14, 10, 0, 0).unwrap()
+    );
+    assert_eq!(
+        DigestSchedule::Daily(time(8, 0)).next_after(&now),
+        tz.with_ymd_and_hms(2026, 10, 15, 8, 0, 0).unwrap()
+    );
+    assert_eq!(
+        DigestSchedule::Daily(time(18, 0)).next_after(&now),
+        tz.with_ymd_and_hms(2026, 10, 14, 18, 0, 0).unwrap()
+    );
+    assert_eq!(
+        DigestSchedule::Weekly(Weekday::Mon, time(8, 0)).next_after(&now),
+        tz.with_ymd_and_hms(2026, 10, 19, 8, 0, 0).unwrap()
+    );
+    assert_eq!(
+        DigestSchedule::Weekly(Weekday::Wed, time(8, 0)).next_after(&now),
+        tz.with_ymd_and_hms(2026, 10, 21, 8, 0, 0).unwrap()
+    );
+}
+
+#[test]
+fn is_due() {
+    let tz = FixedOffset::east_opt(0).unwrap();
+    let sent = tz.with_ymd_and_hms(2026, 10, 14, 8, 0, 0).unwrap();
+    let schedule = DigestSchedule::Daily(time(8, 0));
+
+    assert!(!schedule.is_due(&sent, &tz.with_ymd_and_hms(2026, 10, 15, 7, 59, 0).unwrap()));
+    assert!(schedule.is_due(&sent, &tz.with_ymd_and_hms(2026, 10, 15, 8, 0, 0).unwrap()));
+}
@@ -0,0 +1,52 @@
+use serenity::model::permissions::Permissions;
+
+use super::test_item;
+use crate::delivery::discord::{digest_messages, vahti_owner, CHANNEL_ID, ID};
+use crate::vahti::VahtiItem;
+
+#[test]
+fn owner_in_dms() {
+    assert_eq!(vahti_owner(1, 2, false, None), Some((1, ID)));
+}
+
+#[test]
+fn owner_in_guild_channel() {
+    assert_eq!(
+        vahti_owner(1, 2, true, Some(Permissions::MANAGE_CHANNELS)),
+        Some((2, CHANNEL_ID))
+    );
+    assert_eq!(
+        vahti_owner(1, 2, true, Some(Permissions::ADMINISTRATOR)),
+        Some((2, CHANNEL_ID))
+    );
+    assert_eq!(
+        vahti_owner(1, 2, true, Some(Permissions::SEND_MESSAGES)),
+        None
+    );
+    assert_eq!(vahti_owner(1, 2, true, None), None);
+}
+
+#[test]
+fn digest_in_one_message() {
+    let items = |n: usize, title: &str| {
+        (0..n)
+            .map(|i| VahtiItem {
+                title: title.to_string(),
+                ad_id: i as i64,
+                ..test_item()
+            })
+            .collect::<Vec<_>>()
+    };
+    let sizes = |messages: Vec<Vec<_>>| messages.iter().map(Vec::len).collect::<Vec<_>>();
+
+    assert_eq!(sizes(digest_messages(items(25, "Polkupyörä"))), vec![3]);
+    assert_eq!(
+        sizes(digest_messages(items(150, "Polkupyörä"))),
+        vec![10, 5]
+    );
+    // NOTE: Long titles fill the character limit of a message before the embed limit
+    assert_eq!(
+        sizes(digest_messages(items(30, &"a".repeat(250)))),
+        vec![2, 1]
+    );
+}
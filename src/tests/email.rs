@@ -0,0 +1,159 @@
+use std::sync::{Arc, Mutex};
+
+use lettre::{AsyncSmtpTransport, Tokio1Executor};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+use super::test_item;
+use crate::delivery::email::{format_items, items_message, unsubscribe_token, Mailer};
+use crate::models::EmailAddress;
+use crate::settings::UserSettings;
+use crate::vahti::VahtiItem;
+
+/// Starts a local SMTP sink that accepts a single message and records its data
+async fn smtp_sink() -> (u16, Arc<Mutex<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let data = Arc::new(Mutex::new(String::new()));
+
+    let received = data.clone();
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = socket.into_split();
+        let mut lines = BufReader::new(reader).lines();
+
+        writer.write_all(b"220 localhost\r\n").await.unwrap();
+        let mut in_data = false;
+        while let Some(line) = lines.next_line().await.unwrap() {
+            if in_data {
+                if line == "." {
+                    in_data = false;
+                    writer.write_all(b"250 OK\r\n").await.unwrap();
+                } else {
+                    let mut data = received.lock().unwrap();
+                    data.push_str(&line);
+                    data.push('\n');
+                }
+                continue;
+            }
+
+            let response: &[u8] = match line.split_whitespace().next().unwrap_or_default() {
+                "EHLO" | "HELO" => b"250 localhost\r\n",
+                "DATA" => {
+                    in_data = true;
+                    b"354 Go ahead\r\n"
+                }
+                "QUIT" => {
+                    writer.write_all(b"221 Bye\r\n").await.unwrap();
+                    break;
+                }
+                _ => b"250 OK\r\n",
+            };
+            writer.write_all(response).await.unwrap();
+        }
+    });
+
+    (port, data)
+}
+
+fn address() -> EmailAddress {
+    std::env::set_var("EMAIL_PUBLIC_URL", "https://torimies.example.com/");
+    EmailAddress {
+        id: 2,
+        user_id: 1,
+        delivery_method: 1,
+        address: String::from("matti@example.com"),
+        verification_code: String::from("123456"),
+        verified: true,
+        unsubscribe_token: String::from("abc123"),
+    }
+}
+
+fn item(title: &str, price: i64) -> VahtiItem {
+    VahtiItem {
+        deliver_to: Some(2),
+        delivery_method: Some(crate::delivery::email::ID),
+        title: title.to_string(),
+        price,
+        ..test_item()
+    }
+}
+
+#[tokio::test]
+async fn sends_to_smtp_sink() {
+    let (port, data) = smtp_sink().await;
+    let mailer = Mailer {
+        transport: AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous("127.0.0.1")
+            .port(port)
+            .build(),
+        from: "torimies@example.com".parse().unwrap(),
+    };
+
+    let message = items_message(
+        &mailer.from,
+        &address(),
+        &[item("Bike", 100), item("<Helmet>", 20)],
+        &UserSettings::default(),
+        false,
+    )
+    .unwrap();
+    mailer.send(message).await.unwrap();
+
+    let data = data.lock().unwrap();
+    assert!(data.contains("To: matti@example.com"));
+    assert!(data.contains("Subject: 2 uutta ilmoitusta"));
+    assert!(
+        data.contains("List-Unsubscribe: <https://torimies.example.com/unsubscribe?token=abc123>")
+    );
+    assert!(data.contains("multipart/alternative"));
+    assert!(data.contains("Content-Type: text/plain"));
+    assert!(data.contains("Content-Type: text/html"));
+}
+
+#[test]
+fn digest_subject() {
+    let message = items_message(
+        &"torimies@example.com".parse().unwrap(),
+        &address(),
+        &[item("Helmet", 20)],
+        &UserSettings::default(),
+        true,
+    )
+    .unwrap();
+
+    let formatted = String::from_utf8(message.formatted()).unwrap();
+    assert!(formatted.contains("Subject: Kooste: 1 uutta ilmoitusta"));
+}
+
+#[test]
+fn item_bodies() {
+    let mut dropped = item("<Helmet>", 20);
+    dropped.previous_price = Some(30);
+
+    let (html, text) = format_items(&[dropped], &UserSettings::default());
+    assert!(html.contains(r#"<a href="https://www.tori.fi/item/1"><b>&lt;Helmet&gt;</b></a>"#));
+    assert!(html.contains(r#"<img src="https://img.example.com/1.jpg""#));
+    assert!(html.contains("<s>30€</s> 20€"));
+    assert!(html.contains("<b>Myyjä</b>: Matti"));
+    assert!(html.contains("<b>Sijainti</b>: Helsinki"));
+    assert!(html.contains(r#"<a href="https://www.tori.fi/search?q=pyora">Avaa haku</a>"#));
+
+    assert!(text.starts_with("Hinta laski!\n<Helmet>\nhttps://www.tori.fi/item/1\n"));
+    assert!(text.contains("Hinta: 30€ -> 20€"));
+    assert!(text.contains("Haku: https://www.tori.fi/search?q=pyora"));
+}
+
+#[test]
+fn parse_unsubscribe_token() {
+    assert_eq!(
+        unsubscribe_token("GET /unsubscribe?token=abc123 HTTP/1.1"),
+        Some(String::from("abc123"))
+    );
+    assert_eq!(unsubscribe_token("GET /unsubscribe HTTP/1.1"), None);
+    assert_eq!(unsubscribe_token("GET /other?token=abc123 HTTP/1.1"), None);
+    assert_eq!(
+        unsubscribe_token("POST /unsubscribe?token=abc123 HTTP/1.1"),
+        None
+    );
+    assert_eq!(unsubscribe_token(""), None);
+}
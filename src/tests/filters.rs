@@ -0,0 +1,86 @@
+use super::test_item;
+use crate::filters::VahtiFilters;
+use crate::vahti::VahtiItem;
+
+fn item(title: &str, price: i64, ad_type: &str) -> VahtiItem {
+    VahtiItem {
+        title: title.to_string(),
+        price,
+        ad_type: ad_type.to_string(),
+        ..test_item()
+    }
+}
+
+#[test]
+fn empty_filters_match_everything() {
+    let filters = VahtiFilters::default();
+    assert!(filters.is_empty());
+    assert!(filters.matches(&item("Thinkpad T480", 250, "Myydään")));
+}
+
+#[test]
+fn parse_filters() {
+    let filters: VahtiFilters = "min=100 max=500 +thinkpad -rikki regex=t[0-9]{3} type=Myydään"
+        .parse()
+        .unwrap();
+    let expected = VahtiFilters {
+        min_price: Some(100),
+        max_price: Some(500),
+        include: vec!["thinkpad".to_string()],
+        exclude: vec!["rikki".to_string()],
+        title_regex: Some("t[0-9]{3}".to_string()),
+        ad_types: vec!["Myydään".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(filters, expected);
+}
+
+#[test]
+fn parse_invalid_filters() {
+    assert!("min=halpa".parse::<VahtiFilters>().is_err());
+    assert!("min=500 max=100".parse::<VahtiFilters>().is_err());
+    assert!("regex=x2[0-9".parse::<VahtiFilters>().is_err());
+    assert!("foo=bar".parse::<VahtiFilters>().is_err());
+    assert!("thinkpad".parse::<VahtiFilters>().is_err());
+}
+
+#[test]
+fn price_range() {
+    let filters: VahtiFilters = "min=100 max=500".parse().unwrap();
+    assert!(!filters.matches(&item("Thinkpad", 99, "Myydään")));
+    assert!(filters.matches(&item("Thinkpad", 100, "Myydään")));
+    assert!(filters.matches(&item("Thinkpad", 500, "Myydään")));
+    assert!(!filters.matches(&item("Thinkpad", 501, "Myydään")));
+}
+
+#[test]
+fn keywords() {
+    let filters: VahtiFilters = "+thinkpad -rikki".parse().unwrap();
+    assert!(filters.matches(&item("ThinkPad T480", 250, "Myydään")));
+    assert!(!filters.matches(&item("ThinkPad T480 RIKKI", 250, "Myydään")));
+    assert!(!filters.matches(&item("Latitude 7490", 250, "Myydään")));
+}
+
+#[test]
+fn title_regex() {
+    let filters: VahtiFilters = "regex=x2[0-9]0".parse().unwrap();
+    assert!(filters.matches(&item("Thinkpad X220", 100, "Myydään")));
+    assert!(!filters.matches(&item("Thinkpad X1 Carbon", 100, "Myydään")));
+}
+
+#[test]
+fn ad_types() {
+    let filters: VahtiFilters = "type=annetaan".parse().unwrap();
+    assert!(filters.matches(&item("Sohva", 0, "Annetaan")));
+    assert!(!filters.matches(&item("Sohva", 50, "Myydään")));
+}
+
+#[test]
+fn db_roundtrip() {
+    let filters: VahtiFilters = "max=500 -rikki".parse().unwrap();
+    assert_eq!(VahtiFilters::default().to_db(), None);
+    assert_eq!(
+        VahtiFilters::from_db(filters.to_db().as_deref()).unwrap(),
+        filters
+    );
+}
@@ -52,3 +52,62 @@ fn multiquery2() {
     assert_eq!(vahti_to_api(url), expected);
     assert_eq!(vahti_to_api(slash_url), expected);
 }
+
+#[test]
+fn item_id() {
+    use crate::huutonet::api::item_id_from_url;
+    let url = "https://www.huuto.net/kohteet/thinkpad-x220/565432123";
+    assert_eq!(item_id_from_url(url), Some(565432123));
+    assert_eq!(item_id_from_url(&format!("{}/", url)), Some(565432123));
+    assert_eq!(item_id_from_url("https://www.huuto.net/haku"), None);
+}
+
+#[test]
+fn seller_url() {
+    let url = "https://www.huuto.net/kayttaja/123456";
+    let expected = API_BASE.to_owned() + "seller_id=123456&sort=newest";
+    assert_eq!(vahti_to_api(url), expected);
+    assert_eq!(vahti_to_api(&format!("{}/", url)), expected);
+}
+
+#[test]
+fn summary() {
+    use crate::huutonet::api::summarize_url;
+
+    let url = "https://www.huuto.net/haku?words=thinkpad&price_min=10&price_max=200&area=uusimaa";
+    let slash_url =
+        "https://www.huuto.net/haku/words/thinkpad/price_min/10/price_max/200/area/uusimaa";
+    let expected = "Huuto.net: \"thinkpad\", 10–200€, alue: uusimaa";
+    assert_eq!(summarize_url(url).to_string(), expected);
+    assert_eq!(summarize_url(slash_url).to_string(), expected);
+
+    let url = "https://www.huuto.net/haku?words=&area=";
+    assert_eq!(
+        summarize_url(url).to_string(),
+        "Huuto.net: kaikki ilmoitukset"
+    );
+
+    let url = "https://www.huuto.net/kayttaja/123456";
+    assert_eq!(summarize_url(url).to_string(), "Huuto.net: myyjä 123456");
+}
+
+#[test]
+fn canonical() {
+    use crate::huutonet::api::canonical_url;
+
+    let expected =
+        "https://www.huuto.net/haku?area=uusimaa&classification=new&words=th%C3%B6nkp%C3%A4d";
+    for url in [
+        "https://www.huuto.net/haku?words=th%C3%B6nkp%C3%A4d&classification=new&area=uusimaa",
+        "https://huuto.net/haku?area=uusimaa&words=thönkpäd&classification=new&sort=",
+        "https://www.huuto.net/haku/words/th%C3%B6nkp%C3%A4d/classification/new/area/uusimaa/",
+    ] {
+        assert_eq!(canonical_url(url), expected, "{}", url);
+    }
+    assert_eq!(canonical_url(expected), expected);
+
+    assert_eq!(
+        canonical_url("https://huuto.net/kayttaja/123456/"),
+        "https://www.huuto.net/kayttaja/123456"
+    );
+}
@@ -13,6 +13,7 @@ fn basic_parse() {
     let expected = VahtiItem {
         deliver_to: None,
         delivery_method: None,
+        vahti_id: None,
         site_id: crate::huutonet::ID,
         title: "Tekniikan Maailma 20/1993".to_string(),
         vahti_url: None,
@@ -26,6 +27,7 @@ fn basic_parse() {
         location: "SAARENTAUS".to_string(),
         ad_type: "buy-now".to_string(),
         ad_id: 575647318,
+        previous_price: None,
     };
 
     assert_eq!(
@@ -55,6 +57,7 @@ fn parse_multiple() {
             VahtiItem {
         deliver_to: None,
         delivery_method: None,
+        vahti_id: None,
         site_id: 2,
         title: "Lenovo ThinkPad Workstation Dock telakointiasema".to_string(),
         vahti_url: None,
@@ -67,10 +70,12 @@ fn parse_multiple() {
         location: "HELSINKI".to_string(),
         ad_type: "auction".to_string(),
         ad_id: 578236742,
+        previous_price: None,
     },
     VahtiItem {
         deliver_to: None,
         delivery_method: None,
+        vahti_id: None,
         site_id: 2,
         title: "Sierra Wireless AirPrime 4G LTE".to_string(),
         vahti_url: None,
@@ -83,10 +88,12 @@ fn parse_multiple() {
         location: "TAMPERE".to_string(),
         ad_type: "buy-now".to_string(),
         ad_id: 578174408,
+        previous_price: None,
     },
     VahtiItem {
         deliver_to: None,
         delivery_method: None,
+        vahti_id: None,
         site_id: 2,
         title: "Lenovo ThinkPad 65W slim -virtalähde".to_string(),
         vahti_url: None,
@@ -99,10 +106,12 @@ fn parse_multiple() {
         location: "OULU".to_string(),
         ad_type: "auction".to_string(),
         ad_id: 578086441,
+        previous_price: None,
     },
     VahtiItem {
         deliver_to: None,
         delivery_method: None,
+        vahti_id: None,
         site_id: 2,
         title: "Lenovo ThinkPad Quectel SDX24 EM120R-GL WWAN 4G modeemi".to_string(),
         vahti_url: None,
@@ -115,10 +124,12 @@ fn parse_multiple() {
         location: "VANTAA".to_string(),
         ad_type: "buy-now".to_string(),
         ad_id: 578085280,
+        previous_price: None,
     },
     VahtiItem {
         deliver_to: None,
         delivery_method: None,
+        vahti_id: None,
         site_id: 2,
         title: "Thinkpad W541 / P50  170W virtalähde".to_string(),
         vahti_url: None,
@@ -131,10 +142,12 @@ fn parse_multiple() {
         location: "VANTAA".to_string(),
         ad_type: "buy-now".to_string(),
         ad_id: 578082963,
+        previous_price: None,
     },
     VahtiItem {
         deliver_to: None,
         delivery_method: None,
+        vahti_id: None,
         site_id: 2,
         title: "Thinkpad näppäimistö UK".to_string(),
         vahti_url: None,
@@ -147,10 +160,12 @@ fn parse_multiple() {
         location: "HELSINKI".to_string(),
         ad_type: "auction".to_string(),
         ad_id: 578028877,
+        previous_price: None,
     },
     VahtiItem {
         deliver_to: None,
         delivery_method: None,
+        vahti_id: None,
         site_id: 2,
         title: "Lenovo Thinkpad T15 Gen 2 (20W400HGMX)".to_string(),
         vahti_url: None,
@@ -163,10 +178,12 @@ fn parse_multiple() {
         location: "JUUKA".to_string(),
         ad_type: "auction".to_string(),
         ad_id: 578026882,
+        previous_price: None,
     },
     VahtiItem {
         deliver_to: None,
         delivery_method: None,
+        vahti_id: None,
         site_id: 2,
         title: "ThinkPad Thunderbolt 3 Workstation Dock Gen 1 + 230W sekä 65".to_string(),
         vahti_url: None,
@@ -179,10 +196,12 @@ fn parse_multiple() {
         location: "VANTAA".to_string(),
         ad_type: "buy-now".to_string(),
         ad_id: 578013764,
+        previous_price: None,
     },
     VahtiItem {
         deliver_to: None,
         delivery_method: None,
+        vahti_id: None,
         site_id: 2,
         title: "Lenovo Thinkpad kosketuslevy E440 L440 T440 W540".to_string(),
         vahti_url: None,
@@ -195,10 +214,12 @@ fn parse_multiple() {
         location: "TAMPERE".to_string(),
         ad_type: "buy-now".to_string(),
         ad_id: 577859186,
+        previous_price: None,
     },
     VahtiItem {
         deliver_to: None,
         delivery_method: None,
+        vahti_id: None,
         site_id: 2,
         title: "16 GB DDR4 2666V SO-DIMM muistia".to_string(),
         vahti_url: None,
@@ -211,10 +232,12 @@ fn parse_multiple() {
         location: "TAMPERE".to_string(),
         ad_type: "buy-now".to_string(),
         ad_id: 577756539,
+        previous_price: None,
     },
     VahtiItem {
         deliver_to: None,
         delivery_method: None,
+        vahti_id: None,
         site_id: 2,
         title: "Lenovo ThinkPad laturi 135W".to_string(),
         vahti_url: None,
@@ -227,10 +250,12 @@ fn parse_multiple() {
         location: "TAMPERE".to_string(),
         ad_type: "buy-now".to_string(),
         ad_id: 577619805,
+        previous_price: None,
     },
     VahtiItem {
         deliver_to: None,
         delivery_method: None,
+        vahti_id: None,
         site_id: 2,
         title: "Lenovo Thinkpad X270 M.2 levykelkka + NVMe SSD levy".to_string(),
         vahti_url: None,
@@ -243,10 +268,12 @@ fn parse_multiple() {
         location: "TAMPERE".to_string(),
         ad_type: "buy-now".to_string(),
         ad_id: 577619781,
+        previous_price: None,
     },
     VahtiItem {
         deliver_to: None,
         delivery_method: None,
+        vahti_id: None,
         site_id: 2,
         title: "Lenovo Thinkpad T470 M.2 levykelkka + NVMe SSD levy".to_string(),
         vahti_url: None,
@@ -259,10 +286,12 @@ fn parse_multiple() {
         location: "TAMPERE".to_string(),
         ad_type: "buy-now".to_string(),
         ad_id: 577619757,
+        previous_price: None,
     },
     VahtiItem {
         deliver_to: None,
         delivery_method: None,
+        vahti_id: None,
         site_id: 2,
         title: "Lenovo ThinkPad läppärilaukku 15.6\"".to_string(),
         vahti_url: None,
@@ -275,10 +304,12 @@ fn parse_multiple() {
         location: "HELSINKI".to_string(),
         ad_type: "buy-now".to_string(),
         ad_id: 577600532,
+        previous_price: None,
     },
     VahtiItem {
         deliver_to: None,
         delivery_method: None,
+        vahti_id: None,
         site_id: 2,
         title: "Lenovo ThinkPad advanced minidock telakka ja laturi".to_string(),
         vahti_url: None,
@@ -291,10 +322,12 @@ fn parse_multiple() {
         location: "TAMPERE".to_string(),
         ad_type: "buy-now".to_string(),
         ad_id: 577519756,
+        previous_price: None,
     },
     VahtiItem {
         deliver_to: None,
         delivery_method: None,
+        vahti_id: None,
         site_id: 2,
         title: "Lenovo ThinkPad T540p, 15.5\" 3K (2880 x 1620), IPS".to_string(),
         vahti_url: None,
@@ -307,10 +340,12 @@ fn parse_multiple() {
         location: "TAMPERE".to_string(),
         ad_type: "buy-now".to_string(),
         ad_id: 576827868,
+        previous_price: None,
     },
     VahtiItem {
         deliver_to: None,
         delivery_method: None,
+        vahti_id: None,
         site_id: 2,
         title: "IBM Thinkpad X20 + 2 kpl telakka".to_string(),
         vahti_url: None,
@@ -323,10 +358,12 @@ fn parse_multiple() {
         location: "TAMPERE".to_string(),
         ad_type: "buy-now".to_string(),
         ad_id: 576564494,
+        previous_price: None,
     },
     VahtiItem {
         deliver_to: None,
         delivery_method: None,
+        vahti_id: None,
         site_id: 2,
         title: "IBM Thinkpad T43 + laturi + telakka +  Win XP Pro".to_string(),
         vahti_url: None,
@@ -339,10 +376,12 @@ fn parse_multiple() {
         location: "TAMPERE".to_string(),
         ad_type: "buy-now".to_string(),
         ad_id: 576378051,
+        previous_price: None,
     },
     VahtiItem {
         deliver_to: None,
         delivery_method: None,
+        vahti_id: None,
         site_id: 2,
         title: "512MB PC100 SODIMM".to_string(),
         vahti_url: None,
@@ -355,6 +394,7 @@ fn parse_multiple() {
         location: "IMATRA".to_string(),
         ad_type: "buy-now".to_string(),
         ad_id: 573442000,
+        previous_price: None,
     },
     ];
 
@@ -369,3 +409,15 @@ fn parse_multiple() {
         .map(|(a, b)| assert_eq!(a, b))
         .collect::<Vec<_>>();
 }
+
+#[test]
+fn match_count() {
+    let mut file =
+        File::open("testdata/huutonet/parse_multiple.json").expect("Test data not found");
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).unwrap();
+
+    assert_eq!(crate::huutonet::api::match_count(&contents), Some(19));
+    assert_eq!(crate::huutonet::api::match_count("{}"), None);
+    assert_eq!(crate::huutonet::api::match_count("<html></html>"), None);
+}
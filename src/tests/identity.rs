@@ -0,0 +1,177 @@
+use super::database::test_db;
+use crate::error::Error;
+use crate::identity::{
+    create_pairing_code, linked_vahti_owner, pair, parse_endpoints, set_vahti_endpoints, unlink,
+};
+
+const URL: &str = "https://www.tori.fi/recommerce/forsale/search?q=thinkpad";
+const DISCORD: i32 = crate::delivery::discord::ID;
+const TELEGRAM: i32 = crate::delivery::telegram::ID;
+const DISCORD_USER: u64 = 123456789;
+const TELEGRAM_USER: u64 = 987654321;
+
+fn code_from(message: &str) -> String {
+    message
+        .split_whitespace()
+        .nth(4)
+        .unwrap()
+        .trim_end_matches('.')
+        .to_string()
+}
+
+#[test]
+fn endpoints() {
+    let linked = [(1, DISCORD), (2, TELEGRAM)];
+    assert_eq!(
+        parse_endpoints(&linked, "telegram, discord").unwrap(),
+        vec![(2, TELEGRAM), (1, DISCORD)]
+    );
+    assert_eq!(
+        parse_endpoints(&linked, "telegram,telegram").unwrap(),
+        vec![(2, TELEGRAM)]
+    );
+    assert!(parse_endpoints(&linked, "matrix").is_err());
+    assert!(parse_endpoints(&linked, "").is_err());
+}
+
+#[tokio::test]
+async fn link_accounts() {
+    let (db, path) = test_db("link");
+
+    let code = code_from(
+        &create_pairing_code(&db, DISCORD_USER, DISCORD)
+            .await
+            .unwrap(),
+    );
+    assert!(matches!(
+        pair(&db, &code, DISCORD_USER, DISCORD).await,
+        Err(Error::InvalidPairingCode)
+    ));
+
+    // NOTE: A code can only be used once
+    let code = code_from(
+        &create_pairing_code(&db, DISCORD_USER, DISCORD)
+            .await
+            .unwrap(),
+    );
+    pair(&db, &code.to_lowercase(), TELEGRAM_USER, TELEGRAM)
+        .await
+        .unwrap();
+    assert!(matches!(
+        pair(&db, &code, TELEGRAM_USER, TELEGRAM).await,
+        Err(Error::InvalidPairingCode)
+    ));
+
+    let linked = db
+        .fetch_linked_endpoints(TELEGRAM_USER as i64, TELEGRAM)
+        .await
+        .unwrap();
+    assert_eq!(
+        linked,
+        vec![
+            (DISCORD_USER as i64, DISCORD),
+            (TELEGRAM_USER as i64, TELEGRAM)
+        ]
+    );
+
+    // NOTE: The blacklist is shared by the linked accounts
+    db.add_seller_to_blacklist(DISCORD_USER as i64, DISCORD, 42, crate::tori::ID)
+        .await
+        .unwrap();
+    // NOTE: The same id on another delivery method is a different endpoint
+    db.add_seller_to_blacklist(DISCORD_USER as i64, TELEGRAM, 43, crate::tori::ID)
+        .await
+        .unwrap();
+    assert_eq!(
+        db.fetch_shared_blacklist(TELEGRAM_USER as i64, TELEGRAM)
+            .await
+            .unwrap(),
+        vec![(42, crate::tori::ID)]
+    );
+
+    unlink(&db, TELEGRAM_USER, TELEGRAM).await.unwrap();
+    assert_eq!(
+        db.fetch_linked_endpoints(TELEGRAM_USER as i64, TELEGRAM)
+            .await
+            .unwrap(),
+        vec![(TELEGRAM_USER as i64, TELEGRAM)]
+    );
+    assert!(db
+        .fetch_shared_blacklist(TELEGRAM_USER as i64, TELEGRAM)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn vahti_destinations() {
+    let (db, path) = test_db("destinations");
+
+    let code = code_from(
+        &create_pairing_code(&db, TELEGRAM_USER, TELEGRAM)
+            .await
+            .unwrap(),
+    );
+    pair(&db, &code, DISCORD_USER, DISCORD).await.unwrap();
+
+    db.add_vahti_entry(
+        URL,
+        TELEGRAM_USER as i64,
+        crate::tori::ID,
+        TELEGRAM,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    let vahti = db
+        .fetch_vahti(URL, TELEGRAM_USER as i64, TELEGRAM)
+        .await
+        .unwrap();
+
+    assert!(
+        set_vahti_endpoints(db.clone(), URL, TELEGRAM_USER, TELEGRAM, "matrix")
+            .await
+            .is_err()
+    );
+    set_vahti_endpoints(db.clone(), URL, TELEGRAM_USER, TELEGRAM, "discord,telegram")
+        .await
+        .unwrap();
+    assert_eq!(
+        db.fetch_vahti_destinations(vahti.id).await.unwrap(),
+        vec![
+            (DISCORD_USER as i64, DISCORD),
+            (TELEGRAM_USER as i64, TELEGRAM)
+        ]
+    );
+
+    // NOTE: The Vahti is found from the linked account
+    assert_eq!(
+        linked_vahti_owner(&db, URL, DISCORD_USER, DISCORD).await,
+        (TELEGRAM_USER, TELEGRAM)
+    );
+
+    // NOTE: Delivering only to the owner needs no destinations
+    set_vahti_endpoints(db.clone(), URL, TELEGRAM_USER, TELEGRAM, "telegram")
+        .await
+        .unwrap();
+    assert!(db
+        .fetch_vahti_destinations(vahti.id)
+        .await
+        .unwrap()
+        .is_empty());
+
+    set_vahti_endpoints(db.clone(), URL, TELEGRAM_USER, TELEGRAM, "discord")
+        .await
+        .unwrap();
+    unlink(&db, DISCORD_USER, DISCORD).await.unwrap();
+    assert!(db
+        .fetch_vahti_destinations(vahti.id)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let _ = std::fs::remove_file(path);
+}
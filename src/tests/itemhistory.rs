@@ -0,0 +1,55 @@
+use crate::itemhistory::ItemHistory;
+use crate::vahti::is_price_drop;
+
+#[test]
+fn price_drop_thresholds() {
+    // Defaults: 10% or 20€
+    assert!(is_price_drop(100, 90));
+    assert!(!is_price_drop(100, 95));
+    assert!(is_price_drop(1000, 980));
+    assert!(!is_price_drop(1000, 990));
+    assert!(!is_price_drop(100, 100));
+    assert!(!is_price_drop(100, 150));
+    // Unknown old price
+    assert!(!is_price_drop(0, 0));
+}
+
+#[test]
+fn last_seen_price() {
+    let now = chrono::Local::now().timestamp();
+    let mut ih = ItemHistory::from_items(vec![(1, crate::tori::ID, now, 100)]);
+    assert_eq!(ih.price(1, crate::tori::ID), Some(100));
+    assert_eq!(ih.price(1, crate::huutonet::ID), None);
+
+    ih.set_price(1, crate::tori::ID, 80);
+    assert_eq!(ih.price(1, crate::tori::ID), Some(80));
+}
+
+#[test]
+fn extend_keeps_own_prices() {
+    let now = chrono::Local::now().timestamp();
+    let stale = ItemHistory::from_items(vec![(1, crate::tori::ID, now, 100)]);
+    let mut ih = stale.clone();
+    ih.set_price(1, crate::tori::ID, 80);
+    ih.add_item(2, crate::tori::ID, now, 50);
+
+    ih.extend(&stale);
+    assert_eq!(ih.price(1, crate::tori::ID), Some(80));
+    assert_eq!(ih.price(2, crate::tori::ID), Some(50));
+}
+
+#[test]
+fn old_items_are_purged() {
+    let now = chrono::Local::now().timestamp();
+    let ih = ItemHistory::from_items(vec![
+        (1, crate::tori::ID, now, 100),
+        (
+            2,
+            crate::tori::ID,
+            now - *crate::ITEM_HISTORY_RETENTION - 1,
+            100,
+        ),
+    ]);
+    assert!(ih.contains(1, crate::tori::ID));
+    assert!(!ih.contains(2, crate::tori::ID));
+}
@@ -0,0 +1,99 @@
+use serde_json::json;
+
+use super::{serve, test_item};
+use crate::matrix::{invites_from_sync, messages_from_sync, MatrixClient, RoomMessage};
+use crate::settings::UserSettings;
+use crate::vahti::VahtiItem;
+
+#[test]
+fn parse_sync() {
+    let sync = json!({
+        "next_batch": "s2",
+        "rooms": {
+            "join": {
+                "!room:example.org": {
+                    "timeline": {
+                        "events": [
+                            {
+                                "type": "m.room.message",
+                                "sender": "@matti:example.org",
+                                "content": { "msgtype": "m.text", "body": "!vahti https://www.tori.fi/search" }
+                            },
+                            {
+                                "type": "m.room.message",
+                                "sender": "@matti:example.org",
+                                "content": { "msgtype": "m.image", "body": "kuva.jpg" }
+                            },
+                            {
+                                "type": "m.room.member",
+                                "sender": "@matti:example.org",
+                                "content": { "membership": "join" }
+                            }
+                        ]
+                    }
+                }
+            },
+            "invite": {
+                "!other:example.org": { "invite_state": { "events": [] } }
+            }
+        }
+    });
+
+    assert_eq!(
+        messages_from_sync(&sync),
+        vec![RoomMessage {
+            room_id: String::from("!room:example.org"),
+            sender: String::from("@matti:example.org"),
+            body: String::from("!vahti https://www.tori.fi/search"),
+        }]
+    );
+    assert_eq!(
+        invites_from_sync(&sync),
+        vec![String::from("!other:example.org")]
+    );
+    assert!(messages_from_sync(&json!({ "next_batch": "s1" })).is_empty());
+}
+
+#[test]
+fn format_item() {
+    let item = VahtiItem {
+        delivery_method: Some(crate::delivery::matrix::ID),
+        title: String::from("<Polkupyörä>"),
+        seller_name: String::new(),
+        previous_price: Some(100),
+        ..test_item()
+    };
+
+    let (body, html) = item.format_matrix(&UserSettings::default(), Some("mxc://example.org/abc"));
+    assert!(body.starts_with("Hinta laski!\n<Polkupyörä>\nhttps://www.tori.fi/item/1\n"));
+    assert!(body.contains("Hinta: 100€ -> 80€"));
+    assert!(body.contains("Myyjä: Tuntematon"));
+    assert!(html.contains(r#"<img src="mxc://example.org/abc""#));
+    assert!(html.contains(r#"<a href="https://www.tori.fi/item/1">&lt;Polkupyörä&gt;</a>"#));
+    assert!(html.contains("<b>Hinta</b>: <del>100€</del> 80€"));
+    assert!(html.contains(r#"<a href="https://www.tori.fi/search?q=pyora">Avaa Hakusivu</a>"#));
+
+    let (_, html) = item.format_matrix(&UserSettings::default(), None);
+    assert!(!html.contains("<img"));
+}
+
+#[tokio::test]
+async fn send_message_request() {
+    let (homeserver, received) = serve(vec![(200, r#"{"event_id":"$abc"}"#)]).await;
+
+    let client = MatrixClient::new(&homeserver, "token").unwrap();
+    client
+        .send_message("!room:example.org", "moi", "<b>moi</b>")
+        .await
+        .unwrap();
+
+    let request = received.lock().unwrap()[0].clone();
+    assert!(request.headers.starts_with(
+        "put /_matrix/client/v3/rooms/!room:example.org/send/m.room.message/torimies-"
+    ));
+    assert!(request.headers.contains("authorization: bearer token"));
+    assert!(request.body.contains(r#""formatted_body":"<b>moi</b>""#));
+    assert!(request
+        .body
+        .contains(r#""format":"org.matrix.custom.html""#));
+}
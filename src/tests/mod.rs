@@ -1,2 +1,140 @@
+mod blacklist;
+#[cfg(all(
+    feature = "discord-delivery",
+    feature = "telegram-delivery",
+    feature = "tori"
+))]
+mod database;
+mod digest;
+#[cfg(feature = "discord-delivery")]
+mod discord;
+#[cfg(feature = "email-delivery")]
+mod email;
+mod filters;
 pub mod huutonet;
+#[cfg(all(
+    feature = "discord-delivery",
+    feature = "telegram-delivery",
+    feature = "tori"
+))]
+mod identity;
+mod itemhistory;
+#[cfg(feature = "matrix-delivery")]
+mod matrix;
+#[cfg(all(
+    feature = "discord-delivery",
+    feature = "telegram-delivery",
+    feature = "tori"
+))]
+mod outbox;
+#[cfg(feature = "push-delivery")]
+mod push;
+mod settings;
+#[cfg(feature = "telegram-delivery")]
+mod telegram;
 pub mod tori;
+#[cfg(all(
+    feature = "discord-delivery",
+    feature = "telegram-delivery",
+    feature = "tori"
+))]
+mod unreachable;
+#[cfg(all(
+    feature = "discord-delivery",
+    feature = "telegram-delivery",
+    feature = "tori"
+))]
+mod update;
+mod vahti;
+#[cfg(feature = "webhook-delivery")]
+mod webhook;
+
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use crate::vahti::VahtiItem;
+
+/// A listing for the tests, override the fields that matter with the struct update syntax
+pub fn test_item() -> VahtiItem {
+    VahtiItem {
+        deliver_to: Some(1),
+        delivery_method: None,
+        vahti_id: None,
+        site_id: crate::tori::ID,
+        title: String::from("Polkupyörä"),
+        vahti_url: Some(String::from("https://www.tori.fi/search?q=pyora")),
+        url: String::from("https://www.tori.fi/item/1"),
+        img_url: String::from("https://img.example.com/1.jpg"),
+        published: 1792300000,
+        price: 80,
+        seller_name: String::from("Matti"),
+        seller_id: 0,
+        location: String::from("Helsinki"),
+        ad_type: String::from("Myydään"),
+        ad_id: 1,
+        previous_price: None,
+    }
+}
+
+/// A raw HTTP request received by the test server
+#[derive(Debug, Clone)]
+pub struct Request {
+    /// The request line and the headers in lowercase
+    pub headers: String,
+    pub body: String,
+}
+
+/// Starts a local HTTP server that answers the requests with the given statuses and
+/// bodies in order and records the received requests
+pub async fn serve(responses: Vec<(u16, &'static str)>) -> (String, Arc<Mutex<Vec<Request>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(vec![]));
+
+    let received = requests.clone();
+    tokio::spawn(async move {
+        for (status, body) in responses {
+            let (mut socket, _) = listener.accept().await.unwrap();
+
+            let mut buf = vec![];
+            let (headers, request_body) = loop {
+                let mut chunk = [0; 4096];
+                let n = socket.read(&mut chunk).await.unwrap();
+                buf.extend_from_slice(&chunk[..n]);
+
+                let raw = String::from_utf8_lossy(&buf).to_string();
+                let Some((headers, body)) = raw.split_once("\r\n\r\n") else {
+                    continue;
+                };
+                let length = headers
+                    .lines()
+                    .find_map(|l| {
+                        l.to_lowercase()
+                            .strip_prefix("content-length:")
+                            .map(|v| v.trim().parse::<usize>().unwrap())
+                    })
+                    .unwrap_or(0);
+                if body.len() >= length {
+                    break (headers.to_lowercase(), body.to_string());
+                }
+            };
+            received.lock().unwrap().push(Request {
+                headers,
+                body: request_body,
+            });
+
+            let response = format!(
+                "HTTP/1.1 {} Status\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            socket.shutdown().await.unwrap();
+        }
+    });
+
+    (url, requests)
+}
@@ -0,0 +1,238 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use teloxide::{ApiError, RequestError};
+
+use super::database::test_db;
+use super::test_item;
+use crate::delivery::{perform_delivery, perform_digest_delivery, Delivery, Notification};
+use crate::error::Error;
+use crate::outbox::next_attempt;
+use crate::vahti::VahtiItem;
+use crate::Torimies;
+
+const USER: i64 = 123456789;
+const TELEGRAM: i32 = crate::delivery::telegram::ID;
+
+fn item(ad_id: i64) -> VahtiItem {
+    VahtiItem {
+        deliver_to: Some(USER as u64),
+        delivery_method: Some(TELEGRAM),
+        vahti_id: Some(1),
+        title: format!("Thinkpad {}", ad_id),
+        url: format!("https://www.tori.fi/recommerce/forsale/item/{}", ad_id),
+        price: 100,
+        ad_id,
+        ..test_item()
+    }
+}
+
+#[test]
+fn backoff() {
+    let now = 1_800_000_000;
+    assert_eq!(next_attempt(1, now), Some(now + 30));
+    assert_eq!(next_attempt(2, now), Some(now + 60));
+    assert_eq!(next_attempt(5, now), Some(now + 480));
+    assert_eq!(next_attempt(10, now), Some(now + 3600));
+    assert_eq!(
+        next_attempt(*crate::OUTBOX_MAX_ATTEMPTS - 1, now),
+        Some(now + 3600)
+    );
+    assert_eq!(next_attempt(*crate::OUTBOX_MAX_ATTEMPTS, now), None);
+}
+
+#[test]
+fn permanent_errors() {
+    assert!(Error::Telegram(RequestError::Api(ApiError::BotBlocked)).is_permanent());
+    assert!(Error::Telegram(RequestError::Api(ApiError::ChatNotFound)).is_permanent());
+    assert!(!Error::Telegram(RequestError::MigrateToChatId(-100)).is_permanent());
+    assert!(
+        !Error::Telegram(RequestError::RetryAfter(std::time::Duration::from_secs(5)))
+            .is_permanent()
+    );
+    assert!(Error::Telegram(RequestError::Api(ApiError::CantParseEntities)).is_permanent());
+    assert!(
+        Error::Telegram(RequestError::Api(ApiError::Unknown(String::from(
+            "Bad Request: can't parse entities: Unsupported start tag"
+        ))))
+        .is_permanent()
+    );
+    assert!(Error::Telegram(RequestError::Api(ApiError::MessageIsTooLong)).is_permanent());
+    assert!(Error::Database(diesel::result::Error::NotFound).is_permanent());
+    assert!(Error::InvalidDestination(String::from("webhook:3")).is_permanent());
+    assert!(!Error::Tori.is_permanent());
+}
+
+#[tokio::test]
+async fn outbox_entries() {
+    let (db, path) = test_db("outbox");
+    let now = chrono::Local::now().timestamp();
+
+    perform_delivery(&db, vec![item(1)]).await.unwrap();
+    perform_delivery(&db, vec![item(3)]).await.unwrap();
+    perform_delivery(&db, vec![]).await.unwrap();
+
+    let due = db.fetch_due_outbox_entries(now + 1, 10).await.unwrap();
+    assert_eq!(due.len(), 2);
+    assert_eq!((due[0].user_id, due[0].delivery_method), (USER, TELEGRAM));
+    let items = serde_json::from_str::<Vec<VahtiItem>>(&due[0].items).unwrap();
+    assert_eq!(items.iter().map(|i| i.ad_id).collect::<Vec<_>>(), [1]);
+    assert_eq!(db.outbox_stats().await.unwrap().0, 2);
+
+    db.reschedule_outbox_entry(due[0].id, now + 30, "timeout")
+        .await
+        .unwrap();
+    let due_now = db.fetch_due_outbox_entries(now + 1, 10).await.unwrap();
+    assert_eq!(due_now.len(), 1);
+    assert_eq!(due_now[0].id, due[1].id);
+    let retried = db.fetch_due_outbox_entries(now + 30, 10).await.unwrap();
+    assert_eq!(retried[0].attempts, 1);
+    assert_eq!(retried[0].last_error.as_deref(), Some("timeout"));
+
+    db.fail_outbox_entry(due[1].id, "bot blocked")
+        .await
+        .unwrap();
+    db.remove_outbox_entry(due[0].id).await.unwrap();
+    assert!(db
+        .fetch_due_outbox_entries(now + 3600, 10)
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(db.outbox_stats().await.unwrap(), (0, 1, None));
+
+    db.purge_failed_outbox_entries(now + 1).await.unwrap();
+    assert_eq!(db.outbox_stats().await.unwrap(), (0, 0, None));
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn entry_per_message() {
+    let (db, path) = test_db("outbox_messages");
+    let now = chrono::Local::now().timestamp();
+    let ad_ids = |entries: Vec<crate::models::OutboxEntry>| {
+        entries
+            .iter()
+            .map(|e| {
+                serde_json::from_str::<Vec<VahtiItem>>(&e.items)
+                    .unwrap()
+                    .iter()
+                    .map(|i| i.ad_id)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    perform_delivery(&db, (1..=3).map(item).collect())
+        .await
+        .unwrap();
+    let due = db.fetch_due_outbox_entries(now + 1, 10).await.unwrap();
+    assert_eq!(ad_ids(due), [[1], [2], [3]]);
+
+    let discord = (1..=7)
+        .map(|i| VahtiItem {
+            delivery_method: Some(crate::delivery::discord::ID),
+            deliver_to: Some(1),
+            ..item(i)
+        })
+        .collect();
+    perform_delivery(&db, discord).await.unwrap();
+    let due = db.fetch_due_outbox_entries(now + 1, 10).await.unwrap();
+    assert_eq!(ad_ids(due[3..].to_vec()), [vec![1, 2, 3, 4, 5], vec![6, 7]]);
+
+    let _ = std::fs::remove_file(path);
+}
+
+/// Rejects the item 1 for good, fails the item 3 for now and delivers the rest.
+/// Digests are recorded as a whole
+#[derive(Default)]
+struct Stub {
+    delivered: Arc<Mutex<Vec<i64>>>,
+    digests: Arc<Mutex<Vec<Vec<i64>>>>,
+}
+
+#[async_trait]
+impl Delivery for Stub {
+    async fn deliver(&self, vs: Vec<VahtiItem>) -> Result<(), Error> {
+        for i in vs {
+            match i.ad_id {
+                1 => {
+                    return Err(Error::Telegram(RequestError::Api(
+                        ApiError::CantParseEntities,
+                    )))
+                }
+                3 => return Err(Error::Tori),
+                id => self.delivered.lock().unwrap().push(id),
+            }
+        }
+        Ok(())
+    }
+
+    async fn deliver_digest(&self, vs: Vec<VahtiItem>) -> Result<(), Error> {
+        self.digests
+            .lock()
+            .unwrap()
+            .push(vs.iter().map(|i| i.ad_id).collect());
+        Ok(())
+    }
+
+    async fn notify(&self, _: u64, _: Notification) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn permanent_failure_doesnt_block() {
+    let (db, path) = test_db("outbox_permanent");
+    let delivered = Arc::new(Mutex::new(vec![]));
+    let man = Torimies::new(db.clone());
+    man.delivery.insert(
+        TELEGRAM,
+        Box::new(Stub {
+            delivered: delivered.clone(),
+            ..Default::default()
+        }),
+    );
+
+    perform_delivery(&db, (1..=4).map(item).collect())
+        .await
+        .unwrap();
+    man.drain_outbox().await.unwrap();
+
+    // NOTE: The entries after the retried one wait for it to keep the order
+    assert_eq!(*delivered.lock().unwrap(), [2]);
+    let (pending, failed, _) = db.outbox_stats().await.unwrap();
+    assert_eq!((pending, failed), (2, 1));
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn digest_entry() {
+    let (db, path) = test_db("outbox_digest");
+    let digests = Arc::new(Mutex::new(vec![]));
+    let man = Torimies::new(db.clone());
+    man.delivery.insert(
+        TELEGRAM,
+        Box::new(Stub {
+            digests: digests.clone(),
+            ..Default::default()
+        }),
+    );
+
+    perform_digest_delivery(&db, (1..=3).map(item).collect())
+        .await
+        .unwrap();
+    let due = db
+        .fetch_due_outbox_entries(chrono::Local::now().timestamp() + 1, 10)
+        .await
+        .unwrap();
+    assert_eq!(due.len(), 1);
+    assert!(due[0].digest);
+
+    man.drain_outbox().await.unwrap();
+    assert_eq!(*digests.lock().unwrap(), [vec![1, 2, 3]]);
+    assert_eq!(db.outbox_stats().await.unwrap(), (0, 0, None));
+
+    let _ = std::fs::remove_file(path);
+}
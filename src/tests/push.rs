@@ -0,0 +1,117 @@
+use reqwest::Client;
+use serde_json::json;
+
+use super::{serve, test_item};
+use crate::delivery::push::{gotify_body, ntfy_body, publish, PushMessage, PushService};
+use crate::models::PushTarget;
+use crate::vahti::VahtiItem;
+
+fn item() -> VahtiItem {
+    VahtiItem {
+        delivery_method: Some(crate::delivery::push::ID),
+        ..test_item()
+    }
+}
+
+#[test]
+fn parse_service() {
+    assert_eq!("ntfy".parse::<PushService>().unwrap(), PushService::Ntfy);
+    assert_eq!(
+        "Gotify".parse::<PushService>().unwrap(),
+        PushService::Gotify
+    );
+    assert!("pushover".parse::<PushService>().is_err());
+}
+
+#[test]
+fn message_bodies() {
+    let message = PushMessage::from(&item());
+    assert_eq!(
+        message,
+        PushMessage {
+            title: String::from("Polkupyörä"),
+            message: String::from("Hinta: 80€\nSijainti: Helsinki"),
+            click: Some(String::from("https://www.tori.fi/item/1")),
+            image: Some(String::from("https://img.example.com/1.jpg")),
+        }
+    );
+
+    assert_eq!(
+        ntfy_body("torimies", &message),
+        json!({
+            "topic": "torimies",
+            "title": "Polkupyörä",
+            "message": "Hinta: 80€\nSijainti: Helsinki",
+            "click": "https://www.tori.fi/item/1",
+            "attach": "https://img.example.com/1.jpg",
+        })
+    );
+    assert_eq!(
+        gotify_body(&message),
+        json!({
+            "title": "Polkupyörä",
+            "message": "Hinta: 80€\nSijainti: Helsinki",
+            "extras": {
+                "client::display": { "contentType": "text/plain" },
+                "client::notification": {
+                    "click": { "url": "https://www.tori.fi/item/1" },
+                    "bigImageUrl": "https://img.example.com/1.jpg",
+                },
+            },
+        })
+    );
+
+    let mut no_image = item();
+    no_image.img_url = String::new();
+    assert!(ntfy_body("torimies", &PushMessage::from(&no_image))
+        .get("attach")
+        .is_none());
+}
+
+#[tokio::test]
+async fn publish_ntfy() {
+    let (server, received) = serve(vec![(200, "{}")]).await;
+    let target = PushTarget {
+        id: 1,
+        user_id: 1,
+        delivery_method: 1,
+        service: String::from("ntfy"),
+        server: format!("{}/", server),
+        topic: Some(String::from("torimies")),
+        token: Some(String::from("tk_secret")),
+    };
+
+    publish(&Client::new(), &target, &PushMessage::from(&item()))
+        .await
+        .unwrap();
+
+    let request = received.lock().unwrap()[0].clone();
+    assert!(request.headers.starts_with("post / http/1.1"));
+    assert!(request.headers.contains("authorization: bearer tk_secret"));
+    assert!(request.body.contains(r#""topic":"torimies""#));
+}
+
+#[tokio::test]
+async fn publish_gotify() {
+    let (server, received) = serve(vec![(200, "{}")]).await;
+    let target = PushTarget {
+        id: 1,
+        user_id: 1,
+        delivery_method: 1,
+        service: String::from("gotify"),
+        server,
+        topic: None,
+        token: Some(String::from("app_token")),
+    };
+
+    publish(&Client::new(), &target, &PushMessage::from(&item()))
+        .await
+        .unwrap();
+
+    let request = received.lock().unwrap()[0].clone();
+    assert!(request.headers.starts_with("post /message http/1.1"));
+    assert!(request.headers.contains("x-gotify-key: app_token"));
+    assert!(request
+        .body
+        .contains(r#""bigImageUrl":"https://img.example.com/1.jpg""#));
+}
@@ -0,0 +1,67 @@
+use chrono::{NaiveTime, TimeZone};
+use chrono_tz::Europe::Helsinki;
+
+use crate::settings::{parse_quiet_hours, parse_timezone, UserSettings};
+
+fn time(h: u32, m: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(h, m, 0).unwrap()
+}
+
+#[test]
+fn parse() {
+    assert_eq!(parse_timezone("Europe/Helsinki").unwrap(), Helsinki);
+    assert!(parse_timezone("Europe/Tampere").is_err());
+    assert_eq!(
+        parse_quiet_hours("22:00-07:30").unwrap(),
+        (time(22, 0), time(7, 30))
+    );
+    assert!(parse_quiet_hours("22:00").is_err());
+    assert!(parse_quiet_hours("22:00-31:00").is_err());
+}
+
+#[test]
+fn quiet_hours_over_midnight() {
+    let settings = UserSettings {
+        timezone: Some(Helsinki),
+        quiet_hours: Some((time(22, 0), time(7, 0))),
+    };
+    let at = |h, m| {
+        Helsinki
+            .with_ymd_and_hms(2026, 10, 14, h, m, 0)
+            .unwrap()
+            .timestamp()
+    };
+
+    assert!(settings.is_quiet(at(23, 0)));
+    assert!(settings.is_quiet(at(3, 0)));
+    assert!(!settings.is_quiet(at(7, 0)));
+    assert!(!settings.is_quiet(at(12, 0)));
+}
+
+#[test]
+fn quiet_hours_within_day() {
+    let settings = UserSettings {
+        timezone: Some(Helsinki),
+        quiet_hours: Some((time(9, 0), time(17, 0))),
+    };
+    let at = |h, m| {
+        Helsinki
+            .with_ymd_and_hms(2026, 10, 14, h, m, 0)
+            .unwrap()
+            .timestamp()
+    };
+
+    assert!(!settings.is_quiet(at(8, 59)));
+    assert!(settings.is_quiet(at(9, 0)));
+    assert!(!settings.is_quiet(at(17, 0)));
+}
+
+#[test]
+fn format_time_in_timezone() {
+    let settings = UserSettings {
+        timezone: Some(Helsinki),
+        quiet_hours: None,
+    };
+    // 2026-10-14 12:00 UTC
+    assert_eq!(settings.format_time(1791979200), "14/10/2026 15:00");
+}
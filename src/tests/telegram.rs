@@ -0,0 +1,93 @@
+use teloxide::types::InlineKeyboardButtonKind;
+
+use super::test_item;
+use crate::delivery::telegram::TelegramAction;
+use crate::settings::UserSettings;
+use crate::vahti::VahtiItem;
+
+fn item(seller_id: i32, vahti_id: Option<i32>) -> VahtiItem {
+    VahtiItem {
+        delivery_method: Some(crate::delivery::telegram::ID),
+        vahti_id,
+        seller_id,
+        ..test_item()
+    }
+}
+
+fn buttons(item: &VahtiItem) -> Vec<(String, String)> {
+    item.telegram_keyboard()
+        .map(|k| k.inline_keyboard.concat())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|b| match b.kind {
+            InlineKeyboardButtonKind::CallbackData(data) => (b.text, data),
+            kind => panic!("Unexpected button {:?}", kind),
+        })
+        .collect()
+}
+
+#[test]
+fn action_roundtrip() {
+    let actions = [
+        TelegramAction::BlockSeller {
+            site_id: 1,
+            seller_id: i32::MAX,
+        },
+        TelegramAction::UnblockSeller {
+            site_id: 2,
+            seller_id: 123,
+        },
+        TelegramAction::RemoveVahti { vahti_id: 5 },
+        TelegramAction::MuteVahti { vahti_id: i32::MAX },
+    ];
+
+    for action in actions {
+        let data = action.to_string();
+        assert!(data.len() <= 64);
+        assert_eq!(data.parse::<TelegramAction>(), Ok(action));
+    }
+}
+
+#[test]
+fn invalid_actions() {
+    for data in ["", "b:1", "r", "r:x", "m:1:2", "x:1", "u:1:2:3"] {
+        assert_eq!(data.parse::<TelegramAction>(), Err(()), "{}", data);
+    }
+}
+
+#[test]
+fn item_keyboard() {
+    assert_eq!(
+        buttons(&item(123, Some(5))),
+        vec![
+            (String::from("Estä myyjä"), String::from("b:1:123")),
+            (String::from("Poista vahti"), String::from("r:5")),
+            (String::from("Hiljennä vahti"), String::from("m:5")),
+        ]
+    );
+    assert_eq!(
+        buttons(&item(0, Some(5))),
+        vec![
+            (String::from("Poista vahti"), String::from("r:5")),
+            (String::from("Hiljennä vahti"), String::from("m:5")),
+        ]
+    );
+    assert!(item(0, None).telegram_keyboard().is_none());
+}
+
+#[test]
+fn escapes_html() {
+    let item = VahtiItem {
+        title: String::from("<Polkupyörä> & kypärä"),
+        seller_name: String::from("M&M"),
+        location: String::from("<Helsinki>"),
+        ..item(123, None)
+    };
+
+    let msg = item.format_telegram(&UserSettings::default());
+    assert!(
+        msg.contains(r#"<a href="https://www.tori.fi/item/1">&lt;Polkupyörä&gt; &amp; kypärä</a>"#)
+    );
+    assert!(msg.contains(">M&amp;M</a>"));
+    assert!(msg.contains("<b>Sijainti</b>: &lt;Helsinki&gt;"));
+}
@@ -1,131 +1,212 @@
 use super::API_BASE;
-use crate::tori::api::vahti_to_api;
+use crate::tori::api::{legacy_to_recommerce, vahti_to_api};
 
 #[test]
 fn no_keyword() {
-    let url = "https://www.tori.fi/koko_suomi?";
-    let expected = API_BASE.to_owned();
+    let url = "https://www.tori.fi/recommerce/forsale/search?";
+    let expected = API_BASE.to_owned() + "sort=PUBLISHED_DESC";
     assert_eq!(expected, vahti_to_api(url));
 }
 
 #[test]
 fn basic_query() {
-    let url = "https://www.tori.fi/koko_suomi?q=thinkpad";
-    let expected = API_BASE.to_owned() + "&q=thinkpad";
+    let url = "https://www.tori.fi/recommerce/forsale/search?q=thinkpad";
+    let expected = API_BASE.to_owned() + "q=thinkpad&sort=PUBLISHED_DESC";
     assert_eq!(expected, vahti_to_api(url));
 }
 
 #[test]
 fn query_with_non_ascii() {
-    let url = "https://www.tori.fi/koko_suomi?q=th%F6nkpad";
-    let expected = API_BASE.to_owned() + "&q=thönkpad";
+    let url = "https://www.tori.fi/recommerce/forsale/search?q=th%C3%B6nkpad";
+    let expected = API_BASE.to_owned() + "q=th%C3%B6nkpad&sort=PUBLISHED_DESC";
     assert_eq!(expected, vahti_to_api(url));
 }
 
 #[test]
 fn query_with_category() {
-    let url = "https://www.tori.fi/koko_suomi?q=&cg=2030";
-    let expected = API_BASE.to_owned() + "&q=&category=2030";
+    let url = "https://www.tori.fi/recommerce/forsale/search?category=0.93&sub_category=1.93.3215";
+    let expected = API_BASE.to_owned() + "category=0.93&sub_category=1.93.3215&sort=PUBLISHED_DESC";
     assert_eq!(expected, vahti_to_api(url));
 }
 
 #[test]
-fn query_with_0_category() {
-    let url = "https://www.tori.fi/koko_suomi?q=&cg=0";
-    let expected = API_BASE.to_owned() + "&q=";
+fn query_with_price_range() {
+    let url = "https://www.tori.fi/recommerce/forsale/search?q=thinkpad&price_from=50&price_to=100";
+    let expected =
+        API_BASE.to_owned() + "q=thinkpad&price_from=50&price_to=100&sort=PUBLISHED_DESC";
     assert_eq!(expected, vahti_to_api(url));
 }
 
 #[test]
-fn query_with_price_range() {
-    let url = "https://www.tori.fi/koko_suomi?q=thinkpad&ps=2&pe=4";
-    let expected = API_BASE.to_owned() + "&q=thinkpad&suborder=50-100";
+fn query_with_location() {
+    let url = "https://www.tori.fi/recommerce/forsale/search?location=0.100018&location=0.100019&q=thinkpad";
+    let expected =
+        API_BASE.to_owned() + "location=0.100018&location=0.100019&q=thinkpad&sort=PUBLISHED_DESC";
     assert_eq!(expected, vahti_to_api(url));
 }
 
 #[test]
-fn price_range_no_start() {
-    let url = "https://www.tori.fi/koko_suomi?q=thinkpad&pe=5";
-    let expected = API_BASE.to_owned() + "&q=thinkpad&suborder=-250";
+fn query_with_sort_and_page() {
+    let url = "https://www.tori.fi/recommerce/forsale/search?q=thinkpad&sort=PRICE_ASC&page=3";
+    let expected = API_BASE.to_owned() + "q=thinkpad&sort=PUBLISHED_DESC";
     assert_eq!(expected, vahti_to_api(url));
 }
 
 #[test]
-fn price_range_no_end() {
-    let url = "https://www.tori.fi/koko_suomi?q=thinkpad&ps=6";
-    let expected = API_BASE.to_owned() + "&q=thinkpad&suborder=500-";
+fn query_with_no_argument_name() {
+    let url = "https://www.tori.fi/recommerce/forsale/search?q=thinkpad&=69";
+    let expected = API_BASE.to_owned() + "q=thinkpad&sort=PUBLISHED_DESC";
     assert_eq!(expected, vahti_to_api(url));
 }
 
 #[test]
-fn query_with_ad_type() {
-    let url = "https://www.tori.fi/koko_suomi?q=thinkpad&cg=0&st=s&st=g";
-    let expected = API_BASE.to_owned() + "&q=thinkpad&ad_type=s&ad_type=g";
+fn legacy_basic_query() {
+    let url = "https://www.tori.fi/koko_suomi?q=thinkpad";
+    let expected = API_BASE.to_owned() + "q=thinkpad&sort=PUBLISHED_DESC";
     assert_eq!(expected, vahti_to_api(url));
 }
 
 #[test]
-fn query_with_w() {
-    let url = "https://www.tori.fi/koko_suomi?q=thinkpad&w=3";
-    let expected = API_BASE.to_owned() + "&q=thinkpad";
+fn legacy_query_with_non_ascii() {
+    let url = "https://www.tori.fi/koko_suomi?q=th%F6nkpad";
+    let expected = API_BASE.to_owned() + "q=th%C3%B6nkpad&sort=PUBLISHED_DESC";
     assert_eq!(expected, vahti_to_api(url));
 }
 
 #[test]
-fn query_with_w_region() {
-    let url = "https://www.tori.fi/koko_suomi?q=thinkpad&w=104";
-    let expected = API_BASE.to_owned() + "&q=thinkpad&region=4";
+fn legacy_price_range() {
+    let url = "https://www.tori.fi/koko_suomi?q=thinkpad&ps=2&pe=4";
+    let expected =
+        API_BASE.to_owned() + "q=thinkpad&price_from=50&price_to=100&sort=PUBLISHED_DESC";
     assert_eq!(expected, vahti_to_api(url));
 }
 
 #[test]
-fn query_with_area() {
-    let url = "https://www.tori.fi/koko_suomi?q=thinkpad&m=7";
-    let expected = API_BASE.to_owned() + "&q=thinkpad&area=7";
+fn legacy_price_range_no_start() {
+    let url = "https://www.tori.fi/koko_suomi?q=thinkpad&pe=5";
+    let expected = API_BASE.to_owned() + "q=thinkpad&price_to=250&sort=PUBLISHED_DESC";
     assert_eq!(expected, vahti_to_api(url));
 }
 
 #[test]
-fn query_with_ca() {
-    let url = "https://www.tori.fi/koko_suomi?q=thinkpad&ca=10";
-    let expected = API_BASE.to_owned() + "&q=thinkpad";
+fn legacy_ad_type() {
+    let url = "https://www.tori.fi/koko_suomi?q=thinkpad&cg=0&st=s&st=g";
+    let expected = API_BASE.to_owned() + "q=thinkpad&trade_type=1&trade_type=3&sort=PUBLISHED_DESC";
     assert_eq!(expected, vahti_to_api(url));
 }
 
 #[test]
-fn query_with_ca_and_w() {
-    let url = "https://www.tori.fi/koko_suomi?q=thinkpad&w=104&ca=10";
-    let expected = API_BASE.to_owned() + "&q=thinkpad&region=4";
+fn legacy_incompatible_arguments() {
+    let url = "https://www.tori.fi/pohjanmaa?q=yoga-matto&cg=2030&w=104&m=7&ca=10&l=0&md=th";
+    let expected = API_BASE.to_owned() + "q=yoga-matto&sort=PUBLISHED_DESC";
     assert_eq!(expected, vahti_to_api(url));
 }
 
 #[test]
-fn query_with_no_argument_name() {
-    let url = "https://www.tori.fi/koko_suomi?q=thinkpad&=69";
-    let expected = API_BASE.to_owned() + "&q=thinkpad";
+fn legacy_multiquery() {
+    let url = "https://www.tori.fi/uusimaa?q=vinkulelu+koiralle&cg=0&w=1&st=s&st=k&st=u&st=h&st=g&l=0&md=th";
+    let expected = API_BASE.to_owned()
+        + "q=vinkulelu+koiralle&trade_type=1&trade_type=2&trade_type=3&sort=PUBLISHED_DESC";
     assert_eq!(expected, vahti_to_api(url));
 }
 
 #[test]
-fn query_with_different_base() {
-    let url = "https://www.tori.fi/lappi?q=thinkpad";
-    let expected = API_BASE.to_owned() + "&q=thinkpad";
-    assert_eq!(expected, vahti_to_api(url));
+fn legacy_to_recommerce_url() {
+    let url = "https://www.tori.fi/koko_suomi?q=thinkpad&ps=2";
+    let expected = "https://www.tori.fi/recommerce/forsale/search?q=thinkpad&price_from=50";
+    assert_eq!(expected, legacy_to_recommerce(url));
 }
 
 #[test]
-fn multiquery1() {
-    let url =
-        "https://www.tori.fi/pohjanmaa?q=yoga-matto&cg=0&w=1&st=s&st=k&st=u&st=h&st=g&l=0&md=th";
-    let expected = API_BASE.to_owned()
-        + "&q=yoga-matto&ad_type=s&ad_type=k&ad_type=u&ad_type=h&ad_type=g&l=0&md=th";
+fn seller_url() {
+    let url = "https://www.tori.fi/profile/ads?userId=1234567";
+    let legacy_url = "https://www.tori.fi/li?&aid=1234567";
+    let expected = API_BASE.to_owned() + "userId=1234567&sort=PUBLISHED_DESC";
     assert_eq!(expected, vahti_to_api(url));
+    assert_eq!(expected, vahti_to_api(legacy_url));
 }
 
 #[test]
-fn multiquery2() {
-    let url = "https://www.tori.fi/uusimaa?q=vinkulelu+koiralle&cg=0&w=1&st=s&st=k&st=u&st=h&st=g&l=0&md=th";
-    let expected = API_BASE.to_owned()
-        + "&q=vinkulelu+koiralle&ad_type=s&ad_type=k&ad_type=u&ad_type=h&ad_type=g&l=0&md=th";
-    assert_eq!(expected, vahti_to_api(url));
+fn item_id() {
+    use crate::tori::api::item_id_from_url;
+    let url = "https://www.tori.fi/recommerce/forsale/item/23456789";
+    assert_eq!(item_id_from_url(url), Some(23456789));
+    assert_eq!(
+        item_id_from_url(&format!("{}?ref=search", url)),
+        Some(23456789)
+    );
+    assert_eq!(
+        item_id_from_url("https://www.tori.fi/recommerce/forsale/search?q=thinkpad"),
+        None
+    );
+}
+
+#[test]
+fn summary() {
+    use crate::tori::api::summarize_url;
+
+    let url = "https://www.tori.fi/recommerce/forsale/search?location=0.100018&q=thinkpad&price_from=50&price_to=100";
+    assert_eq!(
+        summarize_url(url).to_string(),
+        "Tori: \"thinkpad\", 50–100€, alue: 0.100018"
+    );
+
+    let url = "https://www.tori.fi/uusimaa?q=thinkpad&ps=2";
+    assert_eq!(
+        summarize_url(url).to_string(),
+        "Tori: \"thinkpad\", yli 50€, alue: uusimaa"
+    );
+
+    let url = "https://www.tori.fi/koko_suomi?q=&pe=4";
+    assert_eq!(
+        summarize_url(url).to_string(),
+        "Tori: kaikki ilmoitukset, alle 100€"
+    );
+
+    let url = "https://www.tori.fi/profile/ads?userId=1234567";
+    assert_eq!(summarize_url(url).to_string(), "Tori: myyjä 1234567");
+}
+
+#[test]
+fn canonical() {
+    use crate::tori::api::canonical_url;
+
+    let expected = "https://www.tori.fi/recommerce/forsale/search?price_from=50&q=th%C3%B6nkpad";
+    for url in [
+        "https://www.tori.fi/recommerce/forsale/search?q=th%C3%B6nkpad&price_from=50",
+        "https://m.tori.fi/recommerce/forsale/search?price_from=50&q=thönkpad&",
+        "https://tori.fi/recommerce/forsale/search/?q=th%C3%B6nkpad&price_to=&price_from=50",
+        " https://www.tori.fi/recommerce/forsale/search?&price_from=50&q=th%C3%B6nkpad ",
+    ] {
+        assert_eq!(canonical_url(url), expected, "{}", url);
+    }
+    assert_eq!(canonical_url(expected), expected);
+
+    assert_eq!(
+        canonical_url("https://www.tori.fi/koko_suomi?q=th%F6nkpad&st=s"),
+        canonical_url("https://www.tori.fi/koko_suomi?st=s&q=th%C3%B6nkpad")
+    );
+    assert_eq!(
+        canonical_url("https://www.tori.fi/li?&aid=1234567"),
+        "https://www.tori.fi/profile/ads?userId=1234567"
+    );
+}
+
+#[test]
+fn legacy_limits() {
+    use crate::tori::api::{check_legacy_limits, legacy_limits};
+
+    let url = "https://www.tori.fi/pohjanmaa?q=yoga-matto&cg=2030&w=104&m=7&ca=10&l=0&md=th";
+    assert_eq!(legacy_limits(url), vec!["category", "region"]);
+    assert!(check_legacy_limits(url).is_err());
+
+    let url = "https://www.tori.fi/uusimaa?q=vinkulelu+koiralle&cg=0&w=1&st=s";
+    assert_eq!(legacy_limits(url), vec!["region"]);
+
+    let url = "https://www.tori.fi/koko_suomi?q=thinkpad&cg=0&ps=2";
+    assert!(legacy_limits(url).is_empty());
+    assert!(check_legacy_limits(url).is_ok());
+
+    let url = "https://www.tori.fi/recommerce/forsale/search?location=0.100018&q=thinkpad";
+    assert!(check_legacy_limits(url).is_ok());
 }
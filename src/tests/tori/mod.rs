@@ -1,4 +1,5 @@
 mod api_url;
 mod parse;
 
-const API_BASE: &str = "https://api.tori.fi/api/v1.2/public/ads?";
+const API_BASE: &str =
+    "https://www.tori.fi/recommerce-search-page/api/search/SEARCH_ID_BAP_COMMON?";
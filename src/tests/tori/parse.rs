@@ -13,19 +13,21 @@ fn basic_parse() {
     let expected = VahtiItem {
         deliver_to: None,
         delivery_method: None,
+        vahti_id: None,
         site_id: crate::tori::ID,
-        title: "Maalaisromanttinen peltipurkki ja eläimiä".to_string(),
+        title: "Lenovo ThinkPad T480 14\" i5 16GB".to_string(),
         vahti_url: None,
-        url: "https://www.tori.fi/vi/81076530.htm".to_string(),
-        img_url: "https://images.tori.fi/api/v1/imagestori/images/9039260397.jpg?rule=medium_660"
+        url: "https://www.tori.fi/recommerce/forsale/item/13425967".to_string(),
+        img_url: "https://images.tori.fi/dynamic/default/item/13425967/8c7b6a1e-0b3d-4a57-9b1f-52f7d9f2b1d4"
             .to_string(),
-        published: 1614890870,
-        price: 7,
-        seller_name: "H.S.M".to_string(),
-        seller_id: 188169,
-        location: "Maunula-Suursuo, Helsinki, Uusimaa".to_string(),
+        published: 1715097610,
+        price: 250,
+        seller_name: "".to_string(),
+        seller_id: 0,
+        location: "Helsinki".to_string(),
         ad_type: "Myydään".to_string(),
-        ad_id: 79217488,
+        ad_id: 13425967,
+        previous_price: None,
     };
 
     assert_eq!(
@@ -40,8 +42,8 @@ fn parse_after() {
     let mut contents = String::new();
     file.read_to_string(&mut contents).unwrap();
 
-    assert_eq!(api_parse_after(&contents, 1651416320).unwrap().len(), 1);
-    assert_eq!(api_parse_after(&contents, 1651416319).unwrap().len(), 2);
+    assert_eq!(api_parse_after(&contents, 1715160319).unwrap().len(), 1);
+    assert_eq!(api_parse_after(&contents, 1715160318).unwrap().len(), 2);
 }
 
 #[test]
@@ -54,232 +56,77 @@ fn parse_multiple() {
         VahtiItem {
             deliver_to: None,
             delivery_method: None,
+            vahti_id: None,
             site_id: 1,
-            title: "Naamiaisasu ".to_string(),
-            vahti_url: None,
-            url: "https://www.tori.fi/vi/107951227.htm".to_string(),
-            img_url:
-                "https://images.tori.fi/api/v1/imagestori/images/7574231064.jpg?rule=medium_660"
-                    .to_string(),
-            published: 1674035937,
-            price: 25,
-            seller_name: "Erja Latva".to_string(),
-            seller_id: 289139,
-            location: "Suvilahti, Vaasa, Pohjanmaa".to_string(),
-            ad_type: "Myydään".to_string(),
-            ad_id: 107463388,
-        },
-        VahtiItem {
-            deliver_to: None,
-            delivery_method: None,
-            site_id: 1,
-            title: "Ninebot by Segway KickScooter sähköpotkulauta F25E".to_string(),
+            title: "Ninebot by Segway KickScooter F25E".to_string(),
             vahti_url: None,
-            url: "https://www.tori.fi/vi/103805389.htm".to_string(),
-            img_url:
-                "https://images.tori.fi/api/v1/imagestori/images/100146113672.jpg?rule=medium_660"
-                    .to_string(),
-            published: 1673531834,
+            url: "https://www.tori.fi/recommerce/forsale/item/13440021".to_string(),
+            img_url: "https://images.tori.fi/dynamic/default/item/13440021/b1c2d3e4-f5a6-4b7c-8d9e-0f1a2b3c4d5e"
+                .to_string(),
+            published: 1715251834,
             price: 339,
             seller_name: "Gigantti outlet Vaasa".to_string(),
-            seller_id: 3237298,
-            location: "Asevelikylä, Vaasa, Pohjanmaa".to_string(),
+            seller_id: 0,
+            location: "Vaasa".to_string(),
             ad_type: "Myydään".to_string(),
-            ad_id: 103120642,
+            ad_id: 13440021,
+            previous_price: None,
         },
         VahtiItem {
             deliver_to: None,
             delivery_method: None,
+            vahti_id: None,
             site_id: 1,
-            title: "Meta Quest 2 Elite hihna + akku".to_string(),
-            vahti_url: None,
-            url: "https://www.tori.fi/vi/108452916.htm".to_string(),
-            img_url:
-                "https://images.tori.fi/api/v1/imagestori/images/100181065412.jpg?rule=medium_660"
-                    .to_string(),
-            published: 1675057180,
-            price: 143,
-            seller_name: "Gigantti outlet Vaasa".to_string(),
-            seller_id: 3237298,
-            location: "Asevelikylä, Vaasa, Pohjanmaa".to_string(),
-            ad_type: "Myydään".to_string(),
-            ad_id: 107987389,
-        },
-        VahtiItem {
-            deliver_to: None,
-            delivery_method: None,
-            site_id: 1,
-            title: "Sea-doo rxt-x 300 rs".to_string(),
-            vahti_url: None,
-            url: "https://www.tori.fi/vi/106281850.htm".to_string(),
-            img_url:
-                "https://images.tori.fi/api/v1/imagestori/images/100170569995.jpg?rule=medium_660"
-                    .to_string(),
-            published: 1674365101,
-            price: 16990,
-            seller_name: "Rinta-Joupin Autoliike, Tervajoki".to_string(),
-            seller_id: 2349504,
-            location: "Tervajoki, Laihia, Pohjanmaa".to_string(),
-            ad_type: "Myydään".to_string(),
-            ad_id: 105715838,
-        },
-        VahtiItem {
-            deliver_to: None,
-            delivery_method: None,
-            site_id: 1,
-            title: "ASUS PRIME Z790-P D4 ATX emolevy".to_string(),
-            vahti_url: None,
-            url: "https://www.tori.fi/vi/107247726.htm".to_string(),
-            img_url:
-                "https://images.tori.fi/api/v1/imagestori/images/100171951188.jpg?rule=medium_660"
-                    .to_string(),
-            published: 1675853738,
-            price: 268,
-            seller_name: "Gigantti outlet Vaasa".to_string(),
-            seller_id: 3237298,
-            location: "Asevelikylä, Vaasa, Pohjanmaa".to_string(),
-            ad_type: "Myydään".to_string(),
-            ad_id: 106730945,
-        },
-        VahtiItem {
-            deliver_to: None,
-            delivery_method: None,
-            site_id: 1,
-            title: "Bosch Ladattava pölynimuri BBH3ZOO28 (tornadon)".to_string(),
+            title: "Naamiaisasu ".to_string(),
             vahti_url: None,
-            url: "https://www.tori.fi/vi/106947918.htm".to_string(),
-            img_url:
-                "https://images.tori.fi/api/v1/imagestori/images/100168416209.jpg?rule=medium_660"
-                    .to_string(),
-            published: 1675842778,
-            price: 174,
-            seller_name: "Gigantti outlet Vaasa".to_string(),
-            seller_id: 3237298,
-            location: "Asevelikylä, Vaasa, Pohjanmaa".to_string(),
+            url: "https://www.tori.fi/recommerce/forsale/item/13439876".to_string(),
+            img_url: "https://images.tori.fi/dynamic/default/item/13439876/aa11bb22-cc33-4d44-8e55-ff6677889900"
+                .to_string(),
+            published: 1715248937,
+            price: 25,
+            seller_name: "".to_string(),
+            seller_id: 0,
+            location: "Vaasa".to_string(),
             ad_type: "Myydään".to_string(),
-            ad_id: 106414054,
+            ad_id: 13439876,
+            previous_price: None,
         },
         VahtiItem {
             deliver_to: None,
             delivery_method: None,
+            vahti_id: None,
             site_id: 1,
             title: "Miele hood 90cm black".to_string(),
             vahti_url: None,
-            url: "https://www.tori.fi/vi/106692075.htm".to_string(),
+            url: "https://www.tori.fi/recommerce/forsale/item/13438110".to_string(),
             img_url: "".to_string(),
-            published: 1675869730,
+            published: 1715245730,
             price: 3329,
             seller_name: "Gigantti outlet Vaasa".to_string(),
-            seller_id: 3237298,
-            location: "Asevelikylä, Vaasa, Pohjanmaa".to_string(),
-            ad_type: "Myydään".to_string(),
-            ad_id: 106144962,
-        },
-        VahtiItem {
-            deliver_to: None,
-            delivery_method: None,
-            site_id: 1,
-            title: "Ninebot by Segway KickScooter sähköpotkulauta E25D".to_string(),
-            vahti_url: None,
-            url: "https://www.tori.fi/vi/101906085.htm".to_string(),
-            img_url:
-                "https://images.tori.fi/api/v1/imagestori/images/100134901177.jpg?rule=medium_660"
-                    .to_string(),
-            published: 1675853818,
-            price: 402,
-            seller_name: "Gigantti outlet Vaasa".to_string(),
-            seller_id: 3237298,
-            location: "Asevelikylä, Vaasa, Pohjanmaa".to_string(),
+            seller_id: 0,
+            location: "Vaasa".to_string(),
             ad_type: "Myydään".to_string(),
-            ad_id: 101130082,
+            ad_id: 13438110,
+            previous_price: None,
         },
         VahtiItem {
             deliver_to: None,
             delivery_method: None,
+            vahti_id: None,
             site_id: 1,
-            title: "Mercury F20EPS".to_string(),
+            title: "Pahvilaatikoita muuttoon".to_string(),
             vahti_url: None,
-            url: "https://www.tori.fi/vi/109023706.htm".to_string(),
-            img_url:
-                "https://images.tori.fi/api/v1/imagestori/images/100185031992.jpg?rule=medium_660"
-                    .to_string(),
-            published: 1676283023,
-            price: 3700,
-            seller_name: "Rinta-Joupin Autoliike, Tervajoki".to_string(),
-            seller_id: 2349504,
-            location: "Tervajoki, Laihia, Pohjanmaa".to_string(),
-            ad_type: "Myydään".to_string(),
-            ad_id: 108584455,
-        },
-        VahtiItem {
-            deliver_to: None,
-            delivery_method: None,
-            site_id: 1,
-            title: "Savo hood a".to_string(),
-            vahti_url: None,
-            url: "https://www.tori.fi/vi/101984681.htm".to_string(),
-            img_url: "".to_string(),
-            published: 1675873122,
-            price: 299,
-            seller_name: "Gigantti outlet Vaasa".to_string(),
-            seller_id: 3237298,
-            location: "Asevelikylä, Vaasa, Pohjanmaa".to_string(),
-            ad_type: "Myydään".to_string(),
-            ad_id: 101212772,
-        },
-        VahtiItem {
-            deliver_to: None,
-            delivery_method: None,
-            site_id: 1,
-            title: "E.t.m sports& casuals 52".to_string(),
-            vahti_url: None,
-            url: "https://www.tori.fi/vi/109489503.htm".to_string(),
-            img_url:
-                "https://images.tori.fi/api/v1/imagestori/images/100188042265.jpg?rule=medium_660"
-                    .to_string(),
-            published: 1677350539,
-            price: 20,
-            seller_name: "moternimies".to_string(),
-            seller_id: 2695759,
-            location: "Vanha Vaasa, Vaasa, Pohjanmaa".to_string(),
-            ad_type: "Myydään".to_string(),
-            ad_id: 109060376,
-        },
-        VahtiItem {
-            deliver_to: None,
-            delivery_method: None,
-            site_id: 1,
-            title: "SoFlow sähköpotkulauta SOFLOW01".to_string(),
-            vahti_url: None,
-            url: "https://www.tori.fi/vi/99732961.htm".to_string(),
-            img_url:
-                "https://images.tori.fi/api/v1/imagestori/images/100124634411.jpg?rule=medium_660"
-                    .to_string(),
-            published: 1676294880,
-            price: 297,
-            seller_name: "Gigantti outlet Vaasa".to_string(),
-            seller_id: 3237298,
-            location: "Asevelikylä, Vaasa, Pohjanmaa".to_string(),
-            ad_type: "Myydään".to_string(),
-            ad_id: 98836530,
-        },
-        VahtiItem {
-            deliver_to: None,
-            delivery_method: None,
-            site_id: 1,
-            title: "Audio Pro G10 älykäs monihuonekaiutin (vaaleanharm".to_string(),
-            vahti_url: None,
-            url: "https://www.tori.fi/vi/91855652.htm".to_string(),
-            img_url:
-                "https://images.tori.fi/api/v1/imagestori/images/100104289690.jpg?rule=medium_660"
-                    .to_string(),
-            published: 1677846660,
-            price: 167,
-            seller_name: "Gigantti outlet Vaasa".to_string(),
-            seller_id: 3237298,
-            location: "Asevelikylä, Vaasa, Pohjanmaa".to_string(),
-            ad_type: "Myydään".to_string(),
-            ad_id: 90554189,
+            url: "https://www.tori.fi/recommerce/forsale/item/13437002".to_string(),
+            img_url: "https://images.tori.fi/dynamic/default/item/13437002/12ab34cd-56ef-4a78-9b01-23cd45ef6789"
+                .to_string(),
+            published: 1715242101,
+            price: 0,
+            seller_name: "".to_string(),
+            seller_id: 0,
+            location: "Laihia".to_string(),
+            ad_type: "Annetaan".to_string(),
+            ad_id: 13437002,
+            previous_price: None,
         },
     ];
 
@@ -288,9 +135,67 @@ fn parse_multiple() {
     expected.sort_by_key(|v| v.ad_id);
     got.sort_by_key(|v| v.ad_id);
 
+    assert_eq!(expected.len(), got.len());
     let _ = expected
         .iter()
         .zip(got.iter())
         .map(|(a, b)| assert_eq!(a, b))
         .collect::<Vec<_>>();
 }
+
+#[test]
+fn match_count() {
+    let mut file = File::open("testdata/tori/parse_multiple.json").expect("Test data not found");
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).unwrap();
+
+    assert_eq!(crate::tori::api::match_count(&contents), Some(4));
+    assert_eq!(crate::tori::api::match_count("{}"), None);
+    assert_eq!(crate::tori::api::match_count("<html></html>"), None);
+}
+
+#[test]
+fn listing_status() {
+    use crate::bookmark::ListingStatus;
+    use crate::tori::api::listing_status;
+
+    let page = |data: &str| {
+        format!(
+            r#"<html><head><title>Thinkpad | Tori</title></head><body><script>{{"adId":123,{}}}</script></body></html>"#,
+            data
+        )
+    };
+
+    assert_eq!(
+        listing_status(&page(r#""disposed":false"#)),
+        ListingStatus::Active
+    );
+    assert_eq!(
+        listing_status(&page(r#""disposed": true"#)),
+        ListingStatus::Sold
+    );
+    assert_eq!(
+        listing_status(&page(r#""adStatus":"SOLD""#)),
+        ListingStatus::Sold
+    );
+    assert_eq!(
+        listing_status(&page(r#""adStatus":"DISABLED""#)),
+        ListingStatus::Removed
+    );
+    assert_eq!(
+        listing_status(&page(r#""adStatus":"ACTIVE""#)),
+        ListingStatus::Active
+    );
+}
+
+#[test]
+fn seller_name() {
+    use crate::tori::seller::seller_name_from_profile;
+
+    assert_eq!(
+        seller_name_from_profile("<html><head><title>Matti M. | Tori</title></head></html>"),
+        Some(String::from("Matti M."))
+    );
+    assert_eq!(seller_name_from_profile("<title> | Tori</title>"), None);
+    assert_eq!(seller_name_from_profile("{}"), None);
+}
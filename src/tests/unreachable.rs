@@ -0,0 +1,161 @@
+use teloxide::{ApiError, RequestError};
+
+use super::database::test_db;
+use crate::error::Error;
+use crate::unreachable::{record_delivery, report, welcome_back};
+
+const URL: &str = "https://www.tori.fi/recommerce/forsale/search?q=thinkpad";
+const USER: i64 = 123456789;
+const DISCORD: i32 = crate::delivery::discord::ID;
+const TELEGRAM: i32 = crate::delivery::telegram::ID;
+
+fn blocked() -> Result<(), Error> {
+    Err(Error::Telegram(RequestError::Api(ApiError::BotBlocked)))
+}
+
+#[test]
+fn unreachable_errors() {
+    assert!(blocked().unwrap_err().is_unreachable());
+    assert!(Error::Telegram(RequestError::Api(ApiError::ChatNotFound)).is_unreachable());
+    assert!(
+        !Error::Telegram(RequestError::RetryAfter(std::time::Duration::from_secs(5)))
+            .is_unreachable()
+    );
+    assert!(!Error::Telegram(RequestError::MigrateToChatId(-100)).is_unreachable());
+    assert!(!Error::Database(diesel::result::Error::NotFound).is_unreachable());
+    assert!(!Error::Tori.is_unreachable());
+}
+
+#[tokio::test]
+async fn disable_unreachable_user() {
+    let (db, path) = test_db("unreachable");
+    let other = URL.replace("thinkpad", "macbook");
+
+    for (url, delivery_method) in [(URL, TELEGRAM), (&other, TELEGRAM), (URL, DISCORD)] {
+        db.add_vahti_entry(url, USER, crate::tori::ID, delivery_method, None, None)
+            .await
+            .unwrap();
+    }
+
+    // NOTE: A successful delivery resets the count and other errors don't affect it
+    for _ in 0..*crate::UNREACHABLE_FAILURE_LIMIT - 1 {
+        record_delivery(&db, USER, TELEGRAM, &blocked())
+            .await
+            .unwrap();
+    }
+    record_delivery(&db, USER, TELEGRAM, &Ok(())).await.unwrap();
+    for _ in 0..*crate::UNREACHABLE_FAILURE_LIMIT - 1 {
+        record_delivery(&db, USER, TELEGRAM, &blocked())
+            .await
+            .unwrap();
+        record_delivery(&db, USER, TELEGRAM, &Err(Error::Tori))
+            .await
+            .unwrap();
+    }
+    assert!(db.count_disabled_vahtis().await.unwrap().is_empty());
+    assert_eq!(
+        report(&db).await.unwrap(),
+        "No Vahtis have been disabled for unreachable users"
+    );
+
+    record_delivery(&db, USER, TELEGRAM, &blocked())
+        .await
+        .unwrap();
+    assert_eq!(
+        db.count_disabled_vahtis()
+            .await
+            .unwrap()
+            .into_iter()
+            .collect::<Vec<_>>(),
+        [(TELEGRAM, 2)]
+    );
+    assert_eq!(
+        report(&db).await.unwrap(),
+        "Vahtis disabled for unreachable users: telegram 2 (2 in total)"
+    );
+    let updated = db.fetch_all_vahtis_group().await.unwrap();
+    assert_eq!(updated.values().flatten().count(), 1);
+    assert_eq!(updated[URL][0].delivery_method, DISCORD);
+
+    assert_eq!(welcome_back(&db, USER as u64, DISCORD).await, None);
+    assert!(welcome_back(&db, USER as u64, TELEGRAM)
+        .await
+        .unwrap()
+        .contains("Your 2 Vahtis were disabled"));
+    assert_eq!(welcome_back(&db, USER as u64, TELEGRAM).await, None);
+    assert!(db.count_disabled_vahtis().await.unwrap().is_empty());
+    assert_eq!(
+        db.fetch_all_vahtis_group()
+            .await
+            .unwrap()
+            .values()
+            .flatten()
+            .count(),
+        3
+    );
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn migrate_group() {
+    let (db, path) = test_db("migrate-group");
+    let (group, supergroup) = (-123, -100123);
+
+    db.add_vahti_entry(URL, group, crate::tori::ID, TELEGRAM, None, None)
+        .await
+        .unwrap();
+    db.add_seller_to_blacklist(group, TELEGRAM, 42, crate::tori::ID)
+        .await
+        .unwrap();
+    // NOTE: The same id on Discord is someone else
+    db.add_seller_to_blacklist(group, DISCORD, 43, crate::tori::ID)
+        .await
+        .unwrap();
+    db.set_user_settings(crate::models::NewUserSettings {
+        user_id: group,
+        delivery_method: TELEGRAM,
+        timezone: Some(String::from("Europe/Helsinki")),
+        quiet_start: None,
+        quiet_end: None,
+    })
+    .await
+    .unwrap();
+
+    let migrated = Err(Error::Telegram(RequestError::MigrateToChatId(supergroup)));
+    assert!(!migrated.as_ref().unwrap_err().is_unreachable());
+    for _ in 0..*crate::UNREACHABLE_FAILURE_LIMIT {
+        record_delivery(&db, group, TELEGRAM, &migrated)
+            .await
+            .unwrap();
+    }
+
+    assert!(db.fetch_vahti(URL, group, TELEGRAM).await.is_err());
+    assert!(
+        !db.fetch_vahti(URL, supergroup, TELEGRAM)
+            .await
+            .unwrap()
+            .disabled
+    );
+    assert!(db
+        .fetch_user_blacklist(group, TELEGRAM)
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        db.fetch_user_blacklist(supergroup, TELEGRAM).await.unwrap(),
+        [(42, crate::tori::ID)]
+    );
+    assert_eq!(
+        db.fetch_user_blacklist(group, DISCORD).await.unwrap(),
+        [(43, crate::tori::ID)]
+    );
+    assert!(db
+        .fetch_user_settings(supergroup, TELEGRAM)
+        .await
+        .unwrap()
+        .is_some());
+    assert!(db.count_disabled_vahtis().await.unwrap().is_empty());
+
+    let _ = std::fs::remove_file(path);
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+
+use super::database::test_db;
+use super::test_item;
+use crate::database::Database;
+use crate::error::Error;
+use crate::itemhistory::ItemHistoryStorage;
+use crate::models::DbVahti;
+use crate::vahti::{update_group, Vahti, VahtiItem};
+
+const URL: &str = "https://www.tori.fi/recommerce/forsale/search?q=thinkpad";
+const OTHER_URL: &str = "https://www.tori.fi/recommerce/forsale/search?q=polkupyora";
+const INACTIVE_URL: &str = "https://www.tori.fi/recommerce/forsale/search?q=kitara";
+const TELEGRAM: i32 = crate::delivery::telegram::ID;
+
+// NOTE: The Vahtis are built by `update_group` itself, so the calls are counted by url
+static FETCHES: LazyLock<Mutex<HashMap<String, usize>>> = LazyLock::new(Default::default);
+static UPDATES: LazyLock<Mutex<HashMap<String, Vec<i32>>>> = LazyLock::new(Default::default);
+
+/// Counts the fetches and the updates and finds the test item on every fetch
+struct Counting {
+    vahti: DbVahti,
+}
+
+#[async_trait]
+impl Vahti for Counting {
+    async fn fetch_items(&self, _: i64) -> Result<Vec<VahtiItem>, Error> {
+        *FETCHES
+            .lock()
+            .unwrap()
+            .entry(self.vahti.url.clone())
+            .or_default() += 1;
+        Ok(vec![test_item()])
+    }
+
+    async fn update(
+        &mut self,
+        _: &Database,
+        _: ItemHistoryStorage,
+        items: &[VahtiItem],
+    ) -> Result<Vec<VahtiItem>, Error> {
+        UPDATES
+            .lock()
+            .unwrap()
+            .entry(self.vahti.url.clone())
+            .or_default()
+            .push(self.vahti.id);
+        Ok(items.to_vec())
+    }
+
+    async fn validate_url(&self) -> Result<(i64, Vec<VahtiItem>), Error> {
+        Ok((0, vec![]))
+    }
+
+    fn is_valid_url(&self, _: &str) -> bool {
+        true
+    }
+
+    fn canonicalize_url(url: &str) -> String {
+        url.to_string()
+    }
+
+    fn from_db(v: DbVahti) -> Result<Self, Error> {
+        Ok(Self { vahti: v })
+    }
+
+    fn to_db(&self) -> DbVahti {
+        self.vahti.clone()
+    }
+}
+
+/// Updates the Vahtis the way `update_vahtis` does, returning the found items
+async fn update_all(db: &Database) -> Vec<VahtiItem> {
+    let ihs: ItemHistoryStorage = Arc::new(DashMap::new());
+    let mut items = vec![];
+    for vs in db.fetch_all_vahtis_group().await.unwrap().into_values() {
+        items.extend(update_group::<Counting>(db, ihs.clone(), vs).await);
+    }
+    items
+}
+
+async fn add_vahti(db: &Database, url: &str, user: i64) -> i32 {
+    db.add_vahti_entry(url, user, crate::tori::ID, TELEGRAM, None, None)
+        .await
+        .unwrap();
+    db.fetch_vahti(url, user, TELEGRAM).await.unwrap().id
+}
+
+#[tokio::test]
+async fn one_fetch_per_url() {
+    let (db, path) = test_db("one-fetch-per-url");
+
+    for user in 1..=3 {
+        add_vahti(&db, URL, user).await;
+    }
+    add_vahti(&db, OTHER_URL, 1).await;
+
+    let items = update_all(&db).await;
+    assert_eq!(items.len(), 4);
+
+    assert_eq!(FETCHES.lock().unwrap()[URL], 1);
+    assert_eq!(FETCHES.lock().unwrap()[OTHER_URL], 1);
+    assert_eq!(UPDATES.lock().unwrap()[URL].len(), 3);
+    assert_eq!(UPDATES.lock().unwrap()[OTHER_URL].len(), 1);
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn inactive_vahtis_are_skipped() {
+    let (db, path) = test_db("inactive-vahtis");
+
+    let active = add_vahti(&db, INACTIVE_URL, 1).await;
+    let paused = add_vahti(&db, INACTIVE_URL, 2).await;
+    db.pause_vahti(paused).await.unwrap();
+    let snoozed = add_vahti(&db, INACTIVE_URL, 3).await;
+    db.snooze_vahti(snoozed, chrono::Local::now().timestamp() + 3600)
+        .await
+        .unwrap();
+    add_vahti(&db, INACTIVE_URL, 4).await;
+    db.disable_vahtis(4, TELEGRAM).await.unwrap();
+
+    let items = update_all(&db).await;
+    assert_eq!(items.len(), 1);
+
+    assert_eq!(FETCHES.lock().unwrap()[INACTIVE_URL], 1);
+    assert_eq!(UPDATES.lock().unwrap()[INACTIVE_URL], [active]);
+
+    let _ = std::fs::remove_file(path);
+}
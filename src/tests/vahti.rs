@@ -0,0 +1,187 @@
+use tokio::net::TcpListener;
+
+use super::{serve, test_item};
+use crate::error::Error;
+use crate::models::DbVahti;
+use crate::vahti::{
+    canonicalization_plan, fan_out, fetch_search, format_elapsed, format_preview, parse_duration,
+    summarize_url, VahtiItem,
+};
+
+fn item(title: &str, price: i64) -> VahtiItem {
+    VahtiItem {
+        title: title.to_string(),
+        url: format!("https://www.tori.fi/recommerce/forsale/item/{}", price),
+        price,
+        ad_id: price,
+        ..test_item()
+    }
+}
+
+/// The search endpoint of a local server that answers once with the status and body
+async fn respond(status: u16, body: &'static str) -> String {
+    format!("{}/search", serve(vec![(status, body)]).await.0)
+}
+
+#[test]
+fn elapsed() {
+    let now = 1_800_000_000;
+    assert_eq!(format_elapsed(now, 0), "ei koskaan");
+    assert_eq!(format_elapsed(now, now - 30), "juuri nyt");
+    assert_eq!(format_elapsed(now, now + 30), "juuri nyt");
+    assert_eq!(format_elapsed(now, now - 5 * 60), "5 min sitten");
+    assert_eq!(format_elapsed(now, now - 3 * 3600 - 59), "3 h sitten");
+    assert_eq!(format_elapsed(now, now - 8 * 86400), "8 pv sitten");
+}
+
+#[test]
+fn unknown_site_summary() {
+    assert_eq!(
+        summarize_url("https://example.com/search?q=x").to_string(),
+        "Tuntematon: \"https://example.com/search?q=x\""
+    );
+}
+
+#[test]
+fn durations() {
+    assert_eq!(parse_duration("30m").unwrap(), 30 * 60);
+    assert_eq!(parse_duration("12h").unwrap(), 12 * 3600);
+    assert_eq!(parse_duration(" 3d ").unwrap(), 3 * 86400);
+    assert_eq!(parse_duration("2w").unwrap(), 14 * 86400);
+
+    for invalid in [
+        "",
+        "d",
+        "0d",
+        "-1h",
+        "3",
+        "3x",
+        "1.5h",
+        "53w",
+        "99999999999m",
+    ] {
+        assert!(parse_duration(invalid).is_err(), "{}", invalid);
+    }
+}
+
+#[tokio::test]
+async fn search_ok() {
+    let api = respond(200, "{}").await;
+    assert_eq!(
+        fetch_search("https://www.tori.fi/", &api).await.unwrap(),
+        "{}"
+    );
+}
+
+#[tokio::test]
+async fn search_invalid_url() {
+    let api = respond(400, "").await;
+    assert!(matches!(
+        fetch_search("https://www.tori.fi/", &api).await,
+        Err(Error::InvalidUrl(url)) if url == "https://www.tori.fi/"
+    ));
+}
+
+#[tokio::test]
+async fn search_unreachable() {
+    let api = respond(503, "").await;
+    assert!(matches!(
+        fetch_search("https://www.tori.fi/", &api).await,
+        Err(Error::SiteUnreachable(_))
+    ));
+
+    // NOTE: Nothing is listening on the port once the listener is dropped
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let api = format!("http://{}/search", listener.local_addr().unwrap());
+    drop(listener);
+    assert!(matches!(
+        fetch_search("https://www.tori.fi/", &api).await,
+        Err(Error::SiteUnreachable(_))
+    ));
+}
+
+#[test]
+fn preview() {
+    assert!(format_preview(0, &[]).starts_with("The search has 0 results currently"));
+    assert_eq!(
+        format_preview(12, &[item("Bike", 100), item("Helmet", 20)]),
+        "The search has 12 results currently, the newest matches are\n\
+        Bike 100€ https://www.tori.fi/recommerce/forsale/item/100\n\
+        Helmet 20€ https://www.tori.fi/recommerce/forsale/item/20"
+    );
+    assert_eq!(format_preview(5, &[]), "The search has 5 results currently");
+}
+
+fn db_vahti(id: i32, url: &str, user_id: i64) -> DbVahti {
+    DbVahti {
+        id,
+        url: url.to_string(),
+        user_id,
+        last_updated: 0,
+        site_id: 1,
+        delivery_method: 1,
+        filters: None,
+        digest: None,
+        last_digest: 0,
+        paused: false,
+        last_error: None,
+        snoozed_until: None,
+        creator_id: None,
+        disabled: false,
+    }
+}
+
+#[test]
+fn canonicalization() {
+    let canonical = "https://www.tori.fi/recommerce/forsale/search?price_from=50&q=bike";
+    let vahtis = [
+        db_vahti(
+            3,
+            "https://m.tori.fi/recommerce/forsale/search?q=bike&price_from=50",
+            1,
+        ),
+        db_vahti(
+            1,
+            "https://www.tori.fi/recommerce/forsale/search?q=bike&price_from=50&",
+            1,
+        ),
+        db_vahti(2, canonical, 2),
+        db_vahti(4, canonical, 1),
+        db_vahti(
+            5,
+            "https://www.tori.fi/recommerce/forsale/search?q=helmet",
+            1,
+        ),
+    ];
+
+    let (rewrites, removed) = canonicalization_plan(&vahtis);
+    assert_eq!(rewrites, vec![(1, canonical.to_string())]);
+    assert_eq!(removed, vec![(3, 1), (4, 1)]);
+
+    let (rewrites, removed) = canonicalization_plan(&[db_vahti(2, canonical, 2)]);
+    assert!(rewrites.is_empty() && removed.is_empty());
+}
+
+#[test]
+fn fan_out_to_destinations() {
+    let mut i = item("Thinkpad", 100);
+    i.deliver_to = Some(1);
+    i.delivery_method = Some(1);
+    i.vahti_id = Some(7);
+
+    let destinations = [(7, vec![(2, 2), (1, 1)])].into_iter().collect();
+    let items = fan_out(i.clone(), &destinations);
+    assert_eq!(
+        items
+            .iter()
+            .map(|i| (i.deliver_to.unwrap(), i.delivery_method.unwrap()))
+            .collect::<Vec<_>>(),
+        vec![(2, 2), (1, 1)]
+    );
+    assert!(items.iter().all(|i| i.ad_id == 100));
+
+    i.vahti_id = Some(8);
+    let items = fan_out(i, &destinations);
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].deliver_to, Some(1));
+}
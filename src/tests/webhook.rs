@@ -0,0 +1,144 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use reqwest::Client;
+
+use super::{serve, Request};
+use crate::bookmark::ListingStatus;
+use crate::delivery::webhook::{post, sign, Payload, PAYLOAD_VERSION, SIGNATURE_HEADER};
+use crate::delivery::Notification;
+
+/// The hook endpoint of a local server that answers with the given statuses in order
+async fn serve_hook(statuses: Vec<u16>) -> (String, Arc<Mutex<Vec<Request>>>) {
+    let (url, requests) = serve(statuses.into_iter().map(|s| (s, "")).collect()).await;
+    (format!("{}/hook", url), requests)
+}
+
+#[tokio::test]
+async fn retries_server_errors() {
+    let (url, requests) = serve_hook(vec![500, 503, 200]).await;
+
+    post(
+        &Client::new(),
+        &url,
+        None,
+        String::from("{}"),
+        3,
+        Duration::from_millis(1),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(requests.lock().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn gives_up_after_retries() {
+    let (url, requests) = serve_hook(vec![500, 500]).await;
+
+    let res = post(
+        &Client::new(),
+        &url,
+        None,
+        String::from("{}"),
+        1,
+        Duration::from_millis(1),
+    )
+    .await;
+
+    assert!(res.is_err());
+    assert_eq!(requests.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn does_not_retry_client_errors() {
+    let (url, requests) = serve_hook(vec![400, 200]).await;
+
+    let res = post(
+        &Client::new(),
+        &url,
+        None,
+        String::from("{}"),
+        3,
+        Duration::from_millis(1),
+    )
+    .await;
+
+    assert!(res.is_err());
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn signs_the_body() {
+    let (url, requests) = serve_hook(vec![200, 200]).await;
+    let body = String::from(r#"{"version":1}"#);
+
+    let client = Client::new();
+    post(
+        &client,
+        &url,
+        Some("secret"),
+        body.clone(),
+        0,
+        Duration::ZERO,
+    )
+    .await
+    .unwrap();
+    post(&client, &url, None, body.clone(), 0, Duration::ZERO)
+        .await
+        .unwrap();
+
+    let requests = requests.lock().unwrap();
+    let header = format!(
+        "{}: {}",
+        SIGNATURE_HEADER.to_lowercase(),
+        sign("secret", &body)
+    );
+    assert_eq!(requests[0].body, body);
+    assert!(requests[0].headers.contains(&header));
+    assert!(requests[0]
+        .headers
+        .contains("content-type: application/json"));
+    assert!(!requests[1]
+        .headers
+        .contains(&SIGNATURE_HEADER.to_lowercase()));
+}
+
+#[test]
+fn signature() {
+    // echo -n 'body' | openssl dgst -sha256 -hmac 'secret'
+    assert_eq!(
+        sign("secret", "body"),
+        "sha256=dc46983557fea127b43af721467eb9b3fde2338fe3e14f51952aa8478c13d355"
+    );
+}
+
+#[test]
+fn payload_schema() {
+    let payload = Payload {
+        version: PAYLOAD_VERSION,
+        kind: "notification",
+        webhook_id: 3,
+        items: vec![],
+        notification: Some(Notification::ListingGone {
+            title: String::from("Polkupyörä"),
+            url: String::from("https://www.tori.fi/item/1"),
+            status: ListingStatus::Removed,
+        }),
+    };
+
+    assert_eq!(
+        serde_json::to_value(payload).unwrap(),
+        serde_json::json!({
+            "version": 1,
+            "type": "notification",
+            "webhook_id": 3,
+            "notification": {
+                "kind": "listing_gone",
+                "title": "Polkupyörä",
+                "url": "https://www.tori.fi/item/1",
+                "status": "removed",
+            },
+        })
+    );
+}
@@ -1,88 +1,298 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+use reqwest::StatusCode;
 use serde_json::Value;
+use url::form_urlencoded;
+
+use super::parse::api_parse_after;
+use crate::bookmark::{Listing, ListingStatus};
+use crate::error::Error;
+use crate::vahti::{fetch_search, sorted_query, SearchSummary, VahtiItem};
+
+pub const API_BASE: &str =
+    "https://www.tori.fi/recommerce-search-page/api/search/SEARCH_ID_BAP_COMMON?";
 
 const TORI_PRICES: [&str; 9] = ["0", "25", "50", "75", "100", "250", "500", "1000", "2000"];
 
-// FIXME: Use url crate to simplify this function
-pub fn vahti_to_api(vahti: &str) -> String {
-    let mut url = "https://api.tori.fi/api/v1.2/public/ads?".to_owned();
-    let args = &vahti[vahti.find('?').unwrap() + 1..];
-    let mut price_set = false;
-    let mut startprice = "";
-    let mut endprice = "";
-    let mut api_args = Vec::<(String, String)>::new();
-    for arg in args.split('&') {
-        let mut parts: Vec<&str> = arg.split('=').collect();
-        if parts.len() == 1 {
-            parts.push("");
-        }
-        match parts[0] {
-            "ps" => {
-                startprice = if let Ok(n) = parts[1].parse::<usize>() {
-                    TORI_PRICES.iter().nth(n).unwrap_or(&parts[1]).to_owned()
-                } else {
-                    ""
-                };
+// The old site used latin-1 in the urls
+const LATIN1_ESCAPES: [(&str, &str); 4] = [
+    ("%E4", "%C3%A4"),
+    ("%C4", "%C3%84"),
+    ("%F6", "%C3%B6"),
+    ("%D6", "%C3%96"),
+];
 
-                price_set = true;
-            }
-            "pe" => {
-                endprice = if let Ok(n) = parts[1].parse::<usize>() {
-                    TORI_PRICES.iter().nth(n).unwrap_or(&parts[1]).to_owned()
+/// Urls of the old tori.fi-site look like `https://www.tori.fi/uusimaa?q=...&cg=...`
+/// while the new ones look like `https://www.tori.fi/recommerce/forsale/search?q=...`
+pub fn is_legacy_url(vahti: &str) -> bool {
+    !vahti.contains("/recommerce/")
+}
+
+fn query_args(vahti: &str) -> Vec<(String, String)> {
+    let query = vahti.find('?').map(|i| &vahti[i + 1..]).unwrap_or_default();
+    form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .filter(|(k, _)| !k.is_empty())
+        .collect()
+}
+
+/// Canonicalizes a vahti url so that equivalent searches share the same url:
+/// the host is `www.tori.fi`, the latin-1 escapes of the old site are converted into utf-8
+/// and the query arguments are sorted with the empty ones dropped
+pub fn canonical_url(vahti: &str) -> String {
+    let vahti = vahti.trim();
+    if let Some(seller) = seller_id_from_url(vahti) {
+        return super::seller::seller_url(seller);
+    }
+
+    let vahti = LATIN1_ESCAPES
+        .iter()
+        .fold(vahti.to_string(), |acc, (l, u)| acc.replace(l, u));
+    let (base, _) = vahti.split_once('?').unwrap_or((&vahti, ""));
+    let path = base
+        .trim_start_matches("https://")
+        .split_once('/')
+        .map(|(_, p)| p.trim_end_matches('/'))
+        .unwrap_or_default();
+
+    format!(
+        "https://www.tori.fi/{}?{}",
+        path,
+        sorted_query(query_args(&vahti))
+    )
+}
+
+/// The region of an old-style vahti, which is the path e.g. `https://www.tori.fi/uusimaa?q=...`
+fn legacy_region(vahti: &str) -> Option<&str> {
+    vahti
+        .split('?')
+        .next()
+        .and_then(|u| u.trim_end_matches('/').rsplit('/').next())
+        .filter(|r| !r.contains('.') && *r != "koko_suomi")
+}
+
+/// Lists the limits of an old-style vahti that the recommerce search can't express.
+/// The category and region codes of the old site are not compatible with the new site
+pub fn legacy_limits(vahti: &str) -> Vec<&'static str> {
+    let args = query_args(vahti);
+    let arg = |key: &str| {
+        args.iter()
+            .any(|(k, v)| k == key && !v.is_empty() && v != "0")
+    };
+
+    let mut limits = vec![];
+    if arg("cg") {
+        limits.push("category");
+    }
+    if legacy_region(vahti).is_some() || arg("m") {
+        limits.push("region");
+    }
+    limits
+}
+
+/// Fails if the old-style vahti would be widened by converting it into the recommerce search,
+/// e.g. from a single category to everything in Finland
+pub fn check_legacy_limits(vahti: &str) -> Result<(), Error> {
+    if !is_legacy_url(vahti) || seller_id_from_url(vahti).is_some() {
+        return Ok(());
+    }
+
+    let limits = legacy_limits(vahti);
+    if limits.is_empty() {
+        return Ok(());
+    }
+
+    warn!(
+        "The {} of {} can't be converted to the new tori.fi",
+        limits.join(" and "),
+        vahti
+    );
+    Err(Error::LegacySearch(limits.join(" and ")))
+}
+
+/// Translates the arguments of an old-style vahti into the arguments
+/// understood by the recommerce search.
+///
+/// The category and region codes of the old site are dropped, see `legacy_limits`.
+/// The rest of the arguments are best-effort.
+fn legacy_args(vahti: &str) -> Vec<(String, String)> {
+    let vahti = LATIN1_ESCAPES
+        .iter()
+        .fold(vahti.to_string(), |acc, (l, u)| acc.replace(l, u));
+
+    let mut args = vec![];
+    for (key, value) in query_args(&vahti) {
+        match key.as_str() {
+            "q" => args.push(("q".to_string(), value)),
+            "ps" | "pe" => {
+                let Ok(n) = value.parse::<usize>() else {
+                    continue;
+                };
+                let price = TORI_PRICES.get(n).map(|p| p.to_string()).unwrap_or(value);
+                let key = if key == "ps" {
+                    "price_from"
                 } else {
-                    ""
+                    "price_to"
                 };
-
-                price_set = true;
+                args.push((key.to_string(), price));
             }
-            "cg" => {
-                if parts[1] != "0" {
-                    api_args.push(("category".to_string(), parts[1].to_string()));
-                }
-            }
-            "st" => api_args.push(("ad_type".to_string(), parts[1].to_string())),
-            "m" => api_args.push(("area".to_string(), parts[1].to_string())),
-            "w" => {
-                let reg: i32 = parts[1].parse().unwrap();
-                if reg >= 100 {
-                    api_args.push(("region".to_string(), (reg - 100).to_string()));
-                }
+            "st" => {
+                let trade_type = match value.as_str() {
+                    "s" => "1",
+                    "k" => "2",
+                    "g" => "3",
+                    _ => continue,
+                };
+                args.push(("trade_type".to_string(), trade_type.to_string()));
             }
-            "ca" => {}
-            _ => api_args.push((parts[0].to_string(), parts[1].to_string())),
+            _ => debug!("Dropping legacy argument {}={}", key, value),
         }
     }
-    for arg in api_args {
-        if arg.0.is_empty() {
-            continue;
-        } else {
-            url += &format!("&{}={}", arg.0, arg.1);
-        }
+    args
+}
+
+/// Converts an old-style vahti url into the corresponding recommerce search url
+pub fn legacy_to_recommerce(vahti: &str) -> String {
+    let query = form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(legacy_args(vahti))
+        .finish();
+    format!("https://www.tori.fi/recommerce/forsale/search?{}", query)
+}
+
+/// Summarizes the search of a vahti url, the region codes of the new site are shown as is
+pub fn summarize_url(vahti: &str) -> SearchSummary {
+    let mut summary = SearchSummary {
+        site: "Tori",
+        seller_id: seller_id_from_url(vahti),
+        ..Default::default()
+    };
+    if summary.seller_id.is_some() {
+        return summary;
     }
-    url = url.replace("%E4", "ä");
-    url = url.replace("%C4", "Ä");
-    url = url.replace("%F6", "ö");
-    url = url.replace("%D6", "Ö");
-    if price_set && (!startprice.is_empty() || !endprice.is_empty()) {
-        url += &format!("&suborder={}-{}", &startprice, &endprice);
+
+    let args = if is_legacy_url(vahti) {
+        summary
+            .regions
+            .extend(legacy_region(vahti).map(String::from));
+        legacy_args(vahti)
+    } else {
+        query_args(vahti)
+    };
+
+    for (key, value) in args {
+        match key.as_str() {
+            "q" if !value.is_empty() => summary.query = Some(value),
+            "price_from" => summary.price_from = Some(value),
+            "price_to" => summary.price_to = Some(value),
+            "location" => summary.regions.push(value),
+            _ => {}
+        }
     }
-    url
-}
-
-pub async fn is_valid_url(url: &str) -> bool {
-    let url = vahti_to_api(url) + "&lim=0";
-    let response = reqwest::get(&url)
-        .await
-        .unwrap()
-        .json::<Value>()
-        .await
-        .unwrap();
-    if let Some(counter_map) = response["counter_map"].as_object() {
-        if let Some(amount) = counter_map["all"].as_i64() {
-            amount > 0
-        } else {
-            false
+    summary
+}
+
+/// Parses the seller id from a seller url, either the old `https://www.tori.fi/li?&aid=...`
+/// or the new `https://www.tori.fi/profile/ads?userId=...`
+pub fn seller_id_from_url(vahti: &str) -> Option<i32> {
+    let key = if vahti.contains("tori.fi/li?") {
+        "aid"
+    } else if vahti.contains("tori.fi/profile/ads?") {
+        "userId"
+    } else {
+        return None;
+    };
+
+    query_args(vahti)
+        .into_iter()
+        .find(|(k, _)| k == key)
+        .and_then(|(_, v)| v.parse().ok())
+}
+
+pub fn vahti_to_api(vahti: &str) -> String {
+    let args = if let Some(seller) = seller_id_from_url(vahti) {
+        vec![("userId".to_string(), seller.to_string())]
+    } else if is_legacy_url(vahti) {
+        legacy_args(vahti)
+    } else {
+        query_args(vahti)
+    };
+
+    let mut serializer =
+        form_urlencoded::Serializer::for_suffix(API_BASE.to_string(), API_BASE.len());
+    for (key, value) in args {
+        match key.as_str() {
+            // These are set by us
+            "sort" | "page" => {}
+            _ => {
+                serializer.append_pair(&key, &value);
+            }
         }
+    }
+    serializer.append_pair("sort", "PUBLISHED_DESC");
+    serializer.finish()
+}
+
+/// Parses the number of listings matching the search from an api response
+pub fn match_count(body: &str) -> Option<i64> {
+    let response = serde_json::from_str::<Value>(body).ok()?;
+    response["metadata"]["result_size"]["match_count"].as_i64()
+}
+
+/// Fetches the search of the url from the api, returning the number of matches
+/// along with the newest matching items
+pub async fn validate_url(url: &str) -> Result<(i64, Vec<VahtiItem>), Error> {
+    check_legacy_limits(url)?;
+    let body = fetch_search(url, &vahti_to_api(url)).await?;
+    let count = match_count(&body).ok_or_else(|| Error::InvalidUrl(url.to_string()))?;
+    Ok((count, api_parse_after(&body, 0)?))
+}
+
+/// Parses the item id from an item url like `https://www.tori.fi/recommerce/forsale/item/123456`
+pub fn item_id_from_url(url: &str) -> Option<i64> {
+    let (_, id) = url.split_once("/item/")?;
+    id.split(['/', '?']).next().and_then(|id| id.parse().ok())
+}
+
+static DISPOSED_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#""disposed"\s*:\s*true"#).unwrap());
+static INACTIVE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#""(?:adStatus|ad_status)"\s*:\s*"(?:DISABLED|INACTIVE|EXPIRED|DELETED)""#).unwrap()
+});
+static SOLD_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#""(?:adStatus|ad_status)"\s*:\s*"SOLD""#).unwrap());
+
+/// Reads the status of the listing from the data the item page is hydrated with.
+/// A sold item is marked disposed and a listing taken down by the seller or tori
+/// has an inactive ad status
+pub fn listing_status(page: &str) -> ListingStatus {
+    if DISPOSED_REGEX.is_match(page) || SOLD_REGEX.is_match(page) {
+        ListingStatus::Sold
+    } else if INACTIVE_REGEX.is_match(page) {
+        ListingStatus::Removed
     } else {
-        false
+        ListingStatus::Active
+    }
+}
+
+// NOTE: There's no public api for single listings, so the item page is used instead
+pub async fn fetch_listing(id: i64) -> Result<Listing, Error> {
+    let url = format!("https://www.tori.fi/recommerce/forsale/item/{}", id);
+    let response = reqwest::get(&url).await?;
+    if matches!(response.status(), StatusCode::NOT_FOUND | StatusCode::GONE) {
+        return Ok(Listing::removed());
     }
+
+    let page = response.error_for_status()?.text().await?;
+    let title = page
+        .split_once("<title>")
+        .and_then(|(_, t)| t.split_once("</title>"))
+        .map(|(t, _)| t.split(" | ").next().unwrap_or(t).trim().to_string())
+        .unwrap_or_else(|| url.clone());
+
+    Ok(Listing {
+        title,
+        url,
+        status: listing_status(&page),
+    })
 }
@@ -1,6 +1,6 @@
 use serde_json::Value;
 
-use super::models::FullToriItem;
+use super::models::ToriItem;
 use crate::error::Error;
 use crate::vahti::VahtiItem;
 
@@ -9,10 +9,12 @@ pub fn api_parse_after(json: &str, after: i64) -> Result<Vec<VahtiItem>, Error>
     let mut items = vec![];
     let mut past_weirdness = false;
 
-    if let Some(ads) = response_json["list_ads"].as_array() {
+    if let Some(ads) = response_json["docs"].as_array() {
         for ad in ads {
-            let ad_object = &ad.as_object().ok_or(Error::Tori)?["ad"];
-            let fullitem: FullToriItem = serde_json::from_value(ad_object.to_owned())?;
+            if !ad.is_object() {
+                return Err(Error::Tori);
+            }
+            let fullitem: ToriItem = serde_json::from_value(ad.to_owned())?;
             let item = VahtiItem::from(fullitem);
 
             if item.published <= after {
@@ -1,22 +1,23 @@
-use serde_json::Value;
-
 use crate::error::Error;
 
+/// Reads the name of the seller from the title of the profile page, e.g. `Matti | Tori`
+pub fn seller_name_from_profile(page: &str) -> Option<String> {
+    let (_, title) = page.split_once("<title>")?;
+    let (title, _) = title.split_once("</title>")?;
+    let name = title.split(" | ").next()?.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
 pub async fn get_seller_name_from_id(sellerid: i32) -> Result<String, Error> {
-    let url = format!(
-        "https://api.tori.fi/api/v1.2/public/ads?account={}&lim=1",
-        sellerid
-    );
-    let response = reqwest::get(&url).await?.text().await?;
-    let response_json: Value = serde_json::from_str(&response)?;
-    if let Some(ads) = response_json["list_ads"].as_array() {
-        if ads.is_empty() {
-            return Ok(String::from("Unknown Seller"));
-        }
-        return Ok(ads[0].as_object().unwrap()["ad"]["user"]["account"]["name"]
-            .as_str()
-            .unwrap_or("Unknown Seller")
-            .to_string());
-    }
-    Ok(String::from("Unknown Seller"))
+    let page = reqwest::get(&seller_url(sellerid))
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    Ok(seller_name_from_profile(&page).unwrap_or(String::from("Unknown Seller")))
+}
+
+/// The url used for following the listings of a seller
+pub fn seller_url(sellerid: i32) -> String {
+    format!("https://www.tori.fi/profile/ads?userId={}", sellerid)
 }
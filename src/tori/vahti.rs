@@ -5,6 +5,7 @@ use regex::Regex;
 
 use crate::database::Database;
 use crate::error::Error;
+use crate::filters::VahtiFilters;
 use crate::itemhistory::ItemHistoryStorage;
 use crate::models::DbVahti;
 use crate::tori::api::*;
@@ -13,7 +14,7 @@ use crate::tori::parse::*;
 pub static TORI_REGEX: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"^https://(m\.|www\.)?tori\.fi/.*\?.*$").unwrap());
 
-use crate::vahti::{Vahti, VahtiItem};
+use crate::vahti::{is_price_drop, Vahti, VahtiItem};
 
 #[derive(Debug, Clone)]
 pub struct ToriVahti {
@@ -23,40 +24,75 @@ pub struct ToriVahti {
     pub user_id: u64,
     pub last_updated: i64,
     pub site_id: i32,
+    pub filters: VahtiFilters,
+    pub digest: Option<String>,
+    pub last_digest: i64,
 }
 
 #[async_trait]
 impl Vahti for ToriVahti {
+    async fn fetch_items(&self, after: i64) -> Result<Vec<VahtiItem>, Error> {
+        debug!("Fetching {}", self.url);
+        check_legacy_limits(&self.url)?;
+        let res = reqwest::get(vahti_to_api(&self.url))
+            .await?
+            .text()
+            .await?
+            .to_string();
+
+        api_parse_after(&res, after)
+    }
+
     async fn update(
         &mut self,
         db: &Database,
         ihs: ItemHistoryStorage,
+        items: &[VahtiItem],
     ) -> Result<Vec<VahtiItem>, Error> {
         debug!("Updating {}", self.url);
         let ihref = ihs
             .get(&(self.user_id, self.delivery_method))
             .expect("bug: impossible");
 
-        let res = reqwest::get(vahti_to_api(&self.url))
-            .await?
-            .text()
-            .await?
-            .to_string();
+        let bind = |i: &VahtiItem| {
+            // FIXME: Somewhat sketchy
+            let mut newi = i.clone();
+            newi.vahti_url = Some(self.url.clone());
+            newi.deliver_to = Some(self.user_id);
+            newi.delivery_method = Some(self.delivery_method);
+            newi.vahti_id = Some(self.id);
+            newi
+        };
 
         let mut ih = ihref.lock().unwrap().clone();
-        let ret = api_parse_after(&res, self.last_updated)?
+        let mut price_changes = vec![];
+        let ret = items
             .iter()
+            .filter(|i| self.filters.matches(i))
             .filter_map(|i| {
-                if !ih.contains(i.ad_id, i.site_id) {
-                    ih.add_item(i.ad_id, i.site_id, chrono::Local::now().timestamp());
+                if let Some(old_price) = ih.price(i.ad_id, i.site_id) {
+                    if old_price == i.price {
+                        return None;
+                    }
+
+                    ih.set_price(i.ad_id, i.site_id, i.price);
+                    price_changes.push((i.ad_id, i.site_id, i.price));
 
-                    // FIXME: Somewhat sketchy
-                    let mut newi = i.clone();
-                    newi.vahti_url = Some(self.url.clone());
-                    newi.deliver_to = Some(self.user_id);
-                    newi.delivery_method = Some(self.delivery_method);
+                    if !is_price_drop(old_price, i.price) {
+                        return None;
+                    }
 
+                    let mut newi = bind(i);
+                    newi.previous_price = Some(old_price);
                     Some(newi)
+                } else if i.published > self.last_updated {
+                    ih.add_item(
+                        i.ad_id,
+                        i.site_id,
+                        chrono::Local::now().timestamp(),
+                        i.price,
+                    );
+                    Some(bind(i))
                 } else {
                     None
                 }
@@ -69,11 +105,32 @@ impl Vahti for ToriVahti {
             *locked = ih;
         }
 
-        if ret.is_empty() {
-            return Ok(vec![]);
+        if !price_changes.is_empty() {
+            db.update_seen_item_prices(self.user_id as i64, self.delivery_method, &price_changes)
+                .await?;
+        }
+
+        let new_items = ret
+            .iter()
+            .filter(|i| i.previous_price.is_none())
+            .collect::<Vec<_>>();
+
+        if new_items.is_empty() {
+            return Ok(ret);
         }
 
-        db.vahti_updated(self.to_db(), ret.iter().map(|i| i.published).max())
+        db.add_seen_items(
+            self.user_id as i64,
+            self.delivery_method,
+            self.id,
+            &new_items
+                .iter()
+                .map(|i| (i.ad_id, i.site_id, i.price))
+                .collect::<Vec<_>>(),
+        )
+        .await?;
+
+        db.vahti_updated(self.to_db(), new_items.iter().map(|i| i.published).max())
             .await?;
 
         Ok(ret)
@@ -83,8 +140,12 @@ impl Vahti for ToriVahti {
         TORI_REGEX.is_match(url)
     }
 
-    async fn validate_url(&self) -> Result<bool, Error> {
-        Ok(is_valid_url(&self.url).await)
+    fn canonicalize_url(url: &str) -> String {
+        canonical_url(url)
+    }
+
+    async fn validate_url(&self) -> Result<(i64, Vec<VahtiItem>), Error> {
+        validate_url(&self.url).await
     }
 
     fn from_db(v: DbVahti) -> Result<Self, Error> {
@@ -97,6 +158,9 @@ impl Vahti for ToriVahti {
             last_updated: v.last_updated,
             site_id: super::ID,
             delivery_method: v.delivery_method,
+            filters: VahtiFilters::from_db(v.filters.as_deref())?,
+            digest: v.digest,
+            last_digest: v.last_digest,
         })
     }
 
@@ -108,6 +172,15 @@ impl Vahti for ToriVahti {
             user_id: self.user_id as i64,
             last_updated: self.last_updated,
             site_id: self.site_id,
+            filters: self.filters.to_db(),
+            digest: self.digest.clone(),
+            last_digest: self.last_digest,
+            // NOTE: Paused and snoozed Vahtis are never updated
+            paused: false,
+            last_error: None,
+            snoozed_until: None,
+            creator_id: None,
+            disabled: false,
         }
     }
 }
@@ -0,0 +1,89 @@
+use itertools::Itertools;
+
+use crate::database::Database;
+use crate::error::Error;
+
+fn delivery_method_name(delivery_method: i32) -> String {
+    match crate::identity::endpoint_name(delivery_method) {
+        Some(name) => name.to_string(),
+        None => format!("delivery method {}", delivery_method),
+    }
+}
+
+/// Records the outcome of a delivery to the user. The Vahtis of a user that could not be
+/// reached UNREACHABLE_FAILURE_LIMIT times in a row are disabled and the user is moved
+/// to the new id if the recipient has migrated
+pub async fn record_delivery(
+    db: &Database,
+    userid: i64,
+    delivery_method: i32,
+    res: &Result<(), Error>,
+) -> Result<(), Error> {
+    // NOTE: A migrated group is still there, only its id has changed
+    if let Some(new) = res.as_ref().err().and_then(Error::migrated_to) {
+        return db.migrate_user(userid, new, delivery_method).await;
+    }
+
+    match res {
+        Ok(()) => {
+            db.clear_delivery_failures(userid, delivery_method).await?;
+        }
+        Err(e) if e.is_unreachable() => {
+            let failures = db.add_delivery_failure(userid, delivery_method).await?;
+            if failures >= *crate::UNREACHABLE_FAILURE_LIMIT {
+                let disabled = db.disable_vahtis(userid, delivery_method).await?;
+                warn!(
+                    "{} ({}) could not be reached {} times in a row, disabled {} Vahtis: {}",
+                    userid, delivery_method, failures, disabled, e
+                );
+                info!("{}", report(db).await?);
+            }
+        }
+        // NOTE: Other errors don't tell anything about the user
+        Err(_) => {}
+    }
+
+    Ok(())
+}
+
+/// Enables the Vahtis that were disabled while the user could not be reached, returning
+/// an explanation the first time the user uses a command after that
+pub async fn welcome_back(db: &Database, userid: u64, delivery_method: i32) -> Option<String> {
+    let enabled = db
+        .enable_vahtis(userid as i64, delivery_method)
+        .await
+        .map_err(|e| error!("Failed to enable the Vahtis of {}: {}", userid, e))
+        .ok()?;
+    if enabled == 0 {
+        return None;
+    }
+
+    info!(
+        "{} ({}) is back, enabled {} Vahtis",
+        userid, delivery_method, enabled
+    );
+    Some(format!(
+        "Welcome back! Your {} Vahtis were disabled because the bot could not deliver messages to you. \
+        They are now enabled again, the listings posted in the meantime are skipped",
+        enabled
+    ))
+}
+
+/// Lists how many Vahtis have been disabled because their users could not be reached
+pub async fn report(db: &Database) -> Result<String, Error> {
+    let counts = db.count_disabled_vahtis().await?;
+    if counts.is_empty() {
+        return Ok(String::from(
+            "No Vahtis have been disabled for unreachable users",
+        ));
+    }
+
+    Ok(format!(
+        "Vahtis disabled for unreachable users: {} ({} in total)",
+        counts
+            .iter()
+            .map(|(d, c)| format!("{} {}", delivery_method_name(*d), c))
+            .join(", "),
+        counts.values().sum::<usize>()
+    ))
+}
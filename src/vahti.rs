@@ -1,27 +1,43 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::sync::{Arc, LazyLock, Mutex};
 
 use async_trait::async_trait;
 use futures::stream::{self, StreamExt};
 use itertools::Itertools;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+use url::form_urlencoded;
 
 use crate::database::Database;
 use crate::delivery::perform_delivery;
 use crate::error::Error;
+use crate::filters::VahtiFilters;
 #[cfg(feature = "huutonet")]
 use crate::huutonet::vahti::HuutonetVahti;
 use crate::itemhistory::{ItemHistory, ItemHistoryStorage};
 use crate::models::DbVahti;
+use crate::settings::UserSettings;
 #[cfg(feature = "tori")]
 use crate::tori::vahti::ToriVahti;
 use crate::Torimies;
 
-static SITES: LazyLock<Vec<(&LazyLock<Regex>, i32)>> = LazyLock::new(|| {
+/// The url regex, the id and the url canonicalization of each supported site
+#[allow(clippy::type_complexity)]
+static SITES: LazyLock<Vec<(&LazyLock<Regex>, i32, fn(&str) -> String)>> = LazyLock::new(|| {
     vec![
         #[cfg(feature = "tori")]
-        (&crate::tori::vahti::TORI_REGEX, crate::tori::ID),
+        (
+            &crate::tori::vahti::TORI_REGEX,
+            crate::tori::ID,
+            ToriVahti::canonicalize_url,
+        ),
         #[cfg(feature = "huutonet")]
-        (&crate::huutonet::vahti::HUUTONET_REGEX, crate::huutonet::ID),
+        (
+            &crate::huutonet::vahti::HUUTONET_REGEX,
+            crate::huutonet::ID,
+            HuutonetVahti::canonicalize_url,
+        ),
     ]
 });
 
@@ -32,21 +48,34 @@ pub trait Vahti
 where
     Self: Sized + Send + Sync,
 {
+    /// Fetches the items matching the url of the Vahti that were published after `after`.
+    /// The returned items are not yet bound to any user
+    async fn fetch_items(&self, after: i64) -> Result<Vec<VahtiItem>, Error>;
+    /// Picks the items that are new to this Vahti from the fetched `items`
     async fn update(
         &mut self,
         db: &Database,
         ihs: ItemHistoryStorage,
+        items: &[VahtiItem],
     ) -> Result<Vec<VahtiItem>, Error>;
-    async fn validate_url(&self) -> Result<bool, Error>;
+    /// Checks the url against the live site, returning the number of listings
+    /// currently matching it along with the newest of them
+    async fn validate_url(&self) -> Result<(i64, Vec<VahtiItem>), Error>;
     fn is_valid_url(&self, url: &str) -> bool;
+    /// Rewrites the url into a canonical form, so that urls of the same search are equal
+    fn canonicalize_url(url: &str) -> String;
     fn from_db(v: DbVahti) -> Result<Self, Error>;
     fn to_db(&self) -> DbVahti;
 }
 
-#[derive(Clone, Debug, PartialEq)]
+// NOTE: Serialized when the item is buffered for a digest
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct VahtiItem {
     pub deliver_to: Option<u64>,
     pub delivery_method: Option<i32>,
+    /// The id of the Vahti that found the item
+    #[serde(default)]
+    pub vahti_id: Option<i32>,
     pub site_id: i32,
     pub title: String,
     pub vahti_url: Option<String>,
@@ -59,43 +88,466 @@ pub struct VahtiItem {
     pub location: String,
     pub ad_type: String,
     pub ad_id: i64,
+    /// Set when the item has already been delivered and its price has dropped since
+    pub previous_price: Option<i64>,
 }
 
+impl VahtiItem {
+    fn digest_key(&self) -> (String, u64, i32) {
+        (
+            self.vahti_url.clone().expect("bug: impossible"),
+            self.deliver_to.expect("bug: impossible"),
+            self.delivery_method.expect("bug: impossible"),
+        )
+    }
+
+    pub fn is_auction(&self) -> bool {
+        #[cfg(feature = "huutonet")]
+        if self.site_id == crate::huutonet::ID {
+            return self.ad_type == "auction";
+        }
+        false
+    }
+}
+
+/// A human-readable summary of the search behind a Vahti url
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchSummary {
+    pub site: &'static str,
+    pub query: Option<String>,
+    pub seller_id: Option<i32>,
+    pub price_from: Option<String>,
+    pub price_to: Option<String>,
+    pub regions: Vec<String>,
+}
+
+impl fmt::Display for SearchSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.site)?;
+        match (&self.seller_id, &self.query) {
+            (Some(seller), _) => write!(f, "myyjä {}", seller)?,
+            (None, Some(query)) => write!(f, "\"{}\"", query)?,
+            (None, None) => write!(f, "kaikki ilmoitukset")?,
+        }
+        match (&self.price_from, &self.price_to) {
+            (Some(from), Some(to)) => write!(f, ", {}–{}€", from, to)?,
+            (Some(from), None) => write!(f, ", yli {}€", from)?,
+            (None, Some(to)) => write!(f, ", alle {}€", to)?,
+            (None, None) => {}
+        }
+        if !self.regions.is_empty() {
+            write!(f, ", alue: {}", self.regions.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Summarizes the search of a Vahti url for listing the Vahtis
+pub fn summarize_url(url: &str) -> SearchSummary {
+    #[cfg(feature = "tori")]
+    if url.contains("tori.fi/") {
+        return crate::tori::api::summarize_url(url);
+    }
+    #[cfg(feature = "huutonet")]
+    if url.contains("huuto.net/") {
+        return crate::huutonet::api::summarize_url(url);
+    }
+    SearchSummary {
+        site: "Tuntematon",
+        query: Some(url.to_string()),
+        ..Default::default()
+    }
+}
+
+/// Sorts the query arguments of an url and drops the empty ones,
+/// the arguments are encoded the same way regardless of the original encoding
+pub fn sorted_query(args: Vec<(String, String)>) -> String {
+    form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(
+            args.into_iter()
+                .filter(|(k, v)| !k.is_empty() && !v.is_empty())
+                .sorted(),
+        )
+        .finish()
+}
+
+/// Canonicalizes the url with the rules of its site, see `Vahti::canonicalize_url`
+pub fn canonicalize_url(url: &str) -> String {
+    let url = url.trim();
+    SITES
+        .iter()
+        .find(|(r, _, _)| r.is_match(url))
+        .map(|(_, _, canonicalize)| canonicalize(url))
+        .unwrap_or_else(|| url.to_string())
+}
+
+/// The version of `canonicalize_url`, the stored Vahtis are canonicalized again when
+/// this is bumped
+const CANONICALIZATION_VERSION: i32 = 1;
+
+/// Plans the canonicalization of the stored Vahtis, returning the urls to rewrite
+/// as (id, url) pairs and the duplicates to remove as (id, id of the kept Vahti) pairs.
+/// Of the Vahtis of a user sharing the same canonical url the oldest one is kept
+#[allow(clippy::type_complexity)]
+pub fn canonicalization_plan(vahtis: &[DbVahti]) -> (Vec<(i32, String)>, Vec<(i32, i32)>) {
+    let mut rewrites = vec![];
+    let mut removed = vec![];
+
+    let groups = vahtis
+        .iter()
+        .sorted_by_key(|v| v.id)
+        .into_group_map_by(|v| (canonicalize_url(&v.url), v.user_id, v.delivery_method));
+
+    for ((url, _, _), group) in groups.into_iter().sorted_by_key(|(_, g)| g[0].id) {
+        let (keep, duplicates) = group.split_first().expect("bug: impossible");
+        if keep.url != url {
+            rewrites.push((keep.id, url));
+        }
+        removed.extend(duplicates.iter().map(|v| (v.id, keep.id)));
+    }
+
+    (rewrites, removed)
+}
+
+/// Canonicalizes the urls of the stored Vahtis and removes the duplicates once,
+/// so that Vahtis added before the canonicalization are covered too
+pub async fn canonicalize_vahtis(db: &Database) -> Result<(), Error> {
+    if db.fetch_data_version("canonical_urls").await? >= CANONICALIZATION_VERSION {
+        return Ok(());
+    }
+
+    let vahtis = db.fetch_all_vahtis().await?;
+    let (rewrites, removed) = canonicalization_plan(&vahtis);
+    // NOTE: The duplicates may have had their own filters or destinations, which are
+    // logged so that they can be restored by hand
+    for (duplicate, kept) in &removed {
+        let v = vahtis
+            .iter()
+            .find(|v| v.id == *duplicate)
+            .expect("bug: impossible");
+        warn!(
+            "Removing Vahti {} of {} ({}) as a duplicate of Vahti {}: {} filters: {:?} digest: {:?}",
+            v.id, v.user_id, v.delivery_method, kept, v.url, v.filters, v.digest
+        );
+    }
+
+    let removed = removed.into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+    if !rewrites.is_empty() || !removed.is_empty() {
+        db.rewrite_vahti_urls(&rewrites, &removed).await?;
+    }
+    db.set_data_version("canonical_urls", CANONICALIZATION_VERSION)
+        .await?;
+    Ok(())
+}
+
+/// Formats the time elapsed since `timestamp` e.g. "3 h sitten"
+pub fn format_elapsed(now: i64, timestamp: i64) -> String {
+    if timestamp <= 0 {
+        return String::from("ei koskaan");
+    }
+
+    match (now - timestamp).max(0) {
+        s if s < 60 => String::from("juuri nyt"),
+        s if s < 3600 => format!("{} min sitten", s / 60),
+        s if s < 86400 => format!("{} h sitten", s / 3600),
+        s => format!("{} pv sitten", s / 86400),
+    }
+}
+
+/// Copies the item to each of the linked endpoints its Vahti delivers to.
+/// The items of Vahtis without destinations are delivered to the owner of the Vahti
+pub fn fan_out(item: VahtiItem, destinations: &HashMap<i32, Vec<(u64, i32)>>) -> Vec<VahtiItem> {
+    match item.vahti_id.and_then(|id| destinations.get(&id)) {
+        Some(ds) if !ds.is_empty() => ds
+            .iter()
+            .map(|(deliver_to, delivery_method)| {
+                let mut i = item.clone();
+                i.deliver_to = Some(*deliver_to);
+                i.delivery_method = Some(*delivery_method);
+                i
+            })
+            .collect(),
+        _ => vec![item],
+    }
+}
+
+/// Groups the destinations of the Vahtis by the id of the Vahti
+pub fn destinations_by_vahti(
+    destinations: Vec<crate::models::VahtiDestination>,
+) -> HashMap<i32, Vec<(u64, i32)>> {
+    destinations
+        .into_iter()
+        .into_group_map_by(|d| d.vahti_id)
+        .into_iter()
+        .map(|(id, ds)| {
+            (
+                id,
+                ds.into_iter()
+                    .map(|d| (d.user_id as u64, d.delivery_method))
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+/// Whether the price of an already delivered item has dropped enough to be notified about
+pub fn is_price_drop(old_price: i64, new_price: i64) -> bool {
+    // NOTE: A price of 0 means that the price is unknown
+    if old_price <= 0 || new_price >= old_price {
+        return false;
+    }
+
+    let drop = old_price - new_price;
+    drop * 100 >= old_price * *crate::PRICE_DROP_PERCENT || drop >= *crate::PRICE_DROP_EUROS
+}
+
+/// Resolves the destination of a Vahti into a (deliver_to, delivery_method) pair.
+/// Without a destination the items are delivered to the user themself,
+/// otherwise the destination is e.g. `webhook:3`, `email:2` or `push:1` and must be owned by the user
+#[allow(unused_variables)]
+pub async fn resolve_destination(
+    db: &Database,
+    destination: Option<&str>,
+    userid: u64,
+    delivery_method: i32,
+) -> Result<(u64, i32), Error> {
+    let Some(destination) = destination.map(str::trim).filter(|d| !d.is_empty()) else {
+        return Ok((userid, delivery_method));
+    };
+
+    let invalid = || Error::InvalidDestination(destination.to_string());
+    let (kind, id) = destination.split_once(':').ok_or_else(invalid)?;
+    let id = id.parse::<i32>().map_err(|_| invalid())?;
+
+    match kind {
+        #[cfg(feature = "webhook-delivery")]
+        crate::delivery::webhook::NAME => match db.fetch_webhook(id).await {
+            Ok(w) if w.user_id == userid as i64 && w.delivery_method == delivery_method => {
+                Ok((id as u64, crate::delivery::webhook::ID))
+            }
+            _ => Err(invalid()),
+        },
+        #[cfg(feature = "email-delivery")]
+        crate::delivery::email::NAME => match db.fetch_email_address(id).await {
+            Ok(a)
+                if a.verified
+                    && a.user_id == userid as i64
+                    && a.delivery_method == delivery_method =>
+            {
+                Ok((id as u64, crate::delivery::email::ID))
+            }
+            _ => Err(invalid()),
+        },
+        #[cfg(feature = "push-delivery")]
+        crate::delivery::push::NAME => match db.fetch_push_target(id).await {
+            Ok(t) if t.user_id == userid as i64 && t.delivery_method == delivery_method => {
+                Ok((id as u64, crate::delivery::push::ID))
+            }
+            _ => Err(invalid()),
+        },
+        _ => Err(invalid()),
+    }
+}
+
+/// How many of the newest matches are shown when a Vahti is added
+const PREVIEW_ITEMS: usize = 3;
+
+/// Fetches the api response of a search, telling an unreachable site apart from an url
+/// that the site doesn't accept
+pub async fn fetch_search(url: &str, api_url: &str) -> Result<String, Error> {
+    let unreachable = |e: reqwest::Error| Error::SiteUnreachable(e.to_string());
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(15))
+        .build()?;
+
+    let response = client.get(api_url).send().await.map_err(unreachable)?;
+    let status = response.status();
+    if status.is_client_error() {
+        return Err(Error::InvalidUrl(url.to_string()));
+    }
+    if !status.is_success() {
+        return Err(Error::SiteUnreachable(status.to_string()));
+    }
+    response.text().await.map_err(unreachable)
+}
+
+/// Runs the validation of the site on a new Vahti, the preview only shows items matching the filters
+async fn validate_new_vahti<V: Vahti>(
+    v: DbVahti,
+    filters: &VahtiFilters,
+) -> Result<(i64, Vec<VahtiItem>), Error> {
+    let (count, items) = V::from_db(v)?.validate_url().await?;
+    let items = items
+        .into_iter()
+        .filter(|i| filters.matches(i))
+        .sorted_by_key(|i| -i.published)
+        .take(PREVIEW_ITEMS)
+        .collect();
+    Ok((count, items))
+}
+
+/// Formats the validation result shown when a Vahti is added
+pub fn format_preview(count: i64, items: &[VahtiItem]) -> String {
+    if count == 0 {
+        return String::from(
+            "The search has 0 results currently, you will be notified once matching listings appear",
+        );
+    }
+
+    let mut msg = format!("The search has {} results currently", count);
+    if !items.is_empty() {
+        msg.push_str(", the newest matches are");
+        for i in items {
+            msg.push_str(&format!("\n{} {}€ {}", i.title, i.price, i.url));
+        }
+    }
+    msg
+}
+
+/// Adds a new Vahti, `creator` being the user who added it when `userid` is a group or a channel
 pub async fn new_vahti(
     db: Database,
     url: &str,
     userid: u64,
     delivery_method: i32,
+    creator: Option<u64>,
+    filters: VahtiFilters,
 ) -> Result<String, Error> {
-    let Some(site_id) = SITES
+    let Some((site_id, url)) = SITES
         .iter()
-        .find(|(r, _)| r.is_match(url))
-        .map(|(_, sid)| *sid)
+        .find(|(r, _, _)| r.is_match(url.trim()))
+        .map(|(_, sid, canonicalize)| (*sid, canonicalize(url)))
     else {
         return Err(Error::UnknownUrl(url.to_string()));
     };
+    let url = url.as_str();
 
-    if db.fetch_vahti(url, userid as i64).await.is_ok() {
+    if db
+        .fetch_vahti(url, userid as i64, delivery_method)
+        .await
+        .is_ok()
+    {
         info!("Not adding a pre-defined Vahti {} for user {}", url, userid);
         return Err(Error::VahtiExists);
     }
 
+    let template = DbVahti {
+        id: 0,
+        url: url.to_string(),
+        user_id: userid as i64,
+        last_updated: 0,
+        site_id,
+        delivery_method,
+        filters: filters.to_db(),
+        digest: None,
+        last_digest: 0,
+        paused: false,
+        last_error: None,
+        snoozed_until: None,
+        creator_id: creator.map(|c| c as i64),
+        disabled: false,
+    };
+    let (count, items) = match site_id {
+        #[cfg(feature = "tori")]
+        crate::tori::ID => validate_new_vahti::<ToriVahti>(template, &filters).await?,
+        #[cfg(feature = "huutonet")]
+        crate::huutonet::ID => validate_new_vahti::<HuutonetVahti>(template, &filters).await?,
+        i => panic!("Unsupported site_id {}", i),
+    };
+    let preview = format_preview(count, &items);
+
     match db
-        .add_vahti_entry(url, userid as i64, site_id, delivery_method)
+        .add_vahti_entry(
+            url,
+            userid as i64,
+            site_id,
+            delivery_method,
+            filters.to_db(),
+            creator.map(|c| c as i64),
+        )
         .await
     {
-        Ok(_) => Ok(String::from("Vahti added succesfully")),
+        Ok(_) if filters.is_empty() => Ok(format!("Vahti added succesfully\n{}", preview)),
+        Ok(_) => Ok(format!("Vahti added succesfully\n{}\n{}", filters, preview)),
         Err(e) => Err(e),
     }
 }
 
+/// Adds a Vahti that follows every new listing of the given seller
+pub async fn follow_seller(
+    db: Database,
+    site_id: i32,
+    seller_id: i32,
+    userid: u64,
+    delivery_method: i32,
+) -> Result<String, Error> {
+    let url = match site_id {
+        #[cfg(feature = "tori")]
+        crate::tori::ID => crate::tori::seller::seller_url(seller_id),
+        #[cfg(feature = "huutonet")]
+        crate::huutonet::ID => crate::huutonet::seller::seller_url(seller_id),
+        i => panic!("Unsupported site_id {}", i),
+    };
+
+    new_vahti(
+        db,
+        &url,
+        userid,
+        delivery_method,
+        Some(userid),
+        VahtiFilters::default(),
+    )
+    .await
+}
+
+/// Replaces the filters of an existing Vahti, returning the new filters
+pub async fn set_vahti_filters(
+    db: Database,
+    url: &str,
+    userid: u64,
+    delivery_method: i32,
+    filters: VahtiFilters,
+) -> Result<String, Error> {
+    let url = &canonicalize_url(url);
+    if db
+        .fetch_vahti(url, userid as i64, delivery_method)
+        .await
+        .is_err()
+    {
+        return Ok(
+            "A Vahti is not defined with that url. Make sure the url is correct".to_string(),
+        );
+    }
+
+    db.update_vahti_filters(url, userid as i64, delivery_method, filters.to_db())
+        .await?;
+    Ok(format!("Filters updated\n{}", filters))
+}
+
+/// Returns the filters of an existing Vahti in a human-readable form
+pub async fn show_vahti_filters(
+    db: Database,
+    url: &str,
+    userid: u64,
+    delivery_method: i32,
+) -> Result<String, Error> {
+    let url = &canonicalize_url(url);
+    let vahti = db.fetch_vahti(url, userid as i64, delivery_method).await?;
+    Ok(VahtiFilters::from_db(vahti.filters.as_deref())?.to_string())
+}
+
 pub async fn remove_vahti(
     db: Database,
     url: &str,
     userid: u64,
     delivery_method: i32,
 ) -> Result<String, Error> {
-    if db.fetch_vahti(url, userid as i64).await.is_err() {
+    let url = &canonicalize_url(url);
+    if db
+        .fetch_vahti(url, userid as i64, delivery_method)
+        .await
+        .is_err()
+    {
         info!("Not removing a nonexistant vahti!");
         return Ok(
             "A Vahti is not defined with that url. Make sure the url is correct".to_string(),
@@ -110,72 +562,241 @@ pub async fn remove_vahti(
     }
 }
 
+/// Parses a duration like `30m`, `12h`, `3d` or `2w` into seconds
+pub fn parse_duration(s: &str) -> Result<i64, Error> {
+    let s = s.trim();
+    let unit = match s.chars().last() {
+        Some('m') => 60,
+        Some('h') => 60 * 60,
+        Some('d') => 24 * 60 * 60,
+        Some('w') => 7 * 24 * 60 * 60,
+        _ => return Err(Error::InvalidDuration(s.to_string())),
+    };
+
+    match s[..s.len() - 1].parse::<i64>() {
+        Ok(n) if n > 0 && n <= 365 * 24 * 60 * 60 / unit => Ok(n * unit),
+        _ => Err(Error::InvalidDuration(s.to_string())),
+    }
+}
+
+async fn fetch_own_vahti(
+    db: &Database,
+    url: &str,
+    userid: u64,
+    delivery_method: i32,
+) -> Option<DbVahti> {
+    db.fetch_vahti(&canonicalize_url(url), userid as i64, delivery_method)
+        .await
+        .ok()
+}
+
+/// Pauses a Vahti until it is resumed
+pub async fn pause_vahti(
+    db: Database,
+    url: &str,
+    userid: u64,
+    delivery_method: i32,
+) -> Result<String, Error> {
+    let Some(vahti) = fetch_own_vahti(&db, url, userid, delivery_method).await else {
+        return Ok(
+            "A Vahti is not defined with that url. Make sure the url is correct".to_string(),
+        );
+    };
+
+    db.pause_vahti(vahti.id).await?;
+    Ok(String::from("Vahti paused"))
+}
+
+/// Resumes a paused or snoozed Vahti, the items published in the meantime are not delivered
+pub async fn resume_vahti(
+    db: Database,
+    url: &str,
+    userid: u64,
+    delivery_method: i32,
+) -> Result<String, Error> {
+    let Some(vahti) = fetch_own_vahti(&db, url, userid, delivery_method).await else {
+        return Ok(
+            "A Vahti is not defined with that url. Make sure the url is correct".to_string(),
+        );
+    };
+
+    if !vahti.paused && vahti.snoozed_until.is_none() {
+        return Ok(String::from("The Vahti is not paused"));
+    }
+
+    db.resume_vahti(vahti.id).await?;
+    Ok(String::from("Vahti resumed"))
+}
+
+/// Pauses a Vahti for `duration` seconds
+pub async fn snooze_vahti(
+    db: Database,
+    url: &str,
+    userid: u64,
+    delivery_method: i32,
+    duration: i64,
+) -> Result<String, Error> {
+    let Some(vahti) = fetch_own_vahti(&db, url, userid, delivery_method).await else {
+        return Ok(
+            "A Vahti is not defined with that url. Make sure the url is correct".to_string(),
+        );
+    };
+
+    let until = chrono::Local::now().timestamp() + duration;
+    db.snooze_vahti(vahti.id, until).await?;
+
+    let settings = UserSettings::fetch(&db, userid as i64, delivery_method).await;
+    Ok(format!(
+        "Vahti snoozed until {}",
+        settings.format_time(until)
+    ))
+}
+
+/// Fetches the items of a group of Vahtis sharing the same url once
+/// and then distributes them to each of the Vahtis
+pub(crate) async fn update_group<V: Vahti>(
+    db: &Database,
+    ihs: ItemHistoryStorage,
+    vahtis: Vec<DbVahti>,
+) -> Vec<VahtiItem> {
+    let Some(fst) = vahtis.first() else {
+        return vec![];
+    };
+
+    // NOTE: Already seen items are needed too, in order to notice price drops
+    let items = match V::from_db(fst.clone()) {
+        Ok(v) => v.fetch_items(0).await,
+        Err(e) => Err(e),
+    };
+
+    let items = match items {
+        Ok(items) => items,
+        Err(e) => {
+            error!("Failed to fetch {}: {}", fst.url, e);
+            if let Err(e) = db.set_vahti_error(&fst.url, Some(e.to_string())).await {
+                error!("Failed to store the error of {}: {}", fst.url, e);
+            }
+            return vec![];
+        }
+    };
+
+    if vahtis.iter().any(|v| v.last_error.is_some()) {
+        if let Err(e) = db.set_vahti_error(&fst.url, None).await {
+            error!("Failed to clear the error of {}: {}", fst.url, e);
+        }
+    }
+
+    let mut ret = vec![];
+    for v in vahtis {
+        let Ok(mut v) = V::from_db(v) else {
+            continue;
+        };
+
+        ret.extend(v.update(db, ihs.clone(), &items).await.unwrap_or_default());
+    }
+    ret
+}
+
 impl Torimies {
     pub async fn update_all_vahtis(&mut self) -> Result<(), Error> {
-        let vahtis = self.database.fetch_all_vahtis().await?;
+        self.database
+            .purge_seen_items(chrono::Local::now().timestamp() - *crate::ITEM_HISTORY_RETENTION)
+            .await?;
+        self.database
+            .resume_snoozed_vahtis(chrono::Local::now().timestamp())
+            .await?;
+        let vahtis = self.database.fetch_all_vahtis_group().await?;
         self.update_vahtis(vahtis).await?;
         Ok(())
     }
 
-    pub async fn update_vahtis(&mut self, vahtis: Vec<DbVahti>) -> Result<(), Error> {
-        info!("Updating {} vahtis", vahtis.len());
+    pub async fn update_vahtis(
+        &mut self,
+        vahtis: BTreeMap<String, Vec<DbVahti>>,
+    ) -> Result<(), Error> {
+        info!(
+            "Updating {} vahtis with {} distinct urls",
+            vahtis.values().map(|vs| vs.len()).sum::<usize>(),
+            vahtis.len()
+        );
         let start = std::time::Instant::now();
 
         let ihs = self.itemhistorystorage.clone();
 
+        let db = self.database.clone();
+
         // NOTE: pre-populate ItemHistoryStorage to prevent deadlocks on inserts
         // this must not be done concurrently and must be done while there are
         // no references (mutable or unmutable) into the ihs dashmap
-        vahtis.iter().for_each(|v| {
+        for v in vahtis.values().flatten() {
             if !ihs.contains_key(&(v.user_id as u64, v.delivery_method)) {
+                // NOTE: If db fails, the history starts out empty
+                let ih = db
+                    .fetch_seen_items(v.user_id, v.delivery_method)
+                    .await
+                    .map(ItemHistory::from_items)
+                    .unwrap_or_default();
                 ihs.insert(
                     (v.user_id as u64, v.delivery_method),
-                    Arc::new(Mutex::new(ItemHistory::new())),
+                    Arc::new(Mutex::new(ih)),
                 );
             }
-        });
+        }
 
-        let db = self.database.clone();
-        let dm = self.delivery.clone();
-
-        let items = stream::iter(vahtis.iter().cloned())
-            .map(|v| (v, ihs.clone(), db.clone()))
-            .map(async move |(v, ihs, db)| match v.site_id {
-                #[cfg(feature = "tori")]
-                crate::tori::ID => {
-                    let Ok(mut tv) = ToriVahti::from_db(v) else {
-                        return vec![];
-                    };
-
-                    tv.update(&db, ihs.clone()).await.unwrap_or_default()
-                }
-                #[cfg(feature = "huutonet")]
-                crate::huutonet::ID => {
-                    if let Ok(mut hv) = HuutonetVahti::from_db(v) {
-                        hv.update(&db, ihs.clone()).await.unwrap_or_default()
-                    } else {
-                        vec![]
-                    }
-                }
-                i => panic!("Unsupported site_id {}", i),
-            })
+        // NOTE: The items of Vahtis with a digest are buffered instead of delivered
+        let digests: Arc<HashMap<(String, u64, i32), i32>> = Arc::new(
+            vahtis
+                .values()
+                .flatten()
+                .filter(|v| v.digest.is_some())
+                .map(|v| ((v.url.clone(), v.user_id as u64, v.delivery_method), v.id))
+                .collect(),
+        );
+
+        let items = stream::iter(vahtis.into_values())
+            .map(|vs| (vs, ihs.clone(), db.clone()))
+            .map(
+                async move |(vs, ihs, db)| match vs.first().map(|v| v.site_id) {
+                    #[cfg(feature = "tori")]
+                    Some(crate::tori::ID) => update_group::<ToriVahti>(&db, ihs, vs).await,
+                    #[cfg(feature = "huutonet")]
+                    Some(crate::huutonet::ID) => update_group::<HuutonetVahti>(&db, ihs, vs).await,
+                    Some(i) => panic!("Unsupported site_id {}", i),
+                    None => vec![],
+                },
+            )
             .buffer_unordered(*crate::FUTURES_MAX_BUFFER_SIZE)
             .collect::<Vec<_>>()
             .await;
 
         info!("Recieving items took {}ms", start.elapsed().as_millis());
 
+        // NOTE: The items of digest Vahtis are fanned out once the digest is sent.
+        // If db fails, the items are delivered to the owners of the Vahtis only
+        let destinations =
+            destinations_by_vahti(db.fetch_all_vahti_destinations().await.unwrap_or_default());
+        let items = items
+            .into_iter()
+            .flatten()
+            .flat_map(|i| {
+                if digests.contains_key(&i.digest_key()) {
+                    vec![i]
+                } else {
+                    fan_out(i, &destinations)
+                }
+            })
+            .collect::<Vec<_>>();
+
         let groups: Vec<Vec<VahtiItem>> = items
             .iter()
-            .flatten()
-            .group_by(|v| {
+            .into_group_map_by(|v| {
                 (
                     v.deliver_to.expect("bug: impossible"),
                     v.delivery_method.expect("bug: impossible"),
                 )
             })
-            .into_iter()
-            .map(|(_, g)| g.cloned().unique_by(|v| v.ad_id).collect())
+            .into_values()
+            .map(|g| g.into_iter().cloned().unique_by(|v| v.ad_id).collect())
             .collect();
 
         // False positive, because we actually want to .await the future elsewhere
@@ -183,26 +804,65 @@ impl Torimies {
         stream::iter(
             groups
                 .iter()
-                .map(|v| (v, db.clone()))
-                .map(async move |(v, db)| {
+                .map(|v| (v, db.clone(), digests.clone()))
+                .map(async move |(v, db, digests)| {
                     let mut v = v.clone();
 
                     if let Some(fst) = v.first() {
                         // NOTE: If db fails, blacklisted sellers are not filtered out
                         if let Ok(bl) = db
-                            .fetch_user_blacklist(fst.deliver_to.expect("bug: impossible") as i64)
+                            .fetch_shared_blacklist(
+                                fst.deliver_to.expect("bug: impossible") as i64,
+                                fst.delivery_method.expect("bug: impossible"),
+                            )
                             .await
                         {
                             v.retain(|i| !bl.contains(&(i.seller_id, i.site_id)));
                         }
                     }
+
+                    let (buffered, v): (Vec<_>, Vec<_>) = v
+                        .into_iter()
+                        .partition(|i| digests.contains_key(&i.digest_key()));
+                    for (vahti_id, items) in buffered
+                        .into_iter()
+                        .into_group_map_by(|i| digests[&i.digest_key()])
+                    {
+                        let items = items
+                            .iter()
+                            .filter_map(|i| serde_json::to_string(i).ok())
+                            .collect();
+                        if let Err(e) = db.add_digest_items(vahti_id, items).await {
+                            error!("Failed to buffer items for Vahti {}: {}", vahti_id, e);
+                        }
+                    }
+
+                    // NOTE: During the quiet hours of the user the items are held
+                    // and delivered once the quiet hours are over
+                    if let Some(fst) = v.first() {
+                        let userid = fst.deliver_to.expect("bug: impossible") as i64;
+                        let delivery_method = fst.delivery_method.expect("bug: impossible");
+                        let settings = UserSettings::fetch(&db, userid, delivery_method).await;
+                        if settings.is_quiet(chrono::Utc::now().timestamp()) {
+                            let items = v
+                                .iter()
+                                .filter_map(|i| serde_json::to_string(i).ok())
+                                .collect();
+                            match db.add_held_items(userid, delivery_method, items).await {
+                                Ok(_) => return vec![],
+                                Err(e) => error!("Failed to hold items for {}: {}", userid, e),
+                            }
+                        }
+                    }
                     v
                 })
-                .map(|v| (v, dm.clone())),
+                .map(|v| (v, db.clone())),
         )
-        .then(|(v, dm)| async move { perform_delivery(dm.clone(), v.await) })
-        .for_each_concurrent(*crate::FUTURES_MAX_BUFFER_SIZE, |d| async move {
-            d.await.ok();
+        .then(|(v, db)| async move { perform_delivery(&db, v.await).await })
+        .for_each_concurrent(*crate::FUTURES_MAX_BUFFER_SIZE, |r| async move {
+            if let Err(e) = r {
+                error!("Failed to queue items for delivery: {}", e);
+            }
         })
         .await;
 